@@ -0,0 +1,11 @@
+// The default post-process shader for the tiles, which passes the color through unchanged.
+// Copy this file to write your own: keep the import path and the signature of `post_process`,
+// then load it from the "Post-Process" window.
+
+#define_import_path diamond_square::post_process
+
+// `color` is the tile color after the terrain texture and tile effects, `uv` the position on the
+// tile (0-1) and `world_position` the fragment's position in the world.
+fn post_process(color: vec4<f32>, uv: vec2<f32>, world_position: vec4<f32>) -> vec4<f32> {
+    return color;
+}
@@ -0,0 +1,98 @@
+// Fragment shader for the tile material: the standard terrain texture, multiplied by a tiling
+// detail texture that fades out with distance from the camera, with animated ripples on the
+// water cells. With POST_PROCESS defined the user's post-process shader runs over the result.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+    mesh_view_bindings::{view, globals},
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+    pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+}
+#endif
+
+#ifdef POST_PROCESS
+#import diamond_square::post_process::post_process
+#endif
+
+struct TileExtension {
+    detail_strength: f32,
+    fade_distance: f32,
+    detail_tiling: f32,
+    water_strength: f32,
+    water_speed: f32,
+    water_tiling: f32,
+}
+
+@group(2) @binding(100) var<uniform> tile_extension: TileExtension;
+@group(2) @binding(101) var detail_texture: texture_2d<f32>;
+@group(2) @binding(102) var detail_sampler: sampler;
+@group(2) @binding(103) var water_mask: texture_2d<f32>;
+@group(2) @binding(104) var water_mask_sampler: sampler;
+@group(2) @binding(105) var water_normals: texture_2d<f32>;
+@group(2) @binding(106) var water_normals_sampler: sampler;
+
+// Where the ripples are lit from, matching the default baked light from the north-west.
+const WATER_LIGHT: vec3<f32> = vec3<f32>(-0.5, 0.5, 0.707);
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    var color = pbr_input.material.base_color.rgb;
+
+    // Mapped in world space so the detail lines up across tiles of every clipmap level.
+    let detail = textureSample(
+        detail_texture,
+        detail_sampler,
+        in.world_position.xy * tile_extension.detail_tiling,
+    ).r;
+    let distance = distance(view.world_position, in.world_position.xyz);
+    let fade = tile_extension.detail_strength
+        * (1.0 - smoothstep(0.0, tile_extension.fade_distance, distance));
+    // Mid gray detail leaves the color as it was.
+    color *= mix(1.0, detail * 2.0, fade);
+
+    // Two copies of the normal map drifting in different directions make the waves shift
+    // instead of just sliding along.
+    let water = textureSample(water_mask, water_mask_sampler, in.uv).r
+        * tile_extension.water_strength;
+    if water > 0.0 {
+        let uv = in.world_position.xy * tile_extension.water_tiling;
+        let offset = globals.time * tile_extension.water_speed * tile_extension.water_tiling;
+        let first = textureSample(water_normals, water_normals_sampler, uv + vec2(offset, offset * 0.5));
+        let second = textureSample(water_normals, water_normals_sampler, uv * 1.3 - vec2(offset * 0.7, -offset * 0.3));
+        let normal = normalize(first.xyz + second.xyz - 1.0);
+        let shade = 0.6 + 0.6 * max(dot(normal, normalize(WATER_LIGHT)), 0.0);
+        color *= mix(1.0, shade, water);
+    }
+
+    pbr_input.material.base_color = vec4(color, pbr_input.material.base_color.a);
+#ifdef POST_PROCESS
+    pbr_input.material.base_color = post_process(pbr_input.material.base_color, in.uv, in.world_position);
+#endif
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        out.color = apply_pbr_lighting(pbr_input);
+    } else {
+        out.color = pbr_input.material.base_color;
+    }
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
@@ -0,0 +1,112 @@
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+use crate::{
+    coloring::WaterLevel,
+    terrain3d::{self, TerrainMesh},
+    Tile, TileHeights,
+};
+
+/// Maps the tile textures so every height sample sits exactly where
+/// [`Position::cell_to_world`](crate::Position::cell_to_world) puts it.
+///
+/// A texture with `n` samples stretched over a quad by the default UVs puts texel `i`'s center at
+/// `(i + 0.5) / n` across the quad, but the samples are `1 / (n - 1)` apart in the world with the
+/// first and last exactly on the tile's edges. The shared edge samples of neighboring tiles then
+/// sit half a texel inside each tile instead of on the boundary between them. With alignment on,
+/// the UVs run from the center of the first texel to the center of the last, so quad edge `0`
+/// shows sample `0` and quad edge `1` shows sample `n - 1`.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct SubpixelAlignment(pub bool);
+
+impl Default for SubpixelAlignment {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// The unit quad for a tile whose texture is `image_size` texels per side.
+///
+/// The quad's tangents point along +X with a +Y bitangent, so normal maps on the tiles are in
+/// world space, see [`BumpMapped`](crate::bump::BumpMapped).
+pub fn tile_mesh(image_size: usize, aligned: bool) -> Mesh {
+    let mut mesh = Mesh::from(Rectangle::new(1.0, 1.0));
+    let tangents = vec![[1.0, 0.0, 0.0, 1.0]; mesh.count_vertices()];
+    mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+    if !aligned {
+        return mesh;
+    }
+
+    let texels = image_size as f32;
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        for uv in uvs.iter_mut().flatten() {
+            *uv = (0.5 + *uv * (texels - 1.0)) / texels;
+        }
+    }
+    mesh
+}
+
+/// Rebuilds the quads of new tiles, and of every tile when the alignment is toggled. Displaced
+/// tiles are also rebuilt whenever their heights or the water level change.
+pub fn update_tile_meshes(
+    alignment: Res<SubpixelAlignment>,
+    terrain: Res<TerrainMesh>,
+    water_level: Res<WaterLevel>,
+    tiles: Query<(&Handle<Mesh>, Ref<TileHeights>, Ref<Tile>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let displaced_changed = terrain.enabled && water_level.is_changed();
+    for (handle, heights, tile) in tiles.iter() {
+        let rebuild = alignment.is_changed()
+            || terrain.is_changed()
+            || displaced_changed
+            || tile.is_added()
+            || (terrain.enabled && heights.is_changed());
+        if !rebuild {
+            continue;
+        }
+        let Some(mesh) = meshes.get_mut(handle) else {
+            continue;
+        };
+        *mesh = if terrain.enabled {
+            terrain3d::heightmap_to_mesh(
+                &heights.0,
+                terrain.vertical_scale,
+                water_level.0,
+                alignment.0,
+            )
+        } else {
+            tile_mesh(heights.0.len(), alignment.0)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uvs(mesh: &Mesh) -> Vec<[f32; 2]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => uvs.clone(),
+            _ => panic!("tile mesh has no UVs"),
+        }
+    }
+
+    #[test]
+    fn aligned_uvs_sit_on_the_edge_texel_centers() {
+        let n = 65;
+        let (first, last) = (0.5 / n as f32, (n as f32 - 0.5) / n as f32);
+        for uv in uvs(&tile_mesh(n, true)).into_iter().flatten() {
+            assert!(
+                (uv - first).abs() < 1e-6 || (uv - last).abs() < 1e-6,
+                "{uv} is not on an edge texel center"
+            );
+        }
+    }
+
+    #[test]
+    fn unaligned_uvs_span_the_whole_texture() {
+        for uv in uvs(&tile_mesh(65, false)).into_iter().flatten() {
+            assert!(uv == 0.0 || uv == 1.0, "{uv} is not on the texture edge");
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+use image::{Rgba, RgbaImage};
+
+use crate::coloring::logistic;
+
+/// Height of the strip added below the map for the scale bar, in pixels.
+const SCALE_BAR_MARGIN: u32 = 28;
+/// Width of the strip added right of the map for the legend, in pixels.
+const LEGEND_MARGIN: u32 = 56;
+/// Number of swatches in the legend.
+const LEGEND_STEPS: usize = 8;
+/// Size of a font pixel, in image pixels.
+const TEXT_SCALE: u32 = 2;
+
+const BACKGROUND: Rgba<u8> = Rgba([0xFF, 0xFF, 0xFF, 0xFF]);
+const INK: Rgba<u8> = Rgba([0, 0, 0, 0xFF]);
+
+/// What is drawn around an exported map so it can be read on its own.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct MapAnnotations {
+    /// Real-world width of one cell, in meters.
+    pub cell_size_m: f32,
+    /// A scale bar in a strip below the map.
+    pub show_scale_bar: bool,
+    /// Height swatches in a strip right of the map.
+    pub show_legend: bool,
+}
+
+impl Default for MapAnnotations {
+    fn default() -> Self {
+        Self {
+            cell_size_m: 30.0,
+            show_scale_bar: false,
+            show_legend: false,
+        }
+    }
+}
+
+impl MapAnnotations {
+    /// The size of the annotated image for a `width` by `height` map.
+    pub fn annotated_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            width + if self.show_legend { LEGEND_MARGIN } else { 0 },
+            height
+                + if self.show_scale_bar {
+                    SCALE_BAR_MARGIN
+                } else {
+                    0
+                },
+        )
+    }
+}
+
+/// 3 by 5 pixel glyphs, one row per byte with the lowest 3 bits used.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'K' | 'k' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'M' | 'm' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        _ => [0; 5],
+    }
+}
+
+fn fill_rect(
+    image: &mut RgbaImage,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+    color: Rgba<u8>,
+) {
+    for px in x..(x + width).min(image.width()) {
+        for py in y..(y + height).min(image.height()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Writes `text` with its top left corner at `(x, y)`.
+fn draw_text(image: &mut RgbaImage, (x, y): (u32, u32), text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * 4 * TEXT_SCALE;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    let pixel = (left + column * TEXT_SCALE, y + row as u32 * TEXT_SCALE);
+                    fill_rect(image, pixel, (TEXT_SCALE, TEXT_SCALE), INK);
+                }
+            }
+        }
+    }
+}
+
+/// The longest round length (1, 2 or 5 times a power of ten) of at most `max_m` meters.
+fn nice_length(max_m: f32) -> f32 {
+    let magnitude = 10f32.powf(max_m.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|&length| length <= max_m)
+        .unwrap_or(magnitude)
+}
+
+fn format_distance(meters: f32) -> String {
+    if meters >= 1000.0 {
+        format!("{}KM", meters / 1000.0)
+    } else {
+        format!("{}M", meters)
+    }
+}
+
+/// Frames an exported map with the enabled annotations. The legend swatches take the average
+/// color of the map's own cells in each height band, so they match whatever coloring was used;
+/// bands no cell falls into are left out. Labels are heights after the logistic curve.
+pub fn annotate(map: &RgbaImage, heights: &[Vec<f32>], annotations: &MapAnnotations) -> RgbaImage {
+    let (width, height) = annotations.annotated_size(map.width(), map.height());
+    let mut image = RgbaImage::from_pixel(width, height, BACKGROUND);
+    image::imageops::replace(&mut image, map, 0, 0);
+
+    if annotations.show_scale_bar {
+        // Aim for a bar about a third of the map wide, split into four alternating segments.
+        let meters_per_pixel = annotations.cell_size_m.max(f32::EPSILON);
+        let length_m = nice_length(map.width() as f32 / 3.0 * meters_per_pixel);
+        let bar = ((length_m / meters_per_pixel).round() as u32).max(4);
+        let (left, top) = (4, map.height() + 4);
+        for segment in 0..4 {
+            let color = if segment % 2 == 0 { INK } else { BACKGROUND };
+            let x = left + segment * bar / 4;
+            fill_rect(&mut image, (x, top), (bar / 4, 6), color);
+        }
+        // Outline, so the white segments still show.
+        fill_rect(&mut image, (left, top), (bar, 1), INK);
+        fill_rect(&mut image, (left, top + 5), (bar, 1), INK);
+        fill_rect(&mut image, (left + bar - 1, top), (1, 6), INK);
+        draw_text(&mut image, (left, top + 10), &format_distance(length_m));
+    }
+
+    if annotations.show_legend {
+        let mut sums = [[0u64; 3]; LEGEND_STEPS];
+        let mut counts = [0u64; LEGEND_STEPS];
+        for (x, row) in heights.iter().enumerate() {
+            for (y, &f) in row.iter().enumerate() {
+                let band = ((logistic(f) * LEGEND_STEPS as f32) as usize).min(LEGEND_STEPS - 1);
+                let pixel = map.get_pixel(y as u32, x as u32);
+                for channel in 0..3 {
+                    sums[band][channel] += pixel[channel] as u64;
+                }
+                counts[band] += 1;
+            }
+        }
+
+        let swatch = (map.height() / LEGEND_STEPS as u32).clamp(1, 20);
+        let left = map.width() + 4;
+        // Highest band at the top, like the terrain it stands for.
+        let bands = (0..LEGEND_STEPS).rev().filter(|&band| counts[band] > 0);
+        for (slot, band) in bands.enumerate() {
+            let [r, g, b] = sums[band].map(|sum| (sum / counts[band]) as u8);
+            let top = 4 + slot as u32 * (swatch + 2);
+            fill_rect(&mut image, (left, top), (12, swatch), Rgba([r, g, b, 0xFF]));
+            let label = format!("{:.2}", band as f32 / LEGEND_STEPS as f32);
+            draw_text(&mut image, (left + 16, top), &label);
+        }
+    }
+
+    image
+}
+
+pub fn annotations_ui(ui: &mut egui::Ui, annotations: &mut MapAnnotations) {
+    ui.checkbox(&mut annotations.show_scale_bar, "Scale Bar");
+    ui.checkbox(&mut annotations.show_legend, "Elevation Legend");
+    ui.add(
+        egui::Slider::new(&mut annotations.cell_size_m, 0.1..=10_000.0)
+            .logarithmic(true)
+            .prefix("Cell Size: ")
+            .suffix(" m"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotated_exports_grow_by_the_margins() {
+        let size = 33;
+        let map = RgbaImage::from_pixel(size, size, Rgba([0x40, 0x80, 0xC0, 0xFF]));
+        let heights = vec![vec![0.0; size as usize]; size as usize];
+
+        for (show_scale_bar, show_legend) in
+            [(false, false), (true, false), (false, true), (true, true)]
+        {
+            let annotations = MapAnnotations {
+                show_scale_bar,
+                show_legend,
+                ..default()
+            };
+            let annotated = annotate(&map, &heights, &annotations);
+
+            let width = size + if show_legend { LEGEND_MARGIN } else { 0 };
+            let height = size + if show_scale_bar { SCALE_BAR_MARGIN } else { 0 };
+            assert_eq!(annotated.dimensions(), (width, height));
+            assert_eq!(
+                annotated.dimensions(),
+                annotations.annotated_size(size, size)
+            );
+            // The map itself is copied unchanged into the top left corner.
+            assert_eq!(
+                annotated.get_pixel(size - 1, size - 1),
+                map.get_pixel(size - 1, size - 1)
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Cursor, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use diamond_square::DiamondSquare;
+
+use crate::{
+    coloring::{colorize, ColorSettings},
+    export::{self, Sidecar},
+    generate_heightmap,
+    stats::TileStats,
+    GenSettings, Position,
+};
+
+/// `--seeds <file> [--size <n>] [--outdir <dir>] [--raw]`: writes one colored PNG per seed
+/// without opening a window. With `--raw` the raw heights are written instead, as
+/// little-endian `f32`s row after row.
+pub struct BatchArgs {
+    pub seeds: PathBuf,
+    pub size: usize,
+    pub outdir: PathBuf,
+    pub raw: bool,
+}
+
+impl BatchArgs {
+    /// `None` unless `--seeds` was given, in which case the window isn't opened at all.
+    pub fn parse() -> Option<Result<Self, String>> {
+        let args: Vec<String> = std::env::args().collect();
+        let value = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|i| args.get(i + 1))
+        };
+
+        let seeds = value("--seeds")?;
+        let size = match value("--size").map(|size| size.parse::<usize>()) {
+            None => GenSettings::default().image_size(),
+            Some(Ok(size)) if size >= 3 && (size - 1).is_power_of_two() => size,
+            Some(_) => return Some(Err("--size must be 2^n + 1, e.g. 513".into())),
+        };
+
+        Some(Ok(Self {
+            seeds: seeds.into(),
+            size,
+            outdir: value("--outdir").map_or("out".into(), PathBuf::from),
+            raw: args.iter().any(|arg| arg == "--raw"),
+        }))
+    }
+}
+
+/// Generates every seed in the file in parallel, printing one line per finished seed. A line
+/// that isn't a seed or a seed that fails is reported and skipped. Returns how many failed.
+pub fn run(args: &BatchArgs) -> Result<usize, Box<dyn Error>> {
+    let mut seeds = Vec::new();
+    let mut unparsed = 0;
+    let file = fs::read_to_string(&args.seeds)?;
+    for (number, line) in file.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse::<isize>() {
+            Ok(seed) => seeds.push(seed),
+            Err(err) => {
+                unparsed += 1;
+                println!("line {}: {:?} is not a seed: {}", number + 1, line, err);
+            }
+        }
+    }
+    fs::create_dir_all(&args.outdir)?;
+
+    // Workers take the next seed until none are left.
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    thread::scope(|scope| {
+        for _ in 0..workers.min(seeds.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&seed) = seeds.get(index) else {
+                    break;
+                };
+
+                let result = if args.raw {
+                    let path = args.outdir.join(format!("{}.f32", seed));
+                    generate_raw(seed, args.size, &path).map(|()| path)
+                } else {
+                    let path = args.outdir.join(format!("{}.png", seed));
+                    generate_png(seed, args.size, &path).map(|()| path)
+                };
+                match result {
+                    Ok(path) => println!("[{}/{}] {}", index + 1, seeds.len(), path.display()),
+                    Err(err) => {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                        println!(
+                            "[{}/{}] seed {} failed: {}",
+                            index + 1,
+                            seeds.len(),
+                            seed,
+                            err
+                        );
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(unparsed + failed.into_inner())
+}
+
+/// The sidecar of a batch tile, which is always the one at the origin.
+fn sidecar<'a>(settings: &'a GenSettings, heights: &[Vec<f32>]) -> Sidecar<'a> {
+    let position = Position((0, 0));
+    Sidecar {
+        settings,
+        position,
+        stats: TileStats::compute(heights, position, &ColorSettings::default()),
+    }
+}
+
+/// The same tile the window shows for `seed` with the default settings, next to its sidecar.
+fn generate_png(seed: isize, size: usize, path: &Path) -> Result<(), Box<dyn Error>> {
+    let settings = GenSettings {
+        seed,
+        ..GenSettings::default()
+    };
+    let position = Position((0, 0));
+    let heights = generate_heightmap(position, settings.roughness, seed, size);
+    let png = encode_png(&heights, position)?;
+    export::save_with_sidecar(path, &sidecar(&settings, &heights), |path| {
+        fs::write(path, png)?;
+        Ok(())
+    })
+}
+
+/// Generates a tile and colors it with the default coloring, encoded as PNG. This is the whole
+/// headless generation path, shared by the batch runs and the tile server.
+pub fn render_png(
+    position: Position,
+    roughness: f32,
+    seed: isize,
+    size: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let heights = generate_heightmap(position, roughness, seed, size);
+    encode_png(&heights, position)
+}
+
+/// Colors `heights` with the default coloring and encodes them as PNG.
+fn encode_png(heights: &[Vec<f32>], position: Position) -> Result<Vec<u8>, Box<dyn Error>> {
+    let size = heights.len() as u32;
+    let rgba = colorize(heights, position, &ColorSettings::default());
+    let image = image::RgbaImage::from_raw(size, size, rgba).ok_or("image buffer size mismatch")?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+/// The raw heights of the same tile, written row by row straight from the generator, next to
+/// their sidecar.
+fn generate_raw(seed: isize, size: usize, path: &Path) -> Result<(), Box<dyn Error>> {
+    let settings = GenSettings {
+        seed,
+        ..GenSettings::default()
+    };
+    let heightmap = DiamondSquare::new(size)
+        .seed(seed)
+        .roughness(settings.roughness)
+        .generate();
+
+    let sidecar = sidecar(&settings, &heightmap.to_rows());
+    export::save_with_sidecar(path, &sidecar, |path| {
+        let mut file = BufWriter::new(File::create(path)?);
+        for row in heightmap.rows() {
+            for height in row {
+                file.write_all(&height.to_le_bytes())?;
+            }
+        }
+        file.flush()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_seed_gets_its_own_file_and_bad_lines_are_skipped() {
+        let dir = std::env::temp_dir().join("diamond-square-batch");
+        fs::create_dir_all(&dir).unwrap();
+        let seeds = dir.join("seeds.txt");
+        fs::write(&seeds, "1\nnot a seed\n\n2\n").unwrap();
+        let args = BatchArgs {
+            seeds,
+            size: 17,
+            outdir: dir.join("out"),
+            raw: false,
+        };
+
+        assert_eq!(run(&args).unwrap(), 1);
+
+        let [one, two] = ["1", "2"].map(|seed| args.outdir.join(seed).with_extension("png"));
+        assert_ne!(fs::read(&one).unwrap(), fs::read(&two).unwrap());
+        assert!(one.with_extension("json").exists());
+        assert!(two.with_extension("json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
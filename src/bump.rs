@@ -0,0 +1,121 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use bevy_inspector_egui::egui;
+
+use crate::{
+    lighting::Lighting, material::TileMaterial, slope, terrain3d::TerrainMesh, TileHeights,
+};
+
+/// Lights the flat tile quads per pixel through a normal map built from their heights, so the
+/// relief catches a real light instead of sitting unlit. Far cheaper than displacing geometry.
+/// The normals use the [`Lighting`] relief and the light follows its first light.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Copy)]
+pub struct BumpMapped(pub bool);
+
+/// The light the bump mapped tiles are lit by.
+#[derive(Component)]
+pub struct BumpLight;
+
+pub fn spawn_bump_light(mut commands: Commands) {
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: light_consts::lux::OVERCAST_DAY,
+                ..default()
+            },
+            ..default()
+        },
+        BumpLight,
+    ));
+}
+
+/// A normal map of the tile, laid out like the tile texture.
+///
+/// The tile quads get a tangent frame equal to the world axes (see
+/// [`tile_mesh`](crate::alignment::tile_mesh)), so the world space normals go in as they are.
+fn normal_map(heights: &[Vec<f32>], relief: f32) -> Image {
+    let size = heights.len() as u32;
+    let data = slope::normals(heights, relief)
+        .into_iter()
+        .flatten()
+        .flat_map(|normal| {
+            let [r, g, b] = (normal * 0.5 + 0.5).to_array().map(|c| (c * 255.0) as u8);
+            [r, g, b, 0xFF]
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        // Normal maps hold vectors, not colors, so they must not be treated as sRGB.
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Keeps every tile's normal map, its lit or unlit shading and the light in sync with
+/// [`BumpMapped`] and [`Lighting`]. Displaced tiles are always lit, by their geometry alone.
+pub fn update_bump_mapping(
+    bump: Res<BumpMapped>,
+    lighting: Res<Lighting>,
+    terrain: Res<TerrainMesh>,
+    tiles: Query<(Ref<TileHeights>, &Handle<TileMaterial>)>,
+    mut lights: Query<(&mut Transform, &mut DirectionalLight), With<BumpLight>>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if bump.is_changed() || lighting.is_changed() {
+        let light = lighting.lights.first().copied().unwrap_or_default();
+        for (mut transform, mut directional) in lights.iter_mut() {
+            // Directional lights shine along their forward axis, away from the light.
+            transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, -light.direction());
+            directional.color = Color::rgb(light.color[0], light.color[1], light.color[2]);
+            directional.illuminance = light_consts::lux::OVERCAST_DAY * light.intensity;
+        }
+    }
+
+    for (heights, handle) in tiles.iter() {
+        let rebuild = bump.is_changed()
+            || lighting.is_changed()
+            || terrain.is_changed()
+            || heights.is_changed();
+        if !rebuild {
+            continue;
+        }
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+
+        let base = &mut material.base;
+        base.unlit = !bump.0 && !terrain.enabled;
+        if !bump.0 || terrain.enabled {
+            base.normal_map_texture = None;
+            continue;
+        }
+
+        let normals = normal_map(&heights.0, lighting.relief);
+        match base
+            .normal_map_texture
+            .as_ref()
+            .and_then(|handle| images.get_mut(handle))
+        {
+            Some(image) => *image = normals,
+            None => base.normal_map_texture = Some(images.add(normals)),
+        }
+    }
+}
+
+pub fn bump_ui(ui: &mut egui::Ui, bump: &mut BumpMapped) {
+    ui.checkbox(&mut bump.0, "Bump Mapping")
+        .on_hover_text("Lights the tiles per pixel with the first light above.");
+}
@@ -0,0 +1,329 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::{coloring::ColorSettings, palette::ColorPalette};
+
+/// Keeps the colored textures of recently used coloring settings, so going back to settings
+/// from a moment ago (like undoing a palette change) puts the old colors back instead of
+/// coloring every tile again.
+#[derive(Resource, Debug)]
+pub struct ColorCache {
+    pub enabled: bool,
+    /// How many settings the textures are kept for. The least recently used ones are dropped
+    /// first.
+    pub capacity: usize,
+    /// Least recently used first.
+    entries: VecDeque<CacheEntry>,
+    /// The settings [`recolor_tiles`](crate::coloring::recolor_tiles) colored each tile's
+    /// texture with. Tiles colored anywhere else aren't in here, since nothing says which
+    /// settings their texture matches.
+    colored: HashMap<Entity, u64>,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    settings: u64,
+    tile: Entity,
+    rgba: Vec<u8>,
+}
+
+impl Default for ColorCache {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: 4,
+            entries: VecDeque::new(),
+            colored: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
+/// Identifies coloring settings. Floats are hashed by their bits and the lookup table by its
+/// colors.
+pub fn settings_hash(settings: &ColorSettings) -> u64 {
+    let ColorSettings {
+        output,
+        water_level,
+        coastline,
+        snow,
+        effective_elevation,
+        dither,
+        lighting,
+        climate,
+        strata,
+        walkability,
+        viewshed,
+        crossfade,
+        lut,
+    } = settings;
+
+    let mut hasher = DefaultHasher::new();
+    let h = &mut hasher;
+    mem::discriminant(output).hash(h);
+    hash_floats(&[water_level.0], h);
+    (coastline.enabled, coastline.iterations).hash(h);
+    snow.enabled.hash(h);
+    hash_floats(&[snow.snow_line, snow.max_slope], h);
+    effective_elevation.enabled.hash(h);
+    hash_floats(&[effective_elevation.slope_weight], h);
+    mem::discriminant(dither).hash(h);
+    lighting.enabled.hash(h);
+    hash_floats(&[lighting.ambient, lighting.relief], h);
+    for light in &lighting.lights {
+        hash_floats(&[light.azimuth, light.elevation, light.intensity], h);
+        hash_floats(&light.color, h);
+    }
+    hash_floats(
+        &[
+            climate.equator_temperature,
+            climate.pole_temperature,
+            climate.latitude_span,
+            climate.lapse_rate,
+            climate.moisture_reach,
+        ],
+        h,
+    );
+    (strata.enabled, strata.band_count).hash(h);
+    hash_floats(&[strata.color_variation, strata.min_slope], h);
+    walkability.enabled.hash(h);
+    hash_floats(&[walkability.max_slope], h);
+    (viewshed.enabled, viewshed.observer).hash(h);
+    hash_floats(&[viewshed.observer_height], h);
+    // Autoplay and its period only move `t`, which is hashed itself.
+    crossfade.enabled.hash(h);
+    hash_palette(&crossfade.a, h);
+    hash_palette(&crossfade.b, h);
+    hash_floats(&[crossfade.t], h);
+    lut.hash(h);
+    hasher.finish()
+}
+
+fn hash_floats(values: &[f32], h: &mut impl Hasher) {
+    for value in values {
+        value.to_bits().hash(h);
+    }
+}
+
+fn hash_palette(palette: &ColorPalette, h: &mut impl Hasher) {
+    let ColorPalette {
+        name,
+        water,
+        lowland,
+        rock,
+        peak,
+    } = palette;
+    (name, water, lowland, rock, peak).hash(h);
+}
+
+impl ColorCache {
+    /// Records that `tile`'s texture now holds the colors of the settings hashed to `settings`.
+    pub fn colored(&mut self, tile: Entity, settings: u64) {
+        self.colored.insert(tile, settings);
+    }
+
+    /// Keeps `rgba`, the texture `tile` is being recolored away from, under the settings it was
+    /// colored with. Does nothing if it wasn't colored by
+    /// [`recolor_tiles`](crate::coloring::recolor_tiles).
+    pub fn store(&mut self, tile: Entity, rgba: Vec<u8>) {
+        let Some(settings) = self.colored.remove(&tile) else {
+            return;
+        };
+        if !self.enabled {
+            return;
+        }
+
+        self.entries.push_back(CacheEntry {
+            settings,
+            tile,
+            rgba,
+        });
+
+        while self.settings_kept() > self.capacity {
+            let Some(oldest) = self.entries.front().map(|entry| entry.settings) else {
+                break;
+            };
+            self.entries.retain(|entry| entry.settings != oldest);
+        }
+    }
+
+    /// How many different settings have textures in the cache.
+    fn settings_kept(&self) -> usize {
+        let mut settings: Vec<u64> = self.entries.iter().map(|entry| entry.settings).collect();
+        settings.sort_unstable();
+        settings.dedup();
+        settings.len()
+    }
+
+    /// Takes `tile`'s texture colored with `settings` out of the cache, counting the hit or
+    /// miss.
+    pub fn take(&mut self, settings: u64, tile: Entity) -> Option<Vec<u8>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.settings == settings && entry.tile == tile);
+
+        match index.and_then(|index| self.entries.remove(index)) {
+            Some(entry) => {
+                self.hits += 1;
+                Some(entry.rgba)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Forgets every texture of `tile`, e.g. because its heights changed or it was despawned.
+    pub fn evict(&mut self, tile: Entity) {
+        self.entries.retain(|entry| entry.tile != tile);
+        self.colored.remove(&tile);
+    }
+}
+
+/// The cache section of the statistics window.
+pub fn cache_ui(ui: &mut egui::Ui, cache: &mut ColorCache) {
+    ui.checkbox(&mut cache.enabled, "Cache Colored Tiles");
+    ui.add(egui::Slider::new(&mut cache.capacity, 1..=16).prefix("Settings Kept: "));
+    ui.label(format!(
+        "{} cached, {} hits, {} misses",
+        cache.entries.len(),
+        cache.hits,
+        cache.misses
+    ));
+    if !cache.enabled {
+        cache.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coloring::OutputMode, dither::DitherMode, lighting::Light, lut::Lut, Position};
+
+    /// Colors `tile` with `settings` the way `recolor_tiles` does, returning the texture.
+    fn recolor(cache: &mut ColorCache, tile: Entity, texture: &mut Vec<u8>, settings: u64) {
+        let rgba = cache
+            .take(settings, tile)
+            .unwrap_or_else(|| vec![settings as u8; 4]);
+        let old = mem::replace(texture, rgba);
+        cache.store(tile, old);
+        cache.colored(tile, settings);
+    }
+
+    #[test]
+    fn recoloring_with_earlier_settings_is_a_hit() {
+        let before = ColorSettings::default();
+        let mut after = before.clone();
+        after.crossfade.enabled = true;
+        after.crossfade.b = ColorPalette::AUTUMN;
+        let (before, after) = (settings_hash(&before), settings_hash(&after));
+        assert_ne!(before, after);
+
+        let mut cache = ColorCache::default();
+        let tile = Entity::from_raw(1);
+        let mut texture = Vec::new();
+        recolor(&mut cache, tile, &mut texture, before);
+        recolor(&mut cache, tile, &mut texture, after);
+        assert_eq!((cache.hits, cache.misses), (0, 2));
+
+        // Undo.
+        recolor(&mut cache, tile, &mut texture, before);
+        assert_eq!((cache.hits, cache.misses), (1, 2));
+        assert_eq!(texture, vec![before as u8; 4]);
+    }
+
+    #[test]
+    fn textures_colored_elsewhere_are_not_kept() {
+        let mut cache = ColorCache::default();
+        let tile = Entity::from_raw(1);
+        // Colored by whatever spawned the tile, so under unknown settings.
+        let mut texture = vec![0; 4];
+        recolor(&mut cache, tile, &mut texture, 1);
+        assert!(cache.entries.is_empty());
+
+        cache.evict(tile);
+        recolor(&mut cache, tile, &mut texture, 2);
+        recolor(&mut cache, tile, &mut texture, 1);
+        assert_eq!(cache.hits, 0);
+    }
+
+    #[test]
+    fn only_a_few_settings_are_kept() {
+        let mut cache = ColorCache::default();
+        let tile = Entity::from_raw(1);
+        let mut texture = Vec::new();
+        for settings in 0..10 {
+            recolor(&mut cache, tile, &mut texture, settings);
+        }
+        assert_eq!(cache.settings_kept(), cache.capacity);
+    }
+
+    #[test]
+    fn every_setting_changes_the_hash() {
+        // One change per hashed field. The crossfade's autoplay and period aren't hashed, they
+        // only move `t`.
+        let changes: &[fn(&mut ColorSettings)] = &[
+            |s| s.output = OutputMode::Raw,
+            |s| s.water_level.0 -= 0.1,
+            |s| s.coastline.enabled ^= true,
+            |s| s.coastline.iterations += 1,
+            |s| s.snow.enabled ^= true,
+            |s| s.snow.snow_line -= 0.1,
+            |s| s.snow.max_slope += 1.0,
+            |s| s.effective_elevation.enabled ^= true,
+            |s| s.effective_elevation.slope_weight += 0.01,
+            |s| s.dither = DitherMode::Ordered,
+            |s| s.lighting.enabled ^= true,
+            |s| s.lighting.ambient += 0.1,
+            |s| s.lighting.relief += 0.1,
+            |s| s.lighting.lights[0].azimuth += 10.0,
+            |s| s.lighting.lights[0].elevation += 10.0,
+            |s| s.lighting.lights[0].color[0] -= 0.1,
+            |s| s.lighting.lights[0].intensity += 0.1,
+            |s| s.lighting.lights.push(Light::default()),
+            |s| s.climate.equator_temperature += 1.0,
+            |s| s.climate.pole_temperature += 1.0,
+            |s| s.climate.latitude_span += 1.0,
+            |s| s.climate.lapse_rate += 1.0,
+            |s| s.climate.moisture_reach += 0.01,
+            |s| s.strata.enabled ^= true,
+            |s| s.strata.band_count += 1,
+            |s| s.strata.color_variation += 0.1,
+            |s| s.strata.min_slope += 1.0,
+            |s| s.walkability.enabled ^= true,
+            |s| s.walkability.max_slope += 1.0,
+            |s| s.viewshed.enabled ^= true,
+            |s| s.viewshed.observer = Some((Position((0, 0)), (1, 1))),
+            |s| s.viewshed.observer_height += 0.01,
+            |s| s.crossfade.enabled ^= true,
+            |s| s.crossfade.a = ColorPalette::AUTUMN,
+            |s| s.crossfade.b = ColorPalette::NIGHT,
+            |s| s.crossfade.t = 0.5,
+            |s| s.lut = Lut::from_image(&Image::default()),
+        ];
+
+        let settings = ColorSettings::default();
+        for (i, change) in changes.iter().enumerate() {
+            let mut changed = settings.clone();
+            change(&mut changed);
+            assert_ne!(
+                settings_hash(&settings),
+                settings_hash(&changed),
+                "change {} kept the hash",
+                i
+            );
+        }
+        assert_eq!(settings_hash(&settings), settings_hash(&settings.clone()));
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+
+use crate::{coastline, Position};
+
+/// Temperature and moisture used to classify biomes on a Whittaker diagram.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct Climate {
+    /// Sea level temperature on the equator (world y = 0), in °C.
+    pub equator_temperature: f32,
+    /// Sea level temperature at the poles, in °C.
+    pub pole_temperature: f32,
+    /// Distance from the equator to either pole, in tiles.
+    pub latitude_span: f32,
+    /// Cooling per unit of height above the water level, in °C.
+    pub lapse_rate: f32,
+    /// How far moisture carries inland, as a fraction of the tile width.
+    pub moisture_reach: f32,
+}
+
+impl Default for Climate {
+    fn default() -> Self {
+        Self {
+            equator_temperature: 30.0,
+            pole_temperature: -20.0,
+            latitude_span: 8.0,
+            lapse_rate: 40.0,
+            moisture_reach: 0.15,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Biome {
+    Tundra,
+    Taiga,
+    ColdDesert,
+    Shrubland,
+    TemperateForest,
+    TemperateRainforest,
+    SubtropicalDesert,
+    Savanna,
+    TropicalSeasonalForest,
+    TropicalRainforest,
+}
+
+impl Biome {
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Biome::Tundra => [221, 221, 228],
+            Biome::Taiga => [100, 130, 100],
+            Biome::ColdDesert => [200, 190, 160],
+            Biome::Shrubland => [150, 170, 90],
+            Biome::TemperateForest => [60, 130, 60],
+            Biome::TemperateRainforest => [40, 100, 70],
+            Biome::SubtropicalDesert => [230, 200, 130],
+            Biome::Savanna => [190, 180, 80],
+            Biome::TropicalSeasonalForest => [90, 150, 40],
+            Biome::TropicalRainforest => [20, 110, 30],
+        }
+    }
+}
+
+/// Looks up the biome for a temperature (°C) and moisture (0 dry to 1 wet).
+pub fn whittaker_biome(temperature: f32, moisture: f32) -> Biome {
+    match temperature {
+        t if t < -5.0 => Biome::Tundra,
+        t if t < 5.0 => match moisture {
+            m if m < 0.25 => Biome::Tundra,
+            _ => Biome::Taiga,
+        },
+        t if t < 20.0 => match moisture {
+            m if m < 0.2 => Biome::ColdDesert,
+            m if m < 0.45 => Biome::Shrubland,
+            m if m < 0.75 => Biome::TemperateForest,
+            _ => Biome::TemperateRainforest,
+        },
+        _ => match moisture {
+            m if m < 0.25 => Biome::SubtropicalDesert,
+            m if m < 0.5 => Biome::Savanna,
+            m if m < 0.75 => Biome::TropicalSeasonalForest,
+            _ => Biome::TropicalRainforest,
+        },
+    }
+}
+
+/// Temperature of every cell from its latitude and its height above the water level. `heights`
+/// are already through the logistic curve (0-1).
+pub fn temperature_field(
+    heights: &[Vec<f32>],
+    position: Position,
+    water_level: f32,
+    climate: &Climate,
+) -> Vec<Vec<f32>> {
+    let size = heights.len();
+    (0..size)
+        .map(|x| {
+            let latitude = position.cell_to_world(size, (x, 0)).y;
+            let polarity = (latitude.abs() / climate.latitude_span).min(1.0);
+            let sea_level = climate.equator_temperature
+                + (climate.pole_temperature - climate.equator_temperature) * polarity;
+
+            heights[x]
+                .iter()
+                .map(|&f| sea_level - (f - water_level).max(0.0) * climate.lapse_rate)
+                .collect()
+        })
+        .collect()
+}
+
+/// Moisture of every cell: the share of water within reach, so coasts are wet and the interior
+/// of large landmasses dries out.
+pub fn moisture_field(land: &[Vec<bool>], climate: &Climate) -> Vec<Vec<f32>> {
+    let size = land.len();
+    let radius = ((size as f32 * climate.moisture_reach) as usize).max(1);
+
+    let water: Vec<Vec<f32>> = land
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&land| if land { 0.0 } else { 1.0 })
+                .collect()
+        })
+        .collect();
+
+    // Two box blurs approximate a smooth falloff. Doubling makes a coast, which is half water
+    // within reach, fully wet.
+    let blurred = box_blur(&box_blur(&water, radius), radius);
+    blurred
+        .into_iter()
+        .map(|row| row.into_iter().map(|m| (m * 2.0).min(1.0)).collect())
+        .collect()
+}
+
+pub fn biome_field(
+    heights: &[Vec<f32>],
+    position: Position,
+    water_level: f32,
+    climate: &Climate,
+) -> Vec<Vec<Biome>> {
+    let land = coastline::land_mask(heights, water_level);
+    let temperature = temperature_field(heights, position, water_level, climate);
+    let moisture = moisture_field(&land, climate);
+
+    temperature
+        .iter()
+        .zip(&moisture)
+        .map(|(t, m)| {
+            t.iter()
+                .zip(m)
+                .map(|(&t, &m)| whittaker_biome(t, m))
+                .collect()
+        })
+        .collect()
+}
+
+/// Averages every cell with the cells within `radius` along both axes, clamping at the edges.
+fn box_blur(field: &[Vec<f32>], radius: usize) -> Vec<Vec<f32>> {
+    let blur_rows = |field: &[Vec<f32>]| -> Vec<Vec<f32>> {
+        field
+            .iter()
+            .map(|row| {
+                let mut prefix = vec![0.0; row.len() + 1];
+                for (i, value) in row.iter().enumerate() {
+                    prefix[i + 1] = prefix[i] + value;
+                }
+
+                (0..row.len())
+                    .map(|i| {
+                        let (lo, hi) = (i.saturating_sub(radius), (i + radius + 1).min(row.len()));
+                        (prefix[hi] - prefix[lo]) / (hi - lo) as f32
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+
+    transpose(&blur_rows(&transpose(&blur_rows(field))))
+}
+
+fn transpose(field: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    let columns = field.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|y| field.iter().map(|row| row[y]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_corners_of_the_diagram_are_the_extreme_biomes() {
+        assert_eq!(whittaker_biome(-30.0, 0.0), Biome::Tundra);
+        assert_eq!(whittaker_biome(-30.0, 1.0), Biome::Tundra);
+        assert_eq!(whittaker_biome(30.0, 0.0), Biome::SubtropicalDesert);
+        assert_eq!(whittaker_biome(30.0, 1.0), Biome::TropicalRainforest);
+        assert_eq!(whittaker_biome(0.0, 1.0), Biome::Taiga);
+        assert_eq!(whittaker_biome(10.0, 1.0), Biome::TemperateRainforest);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    alignment::SubpixelAlignment, grid::ShowTileGrid, topology::Topology, GenSettings,
+    GenTileEvent, GenTileTask, GenerationEpoch, Position, Tile, TileLevel,
+};
+
+/// Holds the world at several resolutions around the camera. Level 0 is full detail, and every
+/// level after it covers twice the area per tile at the same texture size. Tiles are only
+/// requested or dropped as the camera crosses tile boundaries, so scrolling costs the same no
+/// matter how far the camera has travelled.
+///
+/// Coarse levels are generated on their own rather than downsampled from the finer ones, so
+/// they are an approximation of the terrain beneath them.
+#[derive(Resource)]
+pub struct Clipmap {
+    pub enabled: bool,
+    pub levels: u32,
+    /// How many tiles each level extends from its center tile.
+    pub ring_radius: i32,
+    /// Texture resolution is `2^node_size + 1` for every level.
+    pub node_size: usize,
+    /// The tiles currently requested or loaded for each level.
+    occupied: Vec<HashSet<(i32, i32)>>,
+    was_enabled: bool,
+    built_node_size: usize,
+}
+
+impl Default for Clipmap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            levels: 3,
+            ring_radius: 2,
+            node_size: 6,
+            occupied: Vec::new(),
+            was_enabled: false,
+            built_node_size: 6,
+        }
+    }
+}
+
+impl Clipmap {
+    /// Forgets every cached tile, e.g. after the tiles were despawned for a new seed.
+    pub fn clear(&mut self) {
+        self.occupied.clear();
+    }
+
+    /// Turns streaming off without handing control back to the single generated tile, for when
+    /// the caller is about to place tiles itself.
+    pub fn disable(&mut self) {
+        self.enabled = false;
+        self.was_enabled = false;
+        self.clear();
+    }
+
+    /// The tile indices `level` should hold when the camera is at `center`.
+    fn ring(&self, level: u32, center: Vec2) -> HashSet<(i32, i32)> {
+        let tile_index = |level: u32| {
+            let scale = (1 << level) as f32;
+            // Tile (0, 0) of level 0 is centered on the origin.
+            (
+                ((center.x + 0.5) / scale).floor() as i32,
+                ((center.y + 0.5) / scale).floor() as i32,
+            )
+        };
+
+        let (cx, cy) = tile_index(level);
+        let mut ring = HashSet::new();
+        for x in cx - self.ring_radius..=cx + self.ring_radius {
+            for y in cy - self.ring_radius..=cy + self.ring_radius {
+                ring.insert((x, y));
+            }
+        }
+
+        if level == 0 {
+            return ring;
+        }
+
+        // Leave a hole where the finer level already covers the whole tile.
+        let (fx, fy) = tile_index(level - 1);
+        let inner_min = (fx - self.ring_radius, fy - self.ring_radius);
+        let inner_max = (fx + self.ring_radius + 1, fy + self.ring_radius + 1);
+        ring.retain(|&(x, y)| {
+            let covered = |lo: i32, hi: i32, min: i32, max: i32| min <= lo && hi <= max;
+            !(covered(x * 2, x * 2 + 2, inner_min.0, inner_max.0)
+                && covered(y * 2, y * 2 + 2, inner_min.1, inner_max.1))
+        });
+
+        ring
+    }
+}
+
+pub fn update_clipmap(
+    mut commands: Commands,
+    mut clipmap: ResMut<Clipmap>,
+    mut gentile: EventWriter<GenTileEvent>,
+    settings: Res<GenSettings>,
+    epoch: Res<GenerationEpoch>,
+    camera_query: Query<&Transform, With<Camera>>,
+    tile_query: Query<(Entity, &Position, &TileLevel), Or<(With<Tile>, With<GenTileTask>)>>,
+) {
+    // Switching modes hands the tiles over between the clipmap and the single-tile view. A new
+    // resolution invalidates every cached level the same way.
+    let resized = clipmap.enabled && clipmap.node_size != clipmap.built_node_size;
+    if clipmap.enabled != clipmap.was_enabled || resized {
+        clipmap.was_enabled = clipmap.enabled;
+        clipmap.built_node_size = clipmap.node_size;
+        clipmap.clear();
+
+        for (entity, _, _) in tile_query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        if !clipmap.enabled {
+            gentile.send(GenTileEvent {
+                position: Position((0, 0)),
+                seed: settings.noise_seed(),
+                roughness: settings.roughness,
+                image_size: settings.image_size(),
+                level: 0,
+                rotation: settings.rotation,
+                topology: settings.topology,
+                arithmetic: settings.arithmetic,
+                epoch: epoch.0,
+            });
+        }
+    }
+
+    if !clipmap.enabled {
+        return;
+    }
+
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = camera.translation.truncate();
+
+    let levels = clipmap.levels as usize;
+    clipmap.occupied.resize_with(levels, HashSet::new);
+
+    let rings: Vec<_> = (0..clipmap.levels)
+        .map(|level| clipmap.ring(level, center))
+        .collect();
+
+    // Drop tiles that fell out of their ring, including levels that no longer exist. Tiles still
+    // being generated are cancelled.
+    for (entity, position, level) in tile_query.iter() {
+        let level = level.0 as usize;
+        if level >= levels || !rings[level].contains(&position.0) {
+            commands.entity(entity).despawn();
+            if let Some(occupied) = clipmap.occupied.get_mut(level) {
+                occupied.remove(&position.0);
+            }
+        }
+    }
+
+    // Request the tiles that scrolled into view. Requests that are still queued but already out
+    // of view are forgotten here and despawned above once they arrive.
+    let image_size = 2usize.pow(clipmap.node_size as u32) + 1;
+    for (level, ring) in rings.into_iter().enumerate() {
+        clipmap.occupied[level].retain(|index| ring.contains(index));
+        for index in ring {
+            if clipmap.occupied[level].insert(index) {
+                gentile.send(GenTileEvent {
+                    position: Position(index),
+                    seed: settings.noise_seed(),
+                    roughness: settings.roughness,
+                    image_size,
+                    level: level as u32,
+                    // Streamed tiles have to line up with each other, so they are never rotated
+                    // or wrapped.
+                    rotation: 0,
+                    topology: Topology::Planar,
+                    arithmetic: settings.arithmetic,
+                    epoch: epoch.0,
+                });
+            }
+        }
+    }
+}
+
+pub fn clipmap_ui(
+    mut contexts: EguiContexts,
+    mut clipmap: ResMut<Clipmap>,
+    mut show_grid: ResMut<ShowTileGrid>,
+    mut alignment: ResMut<SubpixelAlignment>,
+) {
+    // Toggling the alignment rebuilds every quad, so only write back real changes.
+    let mut new_alignment = *alignment;
+
+    egui::Window::new("World Streaming")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut clipmap.enabled, "Clipmap");
+            ui.add(egui::Slider::new(&mut clipmap.levels, 1..=6).prefix("Levels: "));
+            ui.add(egui::Slider::new(&mut clipmap.ring_radius, 1..=4).prefix("Ring Radius: "));
+            ui.add(egui::Slider::new(&mut clipmap.node_size, 4..=8).prefix("Node Size: "));
+            ui.checkbox(&mut show_grid.0, "Show Tile Grid");
+            ui.checkbox(&mut new_alignment.0, "Align Texels to Tile Edges");
+
+            ui.separator();
+            for (level, occupied) in clipmap.occupied.iter().enumerate() {
+                ui.label(format!("Level {}: {} tiles", level, occupied.len()));
+            }
+        });
+
+    alignment.set_if_neq(new_alignment);
+}
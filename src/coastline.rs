@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+/// Cleans up the land/water boundary. An opening (erode, then dilate) removes islands thinner
+/// than the kernel, and a closing (dilate, then erode) fills inlets and lakes of the same size.
+/// This works on the binary land mask only; the heights themselves are left alone.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct CoastlineSmoothing {
+    pub enabled: bool,
+    /// Radius, in cells, of the features removed.
+    pub iterations: usize,
+}
+
+impl Default for CoastlineSmoothing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            iterations: 2,
+        }
+    }
+}
+
+/// Which cells are land, given heights (0-1) and the water level.
+pub fn land_mask(heights: &[Vec<f32>], water_level: f32) -> Vec<Vec<bool>> {
+    heights
+        .iter()
+        .map(|row| row.iter().map(|&f| f >= water_level).collect())
+        .collect()
+}
+
+pub fn smooth_coastline(land: &mut Vec<Vec<bool>>, iterations: usize) {
+    // Open, removing small islands.
+    for _ in 0..iterations {
+        *land = morph(land, false);
+    }
+    for _ in 0..iterations {
+        *land = morph(land, true);
+    }
+
+    // Close, removing small bodies of water.
+    for _ in 0..iterations {
+        *land = morph(land, true);
+    }
+    for _ in 0..iterations {
+        *land = morph(land, false);
+    }
+}
+
+/// One 3x3 dilation (`grow`) or erosion of the land. Cells past the edge are ignored so the
+/// border of the tile doesn't erode on its own.
+fn morph(land: &[Vec<bool>], grow: bool) -> Vec<Vec<bool>> {
+    let size = land.len();
+    (0..size)
+        .map(|x| {
+            (0..land[x].len())
+                .map(|y| {
+                    let mut neighbors = (x.saturating_sub(1)..=(x + 1).min(size - 1))
+                        .flat_map(|nx| {
+                            (y.saturating_sub(1)..=(y + 1).min(land[nx].len() - 1))
+                                .map(move |ny| (nx, ny))
+                        })
+                        .map(|(nx, ny)| land[nx][ny]);
+                    if grow {
+                        neighbors.any(|cell| cell)
+                    } else {
+                        neighbors.all(|cell| cell)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specks_are_removed_and_the_coast_kept() {
+        // Water on the left half, land on the right.
+        let coast: Vec<Vec<bool>> = (0..16).map(|_| (0..16).map(|y| y >= 8).collect()).collect();
+        let mut land = coast.clone();
+        // A one cell island and a one cell lake.
+        land[4][3] = true;
+        land[11][12] = false;
+
+        smooth_coastline(&mut land, 1);
+
+        assert_eq!(land, coast);
+    }
+}
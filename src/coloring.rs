@@ -0,0 +1,671 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use diamond_square::Heightmap;
+
+use crate::{
+    bump::{self, BumpMapped},
+    cache::{self, ColorCache},
+    climate::{self, Climate},
+    coastline::{self, CoastlineSmoothing},
+    detail::{self, DetailTexturing},
+    dither::{self, DitherMode},
+    lighting::{self, Lighting},
+    lut::{self, ActiveLut, ColoringMode, Lut},
+    material::TileMaterial,
+    palette::{self, PaletteCrossfade},
+    shore, slope,
+    strata::{self, Strata},
+    viewshed::{self, Viewshed},
+    walkability::{self, Walkability},
+    water::{self, WaterAnimation},
+    watershed, Position, TileHeights,
+};
+
+/// What the tile texture shows.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OutputMode {
+    /// The colored terrain.
+    #[default]
+    Terrain,
+    /// Drainage basins from [`watershed::watershed`], each in its own hue.
+    RegionMap,
+    /// Whittaker biomes from temperature and moisture, see [`Climate`].
+    Biomes,
+    /// Grayscale of the min/max normalized heights with no logistic curve, bands or lighting.
+    Raw,
+    /// The compass direction each slope faces as a hue, from [`slope::compute_aspect`].
+    Aspect,
+    /// How far every cell is from water, from [`shore::distance_to_water`].
+    WaterDistance,
+}
+
+impl OutputMode {
+    pub const ALL: [OutputMode; 6] = [
+        OutputMode::Terrain,
+        OutputMode::RegionMap,
+        OutputMode::Biomes,
+        OutputMode::Raw,
+        OutputMode::Aspect,
+        OutputMode::WaterDistance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputMode::Terrain => "Terrain",
+            OutputMode::RegionMap => "Region Map",
+            OutputMode::Biomes => "Biomes",
+            OutputMode::Raw => "Raw",
+            OutputMode::Aspect => "Aspect",
+            OutputMode::WaterDistance => "Distance to Water",
+        }
+    }
+}
+
+/// Height (after the logistic curve) below which cells are water.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct WaterLevel(pub f32);
+
+impl Default for WaterLevel {
+    fn default() -> Self {
+        Self(0.2)
+    }
+}
+
+/// Where snow settles. Above the snow line flat cells turn white while steeper ones keep the rock
+/// color, since snow slides off faces steeper than `max_slope`.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct SnowModel {
+    pub enabled: bool,
+    /// Height (after the logistic curve) where snow starts.
+    pub snow_line: f32,
+    /// Slope, in height per tile width, at which no snow sticks at all.
+    pub max_slope: f32,
+}
+
+impl Default for SnowModel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            snow_line: 0.9,
+            max_slope: 8.0,
+        }
+    }
+}
+
+/// Steep ground reads as higher than flat ground at the same altitude. When enabled the palette
+/// bands are picked by `height + slope_weight * slope` instead of the height alone, so ridges
+/// turn to rock and snow before the flat ground around them. Water still follows the real
+/// height.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct EffectiveElevation {
+    pub enabled: bool,
+    /// Height (after the logistic curve) added per unit of slope, in height per tile width.
+    pub slope_weight: f32,
+}
+
+impl Default for EffectiveElevation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            slope_weight: 0.02,
+        }
+    }
+}
+
+/// Everything that affects how heights turn into colors.
+#[derive(Debug, Clone, Default)]
+pub struct ColorSettings {
+    pub output: OutputMode,
+    pub water_level: WaterLevel,
+    pub coastline: CoastlineSmoothing,
+    pub snow: SnowModel,
+    pub effective_elevation: EffectiveElevation,
+    pub dither: DitherMode,
+    pub lighting: Lighting,
+    pub climate: Climate,
+    pub strata: Strata,
+    pub walkability: Walkability,
+    pub viewshed: Viewshed,
+    pub crossfade: PaletteCrossfade,
+    pub lut: Option<Lut>,
+}
+
+/// The coloring resources, for systems that need to color or recolor tiles.
+#[derive(SystemParam)]
+pub struct Coloring<'w> {
+    output: Res<'w, OutputMode>,
+    water_level: Res<'w, WaterLevel>,
+    coastline: Res<'w, CoastlineSmoothing>,
+    snow: Res<'w, SnowModel>,
+    effective_elevation: Res<'w, EffectiveElevation>,
+    dither: Res<'w, DitherMode>,
+    lighting: Res<'w, Lighting>,
+    climate: Res<'w, Climate>,
+    strata: Res<'w, Strata>,
+    walkability: Res<'w, Walkability>,
+    viewshed: Res<'w, Viewshed>,
+    crossfade: Res<'w, PaletteCrossfade>,
+    lut: Res<'w, ActiveLut>,
+}
+
+impl Coloring<'_> {
+    pub fn settings(&self) -> ColorSettings {
+        ColorSettings {
+            output: *self.output,
+            water_level: *self.water_level,
+            coastline: *self.coastline,
+            snow: *self.snow,
+            effective_elevation: *self.effective_elevation,
+            dither: *self.dither,
+            lighting: self.lighting.clone(),
+            climate: *self.climate,
+            strata: *self.strata,
+            walkability: *self.walkability,
+            viewshed: *self.viewshed,
+            crossfade: *self.crossfade,
+            lut: self.lut.0.clone(),
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.output.is_changed()
+            || self.water_level.is_changed()
+            || self.coastline.is_changed()
+            || self.snow.is_changed()
+            || self.effective_elevation.is_changed()
+            || self.dither.is_changed()
+            || self.lighting.is_changed()
+            || self.climate.is_changed()
+            || self.strata.is_changed()
+            || self.walkability.is_changed()
+            || self.viewshed.is_changed()
+            || self.crossfade.is_changed()
+            || self.lut.is_changed()
+    }
+}
+
+/// Colors a heightmap. `position` is the tile the heights belong to, which matters for anything
+/// that changes across the world such as temperature.
+pub fn colorize(heightmap: &[Vec<f32>], position: Position, settings: &ColorSettings) -> Vec<u8> {
+    match settings.output {
+        OutputMode::RegionMap => return region_colors(&watershed::watershed(heightmap)),
+        OutputMode::Raw => {
+            let normalized = Heightmap::from_rows(heightmap).normalized().to_rows();
+            return dither::quantize(&normalized, settings.dither)
+                .into_iter()
+                .flatten()
+                .flat_map(|value| [value, value, value, 0xFF])
+                .collect();
+        }
+        OutputMode::Aspect => return aspect_colors(&slope::compute_aspect(heightmap)),
+        OutputMode::WaterDistance => {
+            let distances = shore::tile_distance_to_water(heightmap, settings.water_level.0);
+            return shore::distance_colors(&distances);
+        }
+        OutputMode::Terrain | OutputMode::Biomes => {}
+    }
+
+    // Slopes are only needed when the snow, strata, palette or walkability depend on them.
+    let (snow, strata) = (&settings.snow, &settings.strata);
+    let (effective, walk) = (&settings.effective_elevation, &settings.walkability);
+    let crossfade = &settings.crossfade;
+    let slopes = (snow.enabled || strata.enabled || effective.enabled || walk.enabled)
+        .then(|| slope::slope(heightmap));
+    let light = settings
+        .lighting
+        .enabled
+        .then(|| lighting::shade(heightmap, &settings.lighting));
+
+    // Plug each value into logistics curve to clamp (0-1).
+    let heightmap: Vec<Vec<f32>> = heightmap
+        .iter()
+        .map(|row| row.iter().map(|&f| logistic(f)).collect())
+        .collect();
+
+    let values = dither::quantize(&heightmap, settings.dither);
+
+    let walkable = slopes.as_ref().filter(|_| walk.enabled).map(|slopes| {
+        walkability::walkable_mask(&heightmap, slopes, settings.water_level.0, walk.max_slope)
+    });
+
+    let visible = match settings.viewshed.observer {
+        Some((observer, cell)) if settings.viewshed.enabled && observer == position => Some(
+            viewshed::viewshed(&heightmap, cell, settings.viewshed.observer_height),
+        ),
+        _ => None,
+    };
+
+    let mut land = coastline::land_mask(&heightmap, settings.water_level.0);
+    if settings.coastline.enabled {
+        coastline::smooth_coastline(&mut land, settings.coastline.iterations);
+    }
+
+    let biomes = (settings.output == OutputMode::Biomes).then(|| {
+        climate::biome_field(
+            &heightmap,
+            position,
+            settings.water_level.0,
+            &settings.climate,
+        )
+    });
+
+    // Transform the raw data into a usable format.
+    heightmap
+        .iter()
+        .enumerate()
+        .flat_map(|(x, row)| row.iter().enumerate().map(move |(y, &f)| (x, y, f)))
+        .map(|(x, y, f)| {
+            let value = values[x][y];
+            let slope = slopes.as_ref().map(|slopes| slopes[x][y]);
+            let band = match slope {
+                Some(slope) if effective.enabled => f + effective.slope_weight * slope,
+                _ => f,
+            };
+            let color = match (&biomes, &settings.lut, slope) {
+                (None, Some(lut), _) => lut.sample(band),
+                (None, None, _) if crossfade.enabled => crossfade.color(band, value, land[x][y]),
+                _ if !land[x][y] => [0, 0, value],
+                (Some(biomes), _, _) => biomes[x][y].color(),
+                (None, None, Some(slope)) if strata.enabled && slope >= strata.min_slope => {
+                    strata::strata_color(f, value, strata)
+                }
+                (None, None, Some(slope)) if snow.enabled => snow_color(band, value, slope, snow),
+                (None, None, _) => land_color(band, value),
+            };
+
+            let color = match &light {
+                Some(light) => (Vec3::from(color.map(|c| c as f32)) * light[x][y])
+                    .to_array()
+                    .map(|c| c as u8),
+                None => color,
+            };
+
+            let color = match &walkable {
+                Some(walkable) => tint(color, walkable[x][y]),
+                None => color,
+            };
+
+            match &visible {
+                Some(visible) => tint(color, visible[x][y]),
+                None => color,
+            }
+        })
+        // Convert to a color format that Bevy can use.
+        .flat_map(|[r, g, b]| [r, g, b, 0xFF])
+        .collect()
+}
+
+pub fn logistic(f: f32) -> f32 {
+    1.0 / (1.0 + std::f32::consts::E.powf(-f))
+}
+
+/// Apply basic coloring based on value. The band is picked from the height `f` (0-1), while
+/// `value` is its already quantized brightness. Water is decided separately by the land mask, so
+/// anything low that still counts as land is lowland.
+pub fn land_color(f: f32, value: u8) -> [u8; 3] {
+    match f {
+        f if f < 0.65 => [0, value, 0],
+        f if f < 0.9 => [value / 2; 3],
+        _ => [value; 3],
+    }
+}
+
+/// Like [`land_color`], but everything above the snow line is blended from rock to snow by how
+/// flat the cell is.
+fn snow_color(f: f32, value: u8, slope: f32, snow: &SnowModel) -> [u8; 3] {
+    if f < snow.snow_line {
+        // The rock band reaches up to wherever the snow line was moved.
+        return land_color(f.min(0.9 - f32::EPSILON), value);
+    }
+
+    let value = value as f32;
+    let coverage = (1.0 - slope / snow.max_slope).clamp(0.0, 1.0);
+    let shade = value / 2.0 + value / 2.0 * coverage;
+    [shade as u8; 3]
+}
+
+/// Blends the green or red tint of a yes/no overlay like [`Walkability`] or [`Viewshed`] over a
+/// terrain color, green where `good`.
+fn tint(color: [u8; 3], good: bool) -> [u8; 3] {
+    // How strongly the tint covers the terrain colors, 0-1.
+    const OPACITY: f32 = 0.4;
+
+    let tint = if good { [0, 200, 0] } else { [220, 0, 0] };
+    [0, 1, 2].map(|i| {
+        let (color, tint) = (color[i] as f32, tint[i] as f32);
+        (color + (tint - color) * OPACITY) as u8
+    })
+}
+
+/// Gives every region a distinct hue, spreading consecutive ids around the color wheel by the
+/// golden angle so neighboring basins rarely look alike.
+fn region_colors(regions: &[Vec<u32>]) -> Vec<u8> {
+    const GOLDEN_ANGLE: f32 = 137.507_77;
+
+    regions
+        .iter()
+        .flatten()
+        .flat_map(|&region| {
+            let hue = (region as f32 * GOLDEN_ANGLE) % 360.0;
+            Color::hsl(hue, 0.6, 0.5).as_rgba_u8()
+        })
+        .collect()
+}
+
+/// Maps aspect around the color wheel so opposite slopes get opposite hues, with north red.
+/// Flat cells are neutral gray.
+fn aspect_colors(aspect: &[Vec<f32>]) -> Vec<u8> {
+    aspect
+        .iter()
+        .flatten()
+        .flat_map(|&azimuth| {
+            if azimuth.is_nan() {
+                [0x80, 0x80, 0x80, 0xFF]
+            } else {
+                Color::hsl(azimuth, 0.7, 0.5).as_rgba_u8()
+            }
+        })
+        .collect()
+}
+
+/// Rebuilds the textures of every loaded tile from its cached heights when the coloring changes.
+pub fn recolor_tiles(
+    coloring: Coloring,
+    mut cache: ResMut<ColorCache>,
+    tiles: Query<(Entity, Ref<TileHeights>, &Position, &Handle<TileMaterial>)>,
+    mut removed: RemovedComponents<TileHeights>,
+    materials: Res<Assets<TileMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for tile in removed.read() {
+        cache.evict(tile);
+    }
+    // New or edited heights were colored by whoever set them, so cached colors are stale.
+    for (tile, heights, ..) in tiles.iter() {
+        if heights.is_changed() {
+            cache.evict(tile);
+        }
+    }
+
+    if !coloring.is_changed() {
+        return;
+    }
+
+    let settings = coloring.settings();
+    let hash = cache::settings_hash(&settings);
+    for (tile, heights, &position, material) in tiles.iter() {
+        let Some(texture) = materials
+            .get(material)
+            .and_then(|material| material.base.base_color_texture.as_ref())
+        else {
+            continue;
+        };
+
+        if let Some(image) = images.get_mut(texture) {
+            let rgba = cache
+                .take(hash, tile)
+                .unwrap_or_else(|| colorize(&heights.0, position, &settings));
+            let old = std::mem::replace(&mut image.data, rgba);
+            cache.store(tile, old);
+            cache.colored(tile, hash);
+        }
+    }
+}
+
+/// The resources the coloring window edits, for [`coloring_ui`].
+#[derive(SystemParam)]
+pub struct ColoringControls<'w> {
+    output: ResMut<'w, OutputMode>,
+    water_level: ResMut<'w, WaterLevel>,
+    coastline: ResMut<'w, CoastlineSmoothing>,
+    snow: ResMut<'w, SnowModel>,
+    effective_elevation: ResMut<'w, EffectiveElevation>,
+    dither: ResMut<'w, DitherMode>,
+    lighting: ResMut<'w, Lighting>,
+    climate: ResMut<'w, Climate>,
+    strata: ResMut<'w, Strata>,
+    mode: ResMut<'w, ColoringMode>,
+    detail: ResMut<'w, DetailTexturing>,
+    water: ResMut<'w, WaterAnimation>,
+    bump: ResMut<'w, BumpMapped>,
+    walkability: ResMut<'w, Walkability>,
+    crossfade: ResMut<'w, PaletteCrossfade>,
+}
+
+pub fn coloring_ui(
+    mut contexts: EguiContexts,
+    controls: ColoringControls,
+    mut images: ResMut<Assets<Image>>,
+    mut lut_path: Local<String>,
+) {
+    const DEFAULT_LUT_PATH: &str = "lut.png";
+
+    let ColoringControls {
+        mut output,
+        mut water_level,
+        mut coastline,
+        mut snow,
+        mut effective_elevation,
+        mut dither,
+        mut lighting,
+        mut climate,
+        mut strata,
+        mut mode,
+        mut detail,
+        mut water,
+        mut bump,
+        mut walkability,
+        mut crossfade,
+    } = controls;
+
+    if lut_path.is_empty() {
+        *lut_path = DEFAULT_LUT_PATH.to_string();
+    }
+
+    // Edit copies and only write back real changes, otherwise every frame would recolor.
+    let mut new_output = *output;
+    let (mut new_water_level, mut new_coastline) = (*water_level, *coastline);
+    let (mut new_snow, mut new_dither) = (*snow, *dither);
+    let mut new_effective_elevation = *effective_elevation;
+    let mut new_lighting = lighting.clone();
+    let mut new_climate = *climate;
+    let mut new_strata = *strata;
+    let mut new_mode = mode.clone();
+    let mut new_detail = detail.clone();
+    let mut new_water = water.clone();
+    let mut new_bump = *bump;
+    let mut new_walkability = *walkability;
+    let mut new_crossfade = *crossfade;
+
+    egui::Window::new("Coloring")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ComboBox::from_label("Output")
+                .selected_text(new_output.name())
+                .show_ui(ui, |ui| {
+                    for mode in OutputMode::ALL {
+                        ui.selectable_value(&mut new_output, mode, mode.name());
+                    }
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut new_mode, ColoringMode::Bands, "Bands");
+                if let ColoringMode::Lut(_) = new_mode {
+                    ui.label("LUT loaded");
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("LUT:");
+                ui.text_edit_singleline(&mut *lut_path);
+                if ui.button("Load LUT").clicked() {
+                    match lut::load_lut_image(&*lut_path, &mut images) {
+                        Ok(handle) => new_mode = ColoringMode::Lut(handle),
+                        Err(err) => error!("Failed to load LUT {}: {}", *lut_path, err),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.add(egui::Slider::new(&mut new_water_level.0, 0.0..=1.0).prefix("Water Level: "));
+            ui.checkbox(&mut new_coastline.enabled, "Smooth Coastline");
+            ui.add(
+                egui::Slider::new(&mut new_coastline.iterations, 1..=8)
+                    .prefix("Smoothing Radius: "),
+            );
+
+            ui.separator();
+            ui.checkbox(&mut new_snow.enabled, "Slope-Aware Snow");
+            ui.add(egui::Slider::new(&mut new_snow.snow_line, 0.5..=1.0).prefix("Snow Line: "));
+            ui.add(egui::Slider::new(&mut new_snow.max_slope, 0.5..=20.0).prefix("Max Slope: "));
+
+            ui.separator();
+            let effective = &mut new_effective_elevation;
+            ui.checkbox(&mut effective.enabled, "Effective Elevation");
+            ui.add(
+                egui::Slider::new(&mut effective.slope_weight, 0.0..=0.1).prefix("Slope Weight: "),
+            );
+
+            ui.separator();
+            ui.checkbox(&mut new_strata.enabled, "Rock Strata");
+            ui.add(egui::Slider::new(&mut new_strata.band_count, 2..=64).prefix("Layers: "));
+            ui.add(
+                egui::Slider::new(&mut new_strata.color_variation, 0.0..=1.0)
+                    .prefix("Color Variation: "),
+            );
+            ui.add(
+                egui::Slider::new(&mut new_strata.min_slope, 0.5..=20.0).prefix("Cliff Slope: "),
+            );
+
+            ui.separator();
+            egui::ComboBox::from_label("Dithering")
+                .selected_text(new_dither.name())
+                .show_ui(ui, |ui| {
+                    for mode in DitherMode::ALL {
+                        ui.selectable_value(&mut new_dither, mode, mode.name());
+                    }
+                });
+
+            ui.separator();
+            palette::crossfade_ui(ui, &mut new_crossfade);
+
+            ui.separator();
+            lighting::lighting_ui(ui, &mut new_lighting);
+
+            ui.separator();
+            detail::detail_ui(ui, &mut new_detail);
+            water::water_ui(ui, &mut new_water);
+            bump::bump_ui(ui, &mut new_bump);
+
+            ui.separator();
+            walkability::walkability_ui(ui, &mut new_walkability);
+
+            ui.separator();
+            ui.collapsing("Climate", |ui| {
+                let climate = &mut new_climate;
+                ui.add(
+                    egui::Slider::new(&mut climate.equator_temperature, 0.0..=50.0)
+                        .prefix("Equator: ")
+                        .suffix(" °C"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut climate.pole_temperature, -50.0..=20.0)
+                        .prefix("Poles: ")
+                        .suffix(" °C"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut climate.latitude_span, 1.0..=64.0)
+                        .prefix("Equator to Pole: ")
+                        .suffix(" tiles"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut climate.lapse_rate, 0.0..=100.0).prefix("Lapse Rate: "),
+                );
+                ui.add(
+                    egui::Slider::new(&mut climate.moisture_reach, 0.01..=0.5)
+                        .prefix("Moisture Reach: "),
+                );
+            });
+        });
+
+    output.set_if_neq(new_output);
+    water_level.set_if_neq(new_water_level);
+    coastline.set_if_neq(new_coastline);
+    snow.set_if_neq(new_snow);
+    effective_elevation.set_if_neq(new_effective_elevation);
+    dither.set_if_neq(new_dither);
+    lighting.set_if_neq(new_lighting);
+    climate.set_if_neq(new_climate);
+    strata.set_if_neq(new_strata);
+    mode.set_if_neq(new_mode);
+    detail.set_if_neq(new_detail);
+    water.set_if_neq(new_water);
+    bump.set_if_neq(new_bump);
+    walkability.set_if_neq(new_walkability);
+    crossfade.set_if_neq(new_crossfade);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 5;
+
+    /// Raw height whose logistic is `f`.
+    fn raw(f: f32) -> f32 {
+        (f / (1.0 - f)).ln()
+    }
+
+    /// A tile at height `f` (after the logistic curve) rising by `slope`, in height per tile
+    /// width, along the rows.
+    fn incline(f: f32, slope: f32) -> Vec<Vec<f32>> {
+        let center = (SIZE / 2) as f32;
+        let step = slope / (SIZE - 1) as f32;
+        (0..SIZE)
+            .map(|x| vec![raw(f) + (x as f32 - center) * step; SIZE])
+            .collect()
+    }
+
+    /// The color of the center cell, which sits at the same height on every incline.
+    fn center_color(heights: &[Vec<f32>], settings: &ColorSettings) -> [u8; 3] {
+        let rgba = colorize(heights, Position((0, 0)), settings);
+        let i = (SIZE / 2 * SIZE + SIZE / 2) * 4;
+        [rgba[i], rgba[i + 1], rgba[i + 2]]
+    }
+
+    #[test]
+    fn steep_snow_is_less_white_than_flat_snow() {
+        let settings = ColorSettings {
+            snow: SnowModel {
+                enabled: true,
+                ..default()
+            },
+            ..default()
+        };
+        let max_slope = settings.snow.max_slope;
+
+        let flat = center_color(&incline(0.95, 0.0), &settings);
+        let steep = center_color(&incline(0.95, max_slope), &settings);
+        assert!(steep[0] < flat[0], "{:?} vs {:?}", steep, flat);
+    }
+
+    #[test]
+    fn steep_cells_reach_higher_bands_with_effective_elevation() {
+        let mut settings = ColorSettings {
+            effective_elevation: EffectiveElevation {
+                enabled: true,
+                ..default()
+            },
+            ..default()
+        };
+        // Just under the rock band when flat.
+        let (flat, steep) = (incline(0.6, 0.0), incline(0.6, 4.0));
+
+        let value = (logistic(flat[SIZE / 2][SIZE / 2]) * 0xFF as f32) as u8;
+        assert_eq!(center_color(&flat, &settings), [0, value, 0]);
+        assert_eq!(center_color(&steep, &settings), [value / 2; 3]);
+
+        settings.effective_elevation.enabled = false;
+        assert_eq!(center_color(&steep, &settings), [0, value, 0]);
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    coloring::logistic,
+    mapmode::{self, MapMode},
+    Tile, TileHeights,
+};
+
+/// Contours shorter than this many points are too small to label legibly.
+const MIN_LABELED_POINTS: usize = 12;
+
+/// Isolines drawn over the tiles every `interval` of height (after the logistic curve).
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct Contours {
+    pub enabled: bool,
+    pub interval: f32,
+}
+
+impl Default for Contours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 0.1,
+        }
+    }
+}
+
+/// Writes each contour's height next to it while [`Contours`] are shown.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Copy)]
+pub struct ContourLabels(pub bool);
+
+/// One traced isoline, in cell coordinates: `(x, y)` is a point between `heights[x][y]` and
+/// its neighbors.
+pub struct Contour {
+    pub height: f32,
+    pub points: Vec<Vec2>,
+}
+
+/// The contours of a tile, traced whenever its heights or the [`Contours`] change.
+#[derive(Component)]
+pub struct TileContours(Vec<Contour>);
+
+/// A cell edge: the grid point it starts at and whether it runs along `x` (`false`) or `y`
+/// (`true`).
+type Edge = (usize, usize, bool);
+
+/// Where the isoline at `height` crosses `edge`. Always interpolated from the edge's first
+/// point, so both cells sharing the edge compute exactly the same position.
+fn crossing(heights: &[Vec<f32>], (x, y, along_y): Edge, height: f32) -> Vec2 {
+    let (x2, y2) = if along_y { (x, y + 1) } else { (x + 1, y) };
+    let (a, b) = (heights[x][y], heights[x2][y2]);
+    let t = ((height - a) / (b - a)).clamp(0.0, 1.0);
+    Vec2::new(x as f32, y as f32).lerp(Vec2::new(x2 as f32, y2 as f32), t)
+}
+
+/// Marching squares: the isoline at `height` as segments between cell edges.
+fn segments(heights: &[Vec<f32>], height: f32) -> Vec<[Edge; 2]> {
+    let size = heights.len();
+    let mut segments = Vec::new();
+
+    for x in 0..size - 1 {
+        for y in 0..size - 1 {
+            let corners = [
+                heights[x][y],
+                heights[x + 1][y],
+                heights[x + 1][y + 1],
+                heights[x][y + 1],
+            ];
+            // Edges in order around the cell, edge `i` running from corner `i` to `i + 1`.
+            let edges = [
+                (x, y, false),
+                (x + 1, y, true),
+                (x, y + 1, false),
+                (x, y, true),
+            ];
+            let above = corners.map(|h| h >= height);
+            let crossed: Vec<usize> = (0..4).filter(|&i| above[i] != above[(i + 1) % 4]).collect();
+
+            match crossed[..] {
+                [a, b] => segments.push([edges[a], edges[b]]),
+                [_, _, _, _] => {
+                    // A saddle. The average of the corners decides which diagonal is connected,
+                    // and the other two corners are cut off.
+                    let center = corners.iter().sum::<f32>() / 4.0;
+                    if (center >= height) == above[0] {
+                        segments.push([edges[0], edges[1]]);
+                        segments.push([edges[2], edges[3]]);
+                    } else {
+                        segments.push([edges[3], edges[0]]);
+                        segments.push([edges[1], edges[2]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    segments
+}
+
+/// Joins the marching squares segments at `height` into polylines.
+pub fn trace(heights: &[Vec<f32>], height: f32) -> Vec<Contour> {
+    let segments = segments(heights, height);
+    let mut at_edge: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (index, segment) in segments.iter().enumerate() {
+        for edge in segment {
+            at_edge.entry(*edge).or_default().push(index);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut contours = Vec::new();
+    for (start, &[first, second]) in segments.iter().enumerate() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        // Walk forward from the segment's end, then backward from its start, until the line
+        // closes or leaves the tile.
+        let mut line = VecDeque::from([first, second]);
+        for forward in [true, false] {
+            loop {
+                let end = if forward {
+                    line[line.len() - 1]
+                } else {
+                    line[0]
+                };
+                let next = at_edge[&end].iter().copied().find(|&index| !used[index]);
+                let Some(next) = next else {
+                    break;
+                };
+                used[next] = true;
+                let [a, b] = segments[next];
+                let edge = if a == end { b } else { a };
+                if forward {
+                    line.push_back(edge);
+                } else {
+                    line.push_front(edge);
+                }
+            }
+        }
+
+        contours.push(Contour {
+            height,
+            points: line
+                .into_iter()
+                .map(|edge| crossing(heights, edge, height))
+                .collect(),
+        });
+    }
+
+    contours
+}
+
+/// The contour heights every `interval` between 0 and 1.
+pub fn levels(interval: f32) -> impl Iterator<Item = f32> {
+    let interval = interval.max(0.01);
+    (1..)
+        .map(move |i| i as f32 * interval)
+        .take_while(|&h| h < 1.0)
+}
+
+/// Retraces the contours of tiles whose heights changed, or of every tile when the settings did.
+pub fn update_contours(
+    mut commands: Commands,
+    contours: Res<Contours>,
+    tiles: Query<(Entity, Ref<TileHeights>), With<Tile>>,
+) {
+    for (entity, heights) in tiles.iter() {
+        if !contours.is_changed() && !heights.is_changed() {
+            continue;
+        }
+        if !contours.enabled {
+            commands.entity(entity).remove::<TileContours>();
+            continue;
+        }
+
+        let heights: Vec<Vec<f32>> = heights
+            .0
+            .iter()
+            .map(|row| row.iter().map(|&f| logistic(f)).collect())
+            .collect();
+        let traced = levels(contours.interval)
+            .flat_map(|height| trace(&heights, height))
+            .collect();
+        commands.entity(entity).insert(TileContours(traced));
+    }
+}
+
+pub fn draw_contours(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    labels: Res<ContourLabels>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tiles: Query<(&TileContours, &TileHeights, &Transform)>,
+) {
+    let camera = cameras.get_single().ok();
+    // The labels go behind every window so they never cover the UI.
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+
+    for (contours, heights, transform) in tiles.iter() {
+        // Cell coordinates to the tile's unit quad, rows running down along -Y.
+        let cells = (heights.0.len() - 1) as f32;
+        let to_world = |point: Vec2| {
+            let local = Vec3::new(point.y / cells - 0.5, 0.5 - point.x / cells, 0.0);
+            transform.transform_point(local) + Vec3::Z * 0.005
+        };
+
+        for contour in &contours.0 {
+            gizmos.linestrip(contour.points.iter().map(|&p| to_world(p)), Color::BLACK);
+
+            let Some((camera, camera_transform)) = camera else {
+                continue;
+            };
+            if !labels.0 || contour.points.len() < MIN_LABELED_POINTS {
+                continue;
+            }
+            let middle = to_world(contour.points[contour.points.len() / 2]);
+            let Some(screen) = camera.world_to_viewport(camera_transform, middle) else {
+                continue;
+            };
+            painter.text(
+                egui::pos2(screen.x, screen.y),
+                egui::Align2::CENTER_CENTER,
+                format!("{:.2}", contour.height),
+                egui::FontId::proportional(11.0),
+                egui::Color32::BLACK,
+            );
+        }
+    }
+}
+
+pub fn contours_ui(
+    mut contexts: EguiContexts,
+    mut contours: ResMut<Contours>,
+    mut labels: ResMut<ContourLabels>,
+    mut map_mode: ResMut<MapMode>,
+) {
+    // Retracing every tile is slow, so only write back real changes.
+    let mut new_contours = *contours;
+
+    egui::Window::new("Contours")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut new_contours.enabled, "Contour Lines");
+            ui.add(egui::Slider::new(&mut new_contours.interval, 0.02..=0.25).prefix("Interval: "));
+            ui.checkbox(&mut labels.0, "Label Heights");
+
+            ui.separator();
+            mapmode::map_mode_ui(ui, &mut map_mode);
+        });
+
+    contours.set_if_neq(new_contours);
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::{
+    hash,
+    material::{tiling_texture, TileMaterial},
+};
+
+/// Side length of the generated detail texture, in pixels.
+const DETAIL_SIZE: u32 = 64;
+
+/// Fades a high-frequency texture over the tiles near the camera, so zooming in past the tile
+/// resolution shows grain instead of a blur.
+#[derive(Resource, Debug, PartialEq, Clone)]
+pub struct DetailTexturing {
+    pub enabled: bool,
+    /// Grayscale texture that repeats across the world. Mid gray leaves the terrain unchanged.
+    pub detail: Handle<Image>,
+    /// Camera distance, in world units, at which the detail has faded out completely.
+    pub fade_distance: f32,
+    /// How often the detail texture repeats per world unit.
+    pub tiling: f32,
+}
+
+impl FromWorld for DetailTexturing {
+    fn from_world(world: &mut World) -> Self {
+        let detail = world
+            .resource_mut::<Assets<Image>>()
+            .add(detail_noise(DETAIL_SIZE));
+
+        Self {
+            enabled: false,
+            detail,
+            fade_distance: 0.5,
+            tiling: 32.0,
+        }
+    }
+}
+
+/// White noise around mid gray. Every pixel is independent, so the texture tiles seamlessly.
+fn detail_noise(size: u32) -> Image {
+    let data = (0..size as i32)
+        .flat_map(|y| (0..size as i32).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let value = (0.25 + hash(0, x, y) * 0.5) * 255.0;
+            [value as u8, value as u8, value as u8, 0xFF]
+        })
+        .collect();
+
+    tiling_texture(size, data)
+}
+
+/// Keeps the detail settings of every tile material in sync with [`DetailTexturing`].
+pub fn update_detail_materials(
+    detail: Res<DetailTexturing>,
+    tiles: Query<&Handle<TileMaterial>, Added<Handle<TileMaterial>>>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+) {
+    let apply = |material: &mut TileMaterial| {
+        let extension = &mut material.extension;
+        extension.detail_strength = if detail.enabled { 1.0 } else { 0.0 };
+        extension.fade_distance = detail.fade_distance;
+        extension.detail_tiling = detail.tiling;
+        extension.detail = Some(detail.detail.clone());
+    };
+
+    if detail.is_changed() {
+        materials
+            .iter_mut()
+            .for_each(|(_, material)| apply(material));
+        return;
+    }
+
+    for handle in tiles.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            apply(material);
+        }
+    }
+}
+
+pub fn detail_ui(ui: &mut egui::Ui, detail: &mut DetailTexturing) {
+    ui.checkbox(&mut detail.enabled, "Close-Up Detail");
+    ui.add(egui::Slider::new(&mut detail.fade_distance, 0.05..=4.0).prefix("Fade Distance: "));
+    ui.add(egui::Slider::new(&mut detail.tiling, 1.0..=256.0).prefix("Detail Tiling: "));
+}
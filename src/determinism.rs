@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use diamond_square::generate_map_raw;
+
+use crate::{
+    coloring::{colorize, ColorSettings},
+    dither::DitherMode,
+    fixed, generate_heightmap, Position,
+};
+
+/// The tile the check generates.
+const POSITION: Position = Position((3, -2));
+const ROUGHNESS: f32 = 2.0;
+const SEED: isize = 0x5EED;
+const IMAGE_SIZE: usize = 2usize.pow(7) + 1;
+
+/// Run condition: the check only runs when the app was started with `--verify-determinism`.
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == "--verify-determinism")
+}
+
+/// Generates a fixed tile twice and warns if the two runs differ anywhere, which would mean seeds
+/// no longer reproduce the same terrain on this machine.
+pub fn verify_determinism() {
+    let generate = || generate_map_raw(POSITION.0, ROUGHNESS, SEED, IMAGE_SIZE).to_rows();
+    let (first, second) = (generate(), generate());
+
+    // Compare the raw bits so that e.g. NaNs or signed zeros can't hide a difference.
+    let mismatch = (0..IMAGE_SIZE)
+        .flat_map(|x| (0..IMAGE_SIZE).map(move |y| (x, y)))
+        .find(|&(x, y)| first[x][y].to_bits() != second[x][y].to_bits());
+
+    if let Some((x, y)) = mismatch {
+        warn!(
+            "Determinism check failed: heights first differ at cell ({}, {}): {} vs {}",
+            x, y, first[x][y], second[x][y]
+        );
+        return;
+    }
+
+    // Error diffusion is the most order-sensitive part of the coloring.
+    let settings = ColorSettings {
+        dither: DitherMode::ErrorDiffusion,
+        ..Default::default()
+    };
+    let colorize = |heights: &[Vec<f32>]| colorize(heights, POSITION, &settings);
+    let (first, second) = (colorize(&first), colorize(&second));
+    if let Some(index) = (0..first.len()).find(|&i| first[i] != second[i]) {
+        let cell = index / 4;
+        warn!(
+            "Determinism check failed: colors first differ at cell ({}, {})",
+            cell / IMAGE_SIZE,
+            cell % IMAGE_SIZE
+        );
+        return;
+    }
+
+    // Fixed point gives up precision for portability, report how far it strays from floats.
+    let float = generate_heightmap(POSITION, ROUGHNESS, SEED, IMAGE_SIZE);
+    let fixed = fixed::generate_heightmap(POSITION, ROUGHNESS, SEED, IMAGE_SIZE);
+    let deviation = float
+        .iter()
+        .flatten()
+        .zip(fixed.iter().flatten())
+        .map(|(float, fixed)| (float - fixed).abs())
+        .fold(0.0, f32::max);
+    info!(
+        "Fixed point heights are within {} of floating point",
+        deviation
+    );
+
+    info!("Determinism check passed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fixed point heights are multiples of 2^-16 and every halving rounds down, which adds up
+    /// to a few 10^-4 over all the steps of a tile.
+    const MAX_FIXED_DEVIATION: f32 = 1e-3;
+
+    #[test]
+    fn fixed_point_stays_close_to_floating_point() {
+        for roughness in [0.5, ROUGHNESS, 8.0] {
+            let float = generate_heightmap(POSITION, roughness, SEED, IMAGE_SIZE);
+            let fixed = fixed::generate_heightmap(POSITION, roughness, SEED, IMAGE_SIZE);
+            for (float, fixed) in float.iter().flatten().zip(fixed.iter().flatten()) {
+                assert!((float - fixed).abs() < MAX_FIXED_DEVIATION);
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_point_is_bit_identical_across_runs() {
+        let generate = || fixed::generate_heightmap(POSITION, ROUGHNESS, SEED, IMAGE_SIZE);
+        let (first, second) = (generate(), generate());
+        for (first, second) in first.iter().flatten().zip(second.iter().flatten()) {
+            assert_eq!(first.to_bits(), second.to_bits());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+/// How heights (0-1) are quantized to 8-bit brightness. Plain truncation can leave visible
+/// terraces on smooth slopes, which both dithering modes break up.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DitherMode {
+    #[default]
+    None,
+    /// A 4x4 Bayer threshold pattern.
+    Ordered,
+    /// Floyd-Steinberg error diffusion. Always scans in the same order, so it stays deterministic.
+    ErrorDiffusion,
+}
+
+impl DitherMode {
+    pub const ALL: [DitherMode; 3] = [
+        DitherMode::None,
+        DitherMode::Ordered,
+        DitherMode::ErrorDiffusion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DitherMode::None => "None",
+            DitherMode::Ordered => "Ordered",
+            DitherMode::ErrorDiffusion => "Error Diffusion",
+        }
+    }
+}
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+pub fn quantize(heights: &[Vec<f32>], mode: DitherMode) -> Vec<Vec<u8>> {
+    let scaled = |f: f32| f * 0xFF as f32;
+
+    match mode {
+        DitherMode::None => heights
+            .iter()
+            .map(|row| row.iter().map(|&f| scaled(f) as u8).collect())
+            .collect(),
+        DitherMode::Ordered => heights
+            .iter()
+            .enumerate()
+            .map(|(x, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(y, &f)| {
+                        let threshold = (BAYER[x % 4][y % 4] as f32 + 0.5) / 16.0;
+                        (scaled(f) + threshold).floor().clamp(0.0, 255.0) as u8
+                    })
+                    .collect()
+            })
+            .collect(),
+        DitherMode::ErrorDiffusion => error_diffusion(heights, scaled),
+    }
+}
+
+fn error_diffusion(heights: &[Vec<f32>], scaled: impl Fn(f32) -> f32) -> Vec<Vec<u8>> {
+    let mut values: Vec<Vec<f32>> = heights
+        .iter()
+        .map(|row| row.iter().map(|&f| scaled(f)).collect())
+        .collect();
+    let mut output = vec![vec![0; values.first().map_or(0, Vec::len)]; values.len()];
+
+    // Rows are `x`, so "ahead" in the scan is `y + 1` and the next row is `x + 1`.
+    for x in 0..values.len() {
+        for y in 0..values[x].len() {
+            let old = values[x][y];
+            let new = old.round().clamp(0.0, 255.0);
+            output[x][y] = new as u8;
+
+            let error = old - new;
+            let mut spread = |x: usize, y: Option<usize>, weight: f32| {
+                if let Some(cell) = y.and_then(|y| values.get_mut(x)?.get_mut(y)) {
+                    *cell += error * weight / 16.0;
+                }
+            };
+            spread(x, Some(y + 1), 7.0);
+            spread(x + 1, y.checked_sub(1), 3.0);
+            spread(x + 1, Some(y), 5.0);
+            spread(x + 1, Some(y + 1), 1.0);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mean brightness of every 4x4 block.
+    fn block_means(values: &[Vec<u8>]) -> Vec<f32> {
+        let blocks = values.len() / 4;
+        let cells = |bx: usize, by: usize| {
+            (0..16).map(move |i| values[bx * 4 + i / 4][by * 4 + i % 4] as f32)
+        };
+        (0..blocks * blocks)
+            .map(|b| cells(b / blocks, b % blocks).sum::<f32>() / 16.0)
+            .collect()
+    }
+
+    #[test]
+    fn error_diffusion_preserves_the_local_average() {
+        // Between two levels, so truncation alone would be almost a whole level off.
+        let f = 0.3017;
+        let heights = vec![vec![f; 16]; 16];
+        let target = f * 0xFF as f32;
+
+        let dithered = quantize(&heights, DitherMode::ErrorDiffusion);
+        for mean in block_means(&dithered) {
+            assert!((mean - target).abs() < 0.25, "{} vs {}", mean, target);
+        }
+        assert_eq!(dithered, quantize(&heights, DitherMode::ErrorDiffusion));
+
+        let truncated = quantize(&heights, DitherMode::None);
+        assert!(block_means(&truncated)
+            .iter()
+            .all(|mean| (mean - target).abs() > 0.5));
+    }
+}
@@ -0,0 +1,36 @@
+use std::{error::Error, fs, path::Path};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{GenSettings, Position};
+
+/// A self-describing project file. Holds everything needed to reopen a tile exactly as it was
+/// generated (or edited) without running the generator again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TerrainDocument {
+    pub settings: GenSettings,
+    pub position: Position,
+    pub heights: Vec<Vec<f32>>,
+}
+
+impl TerrainDocument {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        // Keep the height rows on one line each, otherwise the file is one float per line.
+        let config = PrettyConfig::new().compact_arrays(true);
+        fs::write(path, ron::ser::to_string_pretty(self, config)?)?;
+        Ok(())
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let document: Self = ron::from_str(&fs::read_to_string(path)?)?;
+
+        // A heightmap that isn't square can't be turned back into a tile.
+        let size = document.heights.len();
+        if size == 0 || document.heights.iter().any(|row| row.len() != size) {
+            return Err("heightmap is empty or not square".into());
+        }
+
+        Ok(document)
+    }
+}
@@ -0,0 +1,476 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    coloring::{colorize, Coloring},
+    geologic::{self, GeologicTime},
+    material::TileMaterial,
+    watershed, GenSettings, Position, Tile, TileHeights, TileLevel,
+};
+
+/// Material slides downhill wherever the terrain is steeper than the talus slope, softening
+/// cliffs into scree slopes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ThermalErosion {
+    /// Steepest stable slope, in height per tile width like [`slope::slope`](crate::slope::slope).
+    pub talus: f32,
+    /// Fraction of the excess height that moves per iteration, 0-1.
+    pub rate: f32,
+}
+
+impl Default for ThermalErosion {
+    fn default() -> Self {
+        Self {
+            talus: 4.0,
+            rate: 0.5,
+        }
+    }
+}
+
+/// One iteration of thermal erosion. Every cell sheds part of its excess height onto its lowest
+/// neighbor, so the total height is preserved.
+pub fn thermal_erosion_step(heights: &[Vec<f32>], erosion: &ThermalErosion) -> Vec<Vec<f32>> {
+    let size = heights.len();
+    let talus = erosion.talus / size.saturating_sub(1).max(1) as f32;
+    let height = |(x, y): (usize, usize)| heights[x][y];
+
+    let mut eroded = heights.to_vec();
+    for x in 0..size {
+        for y in 0..size {
+            let Some(lowest) =
+                watershed::neighbors(size, (x, y)).min_by(|&a, &b| height(a).total_cmp(&height(b)))
+            else {
+                continue;
+            };
+
+            let drop = heights[x][y] - height(lowest);
+            if drop > talus {
+                // Moving half the excess would level the pair exactly.
+                let moved = erosion.rate * (drop - talus) / 2.0;
+                eroded[x][y] -= moved;
+                eroded[lowest.0][lowest.1] += moved;
+            }
+        }
+    }
+    eroded
+}
+
+/// Runs `iterations` steps of [`thermal_erosion_step`].
+pub fn thermal_erosion(
+    heights: &[Vec<f32>],
+    erosion: &ThermalErosion,
+    iterations: u32,
+) -> Vec<Vec<f32>> {
+    (0..iterations).fold(heights.to_vec(), |heights, _| {
+        thermal_erosion_step(&heights, erosion)
+    })
+}
+
+/// Droplet-based hydraulic erosion: water runs downhill, picking up sediment while it speeds
+/// down steep ground and dropping it again where it slows down, which carves channels and fills
+/// the flats.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ErosionParams {
+    /// How much sediment a droplet can carry per unit of speed, water and descent.
+    pub capacity: f32,
+    /// Fraction of the spare capacity picked up per step, 0-1.
+    pub erosion_rate: f32,
+    /// Fraction of the excess sediment dropped per step, 0-1.
+    pub deposition_rate: f32,
+    /// Fraction of a droplet's water lost per step, 0-1.
+    pub evaporation: f32,
+    /// How much a droplet keeps its direction instead of following the slope, 0-1.
+    pub inertia: f32,
+    /// Steps after which a droplet stops even if it still has water.
+    pub max_steps: usize,
+}
+
+impl Default for ErosionParams {
+    fn default() -> Self {
+        Self {
+            capacity: 4.0,
+            erosion_rate: 0.3,
+            deposition_rate: 0.3,
+            evaporation: 0.02,
+            inertia: 0.05,
+            max_steps: 64,
+        }
+    }
+}
+
+/// Height and gradient at a point between cells, bilinearly interpolated from the four cells
+/// around it.
+fn sample(heights: &[Vec<f32>], point: Vec2) -> (f32, Vec2) {
+    let (x, y) = (point.x as usize, point.y as usize);
+    let (u, v) = (point.x - x as f32, point.y - y as f32);
+    let (h00, h10) = (heights[x][y], heights[x + 1][y]);
+    let (h01, h11) = (heights[x][y + 1], heights[x + 1][y + 1]);
+
+    let height =
+        h00 * (1.0 - u) * (1.0 - v) + h10 * u * (1.0 - v) + h01 * (1.0 - u) * v + h11 * u * v;
+    let gradient = Vec2::new(
+        (h10 - h00) * (1.0 - v) + (h11 - h01) * v,
+        (h01 - h00) * (1.0 - u) + (h11 - h10) * u,
+    );
+    (height, gradient)
+}
+
+/// Adds `amount` of height around a point between cells, spread over the four cells around it
+/// by the same weights [`sample`] reads them with.
+fn deposit(heights: &mut [Vec<f32>], point: Vec2, amount: f32) {
+    let (x, y) = (point.x as usize, point.y as usize);
+    let (u, v) = (point.x - x as f32, point.y - y as f32);
+    heights[x][y] += amount * (1.0 - u) * (1.0 - v);
+    heights[x + 1][y] += amount * u * (1.0 - v);
+    heights[x][y + 1] += amount * (1.0 - u) * v;
+    heights[x + 1][y + 1] += amount * u * v;
+}
+
+/// Runs `droplets` droplets of hydraulic erosion over the heights. The droplets start at
+/// positions drawn from `seed`, so the same seed always erodes the same way. Sediment only
+/// moves, apart from what droplets still carry when they leave the tile.
+pub fn hydraulic_erode(
+    heights: &mut [Vec<f32>],
+    droplets: usize,
+    seed: isize,
+    params: ErosionParams,
+) {
+    const GRAVITY: f32 = 4.0;
+    const MIN_CAPACITY: f32 = 0.01;
+
+    let size = heights.len();
+    if size < 2 {
+        return;
+    }
+    // Droplets sample the four cells around them, so they stay one cell inside the far edges.
+    let limit = (size - 1) as f32;
+
+    // SplitMix64, so the droplets don't depend on the `rand` version.
+    let mut state = seed as u64;
+    let mut random = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mix = state;
+        mix = (mix ^ (mix >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mix = (mix ^ (mix >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        mix ^= mix >> 31;
+        (mix >> 40) as f32 / (1u64 << 24) as f32
+    };
+
+    for _ in 0..droplets {
+        let mut position = Vec2::new(random(), random()) * limit;
+        let mut direction = Vec2::ZERO;
+        let (mut speed, mut water, mut sediment) = (1.0, 1.0, 0.0);
+
+        for _ in 0..params.max_steps {
+            let (height, gradient) = sample(heights, position);
+
+            direction = direction * params.inertia - gradient * (1.0 - params.inertia);
+            if direction.length_squared() == 0.0 {
+                break;
+            }
+            direction = direction.normalize();
+
+            let next = position + direction;
+            if !(0.0..limit).contains(&next.x) || !(0.0..limit).contains(&next.y) {
+                // Washed off the tile along with its sediment.
+                sediment = 0.0;
+                break;
+            }
+
+            let descent = height - sample(heights, next).0;
+            let capacity = (descent * speed * water * params.capacity).max(MIN_CAPACITY);
+
+            if descent < 0.0 || sediment > capacity {
+                // Uphill, fill the dip behind the droplet but no higher than the next cell.
+                let amount = if descent < 0.0 {
+                    sediment.min(-descent)
+                } else {
+                    (sediment - capacity) * params.deposition_rate
+                };
+                sediment -= amount;
+                deposit(heights, position, amount);
+            } else {
+                // Never dig deeper than the descent, or the droplet would carve a pit.
+                let amount = ((capacity - sediment) * params.erosion_rate).min(descent);
+                sediment += amount;
+                deposit(heights, position, -amount);
+            }
+
+            speed = (speed * speed + descent * GRAVITY).max(0.0).sqrt();
+            water *= 1.0 - params.evaporation;
+            position = next;
+        }
+
+        // Whatever a droplet still carries settles where it stopped.
+        deposit(heights, position, sediment);
+    }
+}
+
+/// Most iterations the animation goes through.
+const MAX_ITERATIONS: u32 = 200;
+
+/// Time between animation frames while playing.
+const FRAME: Duration = Duration::from_millis(50);
+
+pub type TileQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        &'static mut TileHeights,
+        &'static TileLevel,
+        &'static Handle<TileMaterial>,
+    ),
+    With<Tile>,
+>;
+
+/// The single tile's heights and what it takes to redraw them, for systems that take over the
+/// tile to erode it.
+#[derive(SystemParam)]
+pub struct TileRedraw<'w, 's> {
+    pub tiles: TileQuery<'w, 's>,
+    materials: Res<'w, Assets<TileMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+    pub coloring: Coloring<'w>,
+}
+
+impl TileRedraw<'_, '_> {
+    /// Replaces a tile's heights and redraws its texture from them.
+    pub fn show_heights(&mut self, entity: Entity, heights: Vec<Vec<f32>>) {
+        let Ok((_, &position, mut tile_heights, _, material)) = self.tiles.get_mut(entity) else {
+            return;
+        };
+
+        let texture = self
+            .materials
+            .get(material)
+            .and_then(|material| material.base.base_color_texture.as_ref());
+        if let Some(image) = texture.and_then(|texture| self.images.get_mut(texture)) {
+            image.data = colorize(&heights, position, &self.coloring.settings());
+        }
+        tile_heights.0 = heights;
+    }
+}
+
+#[derive(Default)]
+pub struct ErosionAnimation {
+    enabled: bool,
+    playing: bool,
+    iterations: u32,
+    erosion: ThermalErosion,
+    timer: Timer,
+    /// The tile being animated and its heights before any erosion.
+    base: Option<(Entity, Vec<Vec<f32>>)>,
+    /// The most recently shown step, reused when the animation moves forward.
+    shown: Option<(u32, ThermalErosion, Vec<Vec<f32>>)>,
+    hydraulic: ErosionParams,
+    droplets: usize,
+}
+
+/// Shows the single tile after a chosen number of erosion iterations, either scrubbed with the
+/// slider or played back over time. Turning the animation off restores the uneroded heights.
+/// Hydraulic erosion is applied to the tile once per click instead, and stays.
+pub fn erosion_ui(
+    mut contexts: EguiContexts,
+    mut state: Local<ErosionAnimation>,
+    mut redraw: TileRedraw,
+    time: Res<Time>,
+    settings: Res<GenSettings>,
+    mut geologic: ResMut<GeologicTime>,
+) {
+    const DEFAULT_DROPLETS: usize = 50_000;
+
+    let state = &mut *state;
+    let (previous_iterations, previous_erosion) = (state.iterations, state.erosion);
+    if state.droplets == 0 {
+        state.droplets = DEFAULT_DROPLETS;
+        state.hydraulic = ErosionParams::default();
+    }
+    let mut apply_hydraulic = false;
+    let mut new_geologic = *geologic;
+
+    egui::Window::new("Erosion")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut state.enabled, "Show Erosion Animation");
+            ui.add(egui::Slider::new(&mut state.erosion.talus, 0.5..=20.0).prefix("Talus Slope: "));
+            ui.add(egui::Slider::new(&mut state.erosion.rate, 0.05..=1.0).prefix("Rate: "));
+            ui.add(
+                egui::Slider::new(&mut state.iterations, 0..=MAX_ITERATIONS).prefix("Iterations: "),
+            );
+            let label = if state.playing { "Pause" } else { "Play" };
+            if ui.button(label).clicked() {
+                state.playing = !state.playing;
+                if state.iterations == MAX_ITERATIONS {
+                    state.iterations = 0;
+                }
+                state.timer = Timer::new(FRAME, TimerMode::Repeating);
+            }
+
+            ui.separator();
+            ui.label("Hydraulic Erosion");
+            let hydraulic = &mut state.hydraulic;
+            ui.add(
+                egui::Slider::new(&mut state.droplets, 1_000..=500_000)
+                    .logarithmic(true)
+                    .prefix("Droplets: "),
+            );
+            ui.add(egui::Slider::new(&mut hydraulic.capacity, 0.5..=16.0).prefix("Capacity: "));
+            ui.add(egui::Slider::new(&mut hydraulic.erosion_rate, 0.01..=1.0).prefix("Erosion: "));
+            ui.add(
+                egui::Slider::new(&mut hydraulic.deposition_rate, 0.01..=1.0)
+                    .prefix("Deposition: "),
+            );
+            ui.add(
+                egui::Slider::new(&mut hydraulic.evaporation, 0.001..=0.2).prefix("Evaporation: "),
+            );
+            ui.add(egui::Slider::new(&mut hydraulic.inertia, 0.0..=0.9).prefix("Inertia: "));
+            ui.add(egui::Slider::new(&mut hydraulic.max_steps, 8..=256).prefix("Max Steps: "));
+            // The animation owns the tile's heights while it runs.
+            apply_hydraulic = ui
+                .add_enabled(!state.enabled, egui::Button::new("Apply Hydraulic Erosion"))
+                .on_disabled_hover_text("Turn off the erosion animation first.")
+                .clicked();
+
+            ui.separator();
+            geologic::geologic_time_ui(ui, &mut new_geologic);
+        });
+    geologic.set_if_neq(new_geologic);
+
+    if apply_hydraulic {
+        let tile = redraw
+            .tiles
+            .iter()
+            .find(|(_, _, _, level, _)| level.0 == 0)
+            .map(|(entity, _, heights, _, _)| (entity, heights.0.clone()));
+        if let Some((entity, mut eroded)) = tile {
+            hydraulic_erode(
+                &mut eroded,
+                state.droplets,
+                settings.noise_seed(),
+                state.hydraulic,
+            );
+            redraw.show_heights(entity, eroded);
+        }
+    }
+
+    if state.enabled && state.playing {
+        let frames = state.timer.tick(time.delta()).times_finished_this_tick();
+        state.iterations = (state.iterations + frames).min(MAX_ITERATIONS);
+        state.playing = state.iterations < MAX_ITERATIONS;
+    }
+
+    // The animated tile was replaced, e.g. by a new seed. Start over from the new one.
+    if let Some((entity, _)) = state.base {
+        if redraw.tiles.get(entity).is_err() {
+            state.base = None;
+            state.shown = None;
+        }
+    }
+
+    if !state.enabled {
+        // Hand the tile back as it was.
+        if let Some((entity, base)) = state.base.take() {
+            state.shown = None;
+            redraw.show_heights(entity, base);
+        }
+        return;
+    }
+
+    if state.base.is_none() {
+        let Some((entity, _, heights, _, _)) =
+            redraw.tiles.iter().find(|(_, _, _, level, _)| level.0 == 0)
+        else {
+            return;
+        };
+        state.base = Some((entity, heights.0.clone()));
+        state.shown = None;
+    } else if state.iterations == previous_iterations && state.erosion == previous_erosion {
+        return;
+    }
+
+    let ErosionAnimation {
+        iterations,
+        erosion,
+        base: Some((entity, base)),
+        shown,
+        ..
+    } = state
+    else {
+        return;
+    };
+
+    // Continue from the step on screen when only moving forward with the same settings.
+    let (start, from) = match shown.take() {
+        Some((step, shown_with, heights)) if step <= *iterations && shown_with == *erosion => {
+            (step, heights)
+        }
+        _ => (0, base.clone()),
+    };
+    let eroded = thermal_erosion(&from, erosion, *iterations - start);
+    *shown = Some((*iterations, *erosion, eroded.clone()));
+    redraw.show_heights(*entity, eroded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 65;
+
+    /// A bowl with ripples, so droplets run into the middle instead of off the tile.
+    fn bowl() -> Vec<Vec<f32>> {
+        (0..SIZE)
+            .map(|x| {
+                (0..SIZE)
+                    .map(|y| {
+                        let (fx, fy) = (x as f32 / 64.0, y as f32 / 64.0);
+                        let ripple = 0.3 * (fx * 37.0).sin() * (fy * 23.0).cos();
+                        4.0 * Vec2::new(fx - 0.5, fy - 0.5).length() + ripple
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Variance of the heights within 8 cells of the bottom of the bowl.
+    fn valley_variance(heights: &[Vec<f32>]) -> f32 {
+        let center = (SIZE / 2) as f32;
+        let valley: Vec<f32> = (0..SIZE)
+            .flat_map(|x| (0..SIZE).map(move |y| (x, y)))
+            .filter(|&(x, y)| Vec2::new(x as f32 - center, y as f32 - center).length() <= 8.0)
+            .map(|(x, y)| heights[x][y])
+            .collect();
+        let mean = valley.iter().sum::<f32>() / valley.len() as f32;
+        valley.iter().map(|h| (h - mean).powi(2)).sum::<f32>() / valley.len() as f32
+    }
+
+    fn total(heights: &[Vec<f32>]) -> f32 {
+        heights.iter().flatten().sum()
+    }
+
+    #[test]
+    fn hydraulic_erosion_fills_valleys_and_keeps_the_mass() {
+        let before = bowl();
+        let mut after = before.clone();
+        hydraulic_erode(&mut after, 5_000, 7, ErosionParams::default());
+
+        assert!(valley_variance(&after) < valley_variance(&before) / 2.0);
+        assert!((total(&after) - total(&before)).abs() < total(&before) * 0.01);
+    }
+
+    #[test]
+    fn hydraulic_erosion_is_deterministic_per_seed() {
+        let erode = |seed| {
+            let mut heights = bowl();
+            hydraulic_erode(&mut heights, 1_000, seed, ErosionParams::default());
+            heights
+        };
+
+        assert_eq!(erode(3), erode(3));
+        assert_ne!(erode(3), erode(4));
+    }
+}
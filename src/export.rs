@@ -0,0 +1,401 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::system::SystemParam, prelude::*, render::view::screenshot::ScreenshotManager,
+    window::PrimaryWindow,
+};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use serde::Serialize;
+
+use crate::{
+    annotations::{self, MapAnnotations},
+    climate,
+    coloring::{colorize, logistic, Coloring},
+    contours::{self, Contours},
+    palette::ColorPalette,
+    relief::{self, ShadedReliefExport},
+    shore, slope,
+    stats::TileStats,
+    svg, tilemap, voxel, walkability, GenSettings, Position, Tile, TileHeights, TileLevel,
+};
+
+/// The `.json` written next to every exported image.
+#[derive(Serialize)]
+pub struct Sidecar<'a> {
+    pub settings: &'a GenSettings,
+    pub position: Position,
+    pub stats: TileStats,
+}
+
+/// What the exported image shows.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ExportAspect {
+    /// The generated square texture, one pixel per cell. This is the one to feed into other
+    /// tools.
+    #[default]
+    Native,
+    /// A screenshot of the window, stretched and framed exactly as displayed and including the
+    /// UI. Meant for sharing, not for reading heights back.
+    Displayed,
+}
+
+impl ExportAspect {
+    pub const ALL: [ExportAspect; 2] = [ExportAspect::Native, ExportAspect::Displayed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportAspect::Native => "Native",
+            ExportAspect::Displayed => "Displayed",
+        }
+    }
+}
+
+/// Writes `path` with `write`, and next to it the `.json` sidecar describing how it was made.
+/// Every exported image or asset is saved through here, so none comes without its sidecar.
+pub fn save_with_sidecar(
+    path: &Path,
+    sidecar: &Sidecar,
+    write: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    write(path)?;
+    fs::write(
+        path.with_extension("json"),
+        serde_json::to_string_pretty(sidecar)?,
+    )?;
+    Ok(())
+}
+
+/// Writes `<base>.png` with the tile colored as shown, framed by any enabled
+/// [`MapAnnotations`], and `<base>.json` describing how it was made.
+pub fn export_tile(
+    base: &Path,
+    heights: &[Vec<f32>],
+    rgba: Vec<u8>,
+    annotations: &MapAnnotations,
+    sidecar: &Sidecar,
+) -> Result<(), Box<dyn Error>> {
+    let size = heights.len() as u32;
+    let image = image::RgbaImage::from_raw(size, size, rgba).ok_or("image buffer size mismatch")?;
+    let image = annotations::annotate(&image, heights, annotations);
+    save_with_sidecar(&base.with_extension("png"), sidecar, |path| {
+        image.save(path)?;
+        Ok(())
+    })
+}
+
+/// Writes the raw heights as a 16-bit grayscale PNG, stretched so the lowest cell on the tile is
+/// black and the highest white. The heights are taken before the logistic curve and coloring, so
+/// they can be read back into a mesh at full precision.
+pub fn write_heightmap_png(path: &Path, heights: &[Vec<f32>]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let (min, max) = heights
+        .iter()
+        .flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &h| {
+            (min.min(h), max.max(h))
+        });
+    let range = (max - min).max(f32::EPSILON);
+
+    let size = heights.len() as u32;
+    let pixels = heights
+        .iter()
+        .flatten()
+        .map(|&h| ((h - min) / range * u16::MAX as f32).round() as u16)
+        .collect();
+    let image = image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(size, size, pixels)
+        .ok_or("image buffer size mismatch")?;
+    image.save(path)?;
+    Ok(())
+}
+
+/// Writes `heightmap_<seed>_<roughness>.png` with the raw heights and
+/// `terrain_<seed>_<roughness>.png` with the colors as shown into `dir`, each with its sidecar,
+/// returning their paths. The seed is the one the noise was sampled with, so a run seed shows.
+pub fn export_heightmap_and_terrain(
+    dir: &Path,
+    sidecar: &Sidecar,
+    heights: &[Vec<f32>],
+    rgba: Vec<u8>,
+) -> Result<[PathBuf; 2], Box<dyn Error>> {
+    let settings = sidecar.settings;
+    let suffix = format!("{}_{:.2}", settings.noise_seed(), settings.roughness);
+    let heightmap = dir.join(format!("heightmap_{}.png", suffix));
+    let terrain = dir.join(format!("terrain_{}.png", suffix));
+
+    save_with_sidecar(&heightmap, sidecar, |path| {
+        write_heightmap_png(path, heights)
+    })?;
+    let size = heights.len() as u32;
+    let image = image::RgbaImage::from_raw(size, size, rgba).ok_or("image buffer size mismatch")?;
+    save_with_sidecar(&terrain, sidecar, |path| {
+        image.save(path)?;
+        Ok(())
+    })?;
+
+    Ok([heightmap, terrain])
+}
+
+/// Saves a screenshot of `window` as `<base>.png` next to the usual sidecar. The screenshot is
+/// written a frame or two later, once it has been rendered.
+fn export_displayed(
+    base: &Path,
+    window: Entity,
+    screenshots: &mut ScreenshotManager,
+    sidecar: &Sidecar,
+) -> Result<(), Box<dyn Error>> {
+    save_with_sidecar(&base.with_extension("png"), sidecar, |path| {
+        screenshots.save_screenshot_to_disk(window, path)?;
+        Ok(())
+    })
+}
+
+/// What the exports are made from, for [`export_ui`].
+#[derive(SystemParam)]
+pub struct ExportSources<'w, 's> {
+    tiles: Query<'w, 's, (&'static Position, &'static TileHeights, &'static TileLevel), With<Tile>>,
+    settings: Res<'w, GenSettings>,
+    contour_settings: Res<'w, Contours>,
+    annotations: ResMut<'w, MapAnnotations>,
+    shaded_relief: ResMut<'w, ShadedReliefExport>,
+    screenshots: ResMut<'w, ScreenshotManager>,
+    window: Query<'w, 's, Entity, With<PrimaryWindow>>,
+}
+
+pub fn export_ui(
+    mut contexts: EguiContexts,
+    mut base: Local<String>,
+    mut aspect: Local<ExportAspect>,
+    mut tilemap_resolution: Local<usize>,
+    mut voxel_height: Local<u16>,
+    sources: ExportSources,
+    coloring: Coloring,
+) {
+    const DEFAULT_BASE: &str = "export/terrain";
+    const DEFAULT_TILEMAP_RESOLUTION: usize = 64;
+    const DEFAULT_VOXEL_HEIGHT: u16 = 256;
+
+    let ExportSources {
+        tiles,
+        settings,
+        contour_settings,
+        mut annotations,
+        mut shaded_relief,
+        mut screenshots,
+        window,
+    } = sources;
+
+    if base.is_empty() {
+        *base = DEFAULT_BASE.to_string();
+    }
+    if *tilemap_resolution == 0 {
+        *tilemap_resolution = DEFAULT_TILEMAP_RESOLUTION;
+    }
+    if *voxel_height == 0 {
+        *voxel_height = DEFAULT_VOXEL_HEIGHT;
+    }
+
+    egui::Window::new("Export")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut *base);
+            });
+            egui::ComboBox::from_label("Aspect")
+                .selected_text(aspect.name())
+                .show_ui(ui, |ui| {
+                    for mode in ExportAspect::ALL {
+                        ui.selectable_value(&mut *aspect, mode, mode.name());
+                    }
+                });
+
+            let Some((&position, heights, _)) = tiles.iter().find(|(_, _, level)| level.0 == 0)
+            else {
+                ui.label("No tile to export.");
+                return;
+            };
+
+            // Only built for the export that was clicked, the stats take a while on big tiles.
+            let sidecar = || Sidecar {
+                settings: &*settings,
+                position,
+                stats: TileStats::compute(&heights.0, position, &coloring.settings()),
+            };
+
+            if ui.button("Export").clicked() {
+                let color_settings = coloring.settings();
+                let sidecar = sidecar();
+                let result = match *aspect {
+                    ExportAspect::Native => {
+                        let rgba = colorize(&heights.0, position, &color_settings);
+                        export_tile(Path::new(&*base), &heights.0, rgba, &annotations, &sidecar)
+                    }
+                    ExportAspect::Displayed => match window.get_single() {
+                        Ok(window) => {
+                            export_displayed(Path::new(&*base), window, &mut screenshots, &sidecar)
+                        }
+                        Err(err) => Err(err.into()),
+                    },
+                };
+
+                match result {
+                    Ok(()) => info!("Exported {}.png", *base),
+                    Err(err) => error!("Failed to export {}: {}", *base, err),
+                }
+            }
+            // Screenshots already come framed by the window.
+            ui.add_enabled_ui(*aspect == ExportAspect::Native, |ui| {
+                annotations::annotations_ui(ui, &mut annotations);
+            });
+
+            ui.separator();
+            relief::shaded_relief_ui(ui, &mut shaded_relief);
+            if ui.button("Export Shaded Relief").clicked() {
+                let path = Path::new(&*base).with_extension("relief.png");
+                let settings = coloring.settings();
+                let write = |path: &Path| {
+                    relief::write_png(path, &heights.0, position, &settings, &shaded_relief)
+                };
+                match save_with_sidecar(&path, &sidecar(), write) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+
+            ui.separator();
+            ui.add(
+                egui::Slider::new(&mut *tilemap_resolution, 4..=heights.0.len())
+                    .prefix("Tilemap Resolution: "),
+            );
+            if ui.button("Export Tilemap").clicked() {
+                let tilemap =
+                    tilemap::to_tilemap(&heights.0, &coloring.settings(), *tilemap_resolution);
+                let path = Path::new(&*base).with_extension("csv");
+                match save_with_sidecar(&path, &sidecar(), |path| {
+                    tilemap::write_csv(path, &tilemap)
+                }) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+            if ui.button("Export Water Distance").clicked() {
+                let distances =
+                    shore::tile_distance_to_water(&heights.0, coloring.settings().water_level.0);
+                let path = Path::new(&*base).with_extension("water.csv");
+                match save_with_sidecar(&path, &sidecar(), |path| {
+                    shore::write_csv(path, &distances)
+                }) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+
+            if ui.button("Export Walkability").clicked() {
+                let settings = coloring.settings();
+                let slopes = slope::slope(&heights.0);
+                let heights: Vec<Vec<f32>> = heights
+                    .0
+                    .iter()
+                    .map(|row| row.iter().map(|&f| logistic(f)).collect())
+                    .collect();
+                let mask = walkability::walkable_mask(
+                    &heights,
+                    &slopes,
+                    settings.water_level.0,
+                    settings.walkability.max_slope,
+                );
+                let path = Path::new(&*base).with_extension("walkable.png");
+                match save_with_sidecar(&path, &sidecar(), |path| {
+                    walkability::write_png(path, &mask)
+                }) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+
+            // Uses the interval of the contour overlay, whether or not it's shown.
+            if ui.button("Export SVG").clicked() {
+                let settings = coloring.settings();
+                let heights: Vec<Vec<f32>> = heights
+                    .0
+                    .iter()
+                    .map(|row| row.iter().map(|&f| logistic(f)).collect())
+                    .collect();
+                let levels: Vec<f32> = contours::levels(contour_settings.interval).collect();
+                let palette = if settings.crossfade.enabled {
+                    settings.crossfade.blended()
+                } else {
+                    ColorPalette::SUMMER
+                };
+                let document = svg::to_svg(&heights, &levels, &palette, settings.water_level.0);
+                let path = Path::new(&*base).with_extension("svg");
+                match save_with_sidecar(&path, &sidecar(), |path| svg::write_svg(path, &document)) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+
+            ui.separator();
+            ui.add(egui::Slider::new(&mut *voxel_height, 16..=1024).prefix("Voxel Height: "));
+            if ui.button("Export Voxels").clicked() {
+                let settings = coloring.settings();
+                let heights: Vec<Vec<f32>> = heights
+                    .0
+                    .iter()
+                    .map(|row| row.iter().map(|&f| logistic(f)).collect())
+                    .collect();
+                let water_level = settings.water_level.0;
+                let biomes =
+                    climate::biome_field(&heights, position, water_level, &settings.climate);
+                let voxels = voxel::to_voxels(&heights, &biomes, *voxel_height, water_level);
+                let path = Path::new(&*base).with_extension("voxels.ron");
+                match save_with_sidecar(&path, &sidecar(), |path| voxels.save(path)) {
+                    Ok(()) => info!("Exported {}", path.display()),
+                    Err(err) => error!("Failed to export {}: {}", path.display(), err),
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring::ColorSettings;
+
+    #[test]
+    fn every_export_gets_a_sidecar() {
+        let dir = std::env::temp_dir().join("diamond-square-export-sidecar");
+        let heights = vec![vec![0.0, 0.5, 1.0]; 3];
+        let settings = GenSettings::default();
+        let position = Position((0, 0));
+        let sidecar = Sidecar {
+            settings: &settings,
+            position,
+            stats: TileStats::compute(&heights, position, &ColorSettings::default()),
+        };
+
+        let path = dir.join("terrain.walkable.png");
+        let mask = vec![vec![true; 3]; 3];
+        save_with_sidecar(&path, &sidecar, |path| walkability::write_png(path, &mask)).unwrap();
+        assert!(path.exists());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("terrain.walkable.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["settings"]["seed"], settings.seed);
+        assert_eq!(json["position"], serde_json::json!([0, 0]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts,
+    egui::{self, load::SizedTexture},
+};
+
+use crate::{
+    coloring::{colorize, Coloring},
+    generate_heightmap, GenSettings, Position, RegenerateEvent,
+};
+
+const FAVORITES_PATH: &str = "favorites.ron";
+
+/// Thumbnails are generated at `2^THUMBNAIL_NODE_SIZE + 1` pixels regardless of the seed's size.
+const THUMBNAIL_NODE_SIZE: u32 = 6;
+
+/// Bookmarked seeds with their parameters, kept in `favorites.ron` so they survive restarts.
+#[derive(Resource)]
+pub struct Favorites {
+    entries: Vec<GenSettings>,
+    /// One per entry, filled in lazily by the browser.
+    thumbnails: Vec<Handle<Image>>,
+}
+
+impl Default for Favorites {
+    fn default() -> Self {
+        // A missing or unreadable file just means there are no favorites yet.
+        let entries = fs::read_to_string(FAVORITES_PATH)
+            .ok()
+            .and_then(|contents| match ron::from_str(&contents) {
+                Ok(entries) => Some(entries),
+                Err(err) => {
+                    warn!("Ignoring corrupt {}: {}", FAVORITES_PATH, err);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            entries,
+            thumbnails: Vec::new(),
+        }
+    }
+}
+
+impl Favorites {
+    fn save(&self) {
+        let result = ron::to_string(&self.entries)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                fs::write(FAVORITES_PATH, contents).map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = result {
+            error!("Failed to save {}: {}", FAVORITES_PATH, err);
+        }
+    }
+}
+
+fn thumbnail(
+    settings: &GenSettings,
+    coloring: &Coloring,
+    images: &mut Assets<Image>,
+) -> Handle<Image> {
+    let size = 2usize.pow(THUMBNAIL_NODE_SIZE) + 1;
+    let heights = generate_heightmap(
+        Position((0, 0)),
+        settings.roughness,
+        settings.noise_seed(),
+        size,
+    );
+
+    images.add(Image::new(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        colorize(&heights, Position((0, 0)), &coloring.settings()),
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    ))
+}
+
+pub fn favorites_ui(
+    mut contexts: EguiContexts,
+    mut favorites: ResMut<Favorites>,
+    mut settings: ResMut<GenSettings>,
+    mut regenerate: EventWriter<RegenerateEvent>,
+    mut images: ResMut<Assets<Image>>,
+    coloring: Coloring,
+) {
+    // Thumbnails for favorites loaded from disk or added since the last frame.
+    while favorites.thumbnails.len() < favorites.entries.len() {
+        let entry = favorites.entries[favorites.thumbnails.len()];
+        let handle = thumbnail(&entry, &coloring, &mut images);
+        favorites.thumbnails.push(handle);
+    }
+
+    let textures: Vec<_> = favorites
+        .thumbnails
+        .iter()
+        .map(|handle| contexts.add_image(handle.clone_weak()))
+        .collect();
+
+    let mut load = None;
+    let mut remove = None;
+
+    egui::Window::new("Favorites")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if ui.button("Favorite this seed").clicked() && !favorites.entries.contains(&*settings)
+            {
+                favorites.entries.push(*settings);
+                favorites.save();
+            }
+
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    for (index, (entry, &texture)) in
+                        favorites.entries.iter().zip(&textures).enumerate()
+                    {
+                        ui.horizontal(|ui| {
+                            let image = SizedTexture::new(texture, [64.0, 64.0]);
+                            if ui.add(egui::ImageButton::new(image)).clicked() {
+                                load = Some(*entry);
+                            }
+
+                            ui.vertical(|ui| {
+                                ui.label(format!("Seed: {}", entry.seed));
+                                ui.label(format!("Roughness: {:.2}", entry.roughness));
+                                ui.label(format!("Node Size: {}", entry.node_size));
+                                if ui.button("Remove").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                        });
+                    }
+                });
+        });
+
+    if let Some(entry) = load {
+        *settings = entry;
+        regenerate.send(RegenerateEvent);
+    }
+
+    if let Some(index) = remove {
+        favorites.entries.remove(index);
+        let handle = favorites.thumbnails.remove(index);
+        images.remove(&handle);
+        favorites.save();
+    }
+}
@@ -0,0 +1,154 @@
+use std::hash::{DefaultHasher, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Position;
+
+/// Fractional bits of the fixed point heights, i.e. heights are stored as `f * 2^FRACTION_BITS`.
+const FRACTION_BITS: u32 = 16;
+const ONE: i64 = 1 << FRACTION_BITS;
+
+/// The number type the planar diamond-square runs in.
+///
+/// Floating point results may differ in the last bits between architectures and compilers,
+/// which is enough to make two networked clients disagree about the terrain. Fixed point only
+/// uses integer adds, multiplies and shifts, so the heights come out bit-identical everywhere.
+/// The cost is precision: heights are multiples of 2^-16 and every division rounds down, so the
+/// two modes make visibly the same terrain but not the same bits. Torus tiles are always
+/// generated in floating point.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Arithmetic {
+    #[default]
+    Float,
+    Fixed,
+}
+
+impl Arithmetic {
+    pub const ALL: [Arithmetic; 2] = [Arithmetic::Float, Arithmetic::Fixed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Arithmetic::Float => "Floating Point",
+            Arithmetic::Fixed => "Fixed Point",
+        }
+    }
+}
+
+/// [`hash`](crate::hash) in fixed point. The seed is always hashed as 8 little-endian bytes, so
+/// 32-bit and big-endian platforms agree with the usual 64-bit little-endian ones. Clients still
+/// have to be built with the same Rust release, as `DefaultHasher` may change between them.
+fn hash(seed: isize, x: i32, y: i32) -> i64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&(seed as i64).to_le_bytes());
+    hasher.write(&x.to_le_bytes());
+    hasher.write(&y.to_le_bytes());
+    (hasher.finish() % 0xFF) as i64 * ONE / 0xFF
+}
+
+/// [`generate_heightmap`](crate::generate_heightmap) step for step, with every height and the
+/// roughness held as fixed point integers. Only the final heights are converted to `f32`, which
+/// is exact for the range they are in.
+pub fn generate_heightmap(
+    position: Position,
+    roughness: f32,
+    seed: isize,
+    image_size: usize,
+) -> Vec<Vec<f32>> {
+    let mut heightmap: Vec<Vec<i64>> = vec![vec![0; image_size]; image_size];
+
+    let mut chunk_size = image_size - 1;
+    // Rounding a float to an integer is exact and the same everywhere.
+    let mut roughness = (roughness * ONE as f32).round() as i64;
+
+    // A random offset in -roughness..roughness, keyed on the global cell.
+    let (px, py) = position.0;
+    let span = image_size as i32 - 1;
+    // Columns run along global +X and rows along global -Y, like the tiles are laid out.
+    let (ox, oy) = (px * span, (py + 1) * span);
+    let offset = |x: usize, y: usize, roughness: i64| {
+        ((hash(seed, ox + y as i32, oy - x as i32) * 2 - ONE) * roughness) >> FRACTION_BITS
+    };
+    let last = image_size - 1;
+
+    // Set values for all four corners, row 0 being the top edge and column 0 the left.
+    heightmap[0][0] = hash(seed, px, py + 1);
+    heightmap[0][image_size - 1] = hash(seed, px + 1, py + 1);
+    heightmap[image_size - 1][0] = hash(seed, px, py);
+    heightmap[image_size - 1][image_size - 1] = hash(seed, px + 1, py);
+
+    while chunk_size > 1 {
+        let half = chunk_size / 2;
+
+        // square step
+        for y in (0..image_size - 1).step_by(chunk_size) {
+            for x in (0..image_size - 1).step_by(chunk_size) {
+                let sum = heightmap[x][y]
+                    + heightmap[x + chunk_size][y]
+                    + heightmap[x][y + chunk_size]
+                    + heightmap[x + chunk_size][y + chunk_size];
+                heightmap[x + half][y + half] =
+                    sum.div_euclid(4) + offset(x + half, y + half, roughness);
+            }
+        }
+
+        // diamond step
+        for y in (0..image_size).step_by(half) {
+            for x in ((y + half) % chunk_size..image_size).step_by(chunk_size) {
+                if x == 0 || x == last {
+                    let sum = heightmap[x][y - half] + heightmap[x][y + half];
+                    heightmap[x][y] = sum.div_euclid(2) + offset(x, y, roughness);
+                    continue;
+                }
+                if y == 0 || y == last {
+                    let sum = heightmap[x - half][y] + heightmap[x + half][y];
+                    heightmap[x][y] = sum.div_euclid(2) + offset(x, y, roughness);
+                    continue;
+                }
+
+                let mut neighbors = 0;
+                let mut neighbor_sum = 0;
+
+                if x > half {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x - half][y];
+                }
+                if y > half {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x][y - half];
+                }
+                if x + half < image_size - 1 {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x + half][y];
+                }
+                if y + half < image_size - 1 {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x][y + half];
+                }
+
+                heightmap[x][y] = neighbor_sum.div_euclid(neighbors) + offset(x, y, roughness);
+            }
+        }
+
+        chunk_size /= 2;
+        roughness /= 2;
+    }
+
+    heightmap
+        .into_iter()
+        .map(|row| row.into_iter().map(|h| h as f32 / ONE as f32).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horizontal_neighbours_share_their_edge() {
+        let left = generate_heightmap(Position((0, 0)), 2.0, 7, 65);
+        let right = generate_heightmap(Position((1, 0)), 2.0, 7, 65);
+        for (a, b) in left.iter().zip(&right) {
+            assert_eq!(a[64], b[0]);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::{
+    erosion::{self, ErosionParams, ThermalErosion, TileRedraw},
+    palette::{ColorPalette, PaletteCrossfade},
+    GenSettings,
+};
+
+/// Droplets of hydraulic erosion per step. Small, so a step fits in a frame.
+const DROPLETS_PER_STEP: usize = 2_000;
+
+/// A landscape aging on screen: every frame the single tile goes through one more step of
+/// thermal and hydraulic erosion, while the palette moves from bare rock through soil to
+/// vegetation. Turning it off restores the tile and palette as they were before.
+///
+/// It owns the tile's heights while running, like the erosion animation, so the two shouldn't be
+/// used together.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct GeologicTime {
+    pub enabled: bool,
+    /// Steps from fresh terrain to the fully aged landscape.
+    pub total_steps: usize,
+    /// Steps taken so far.
+    pub step: usize,
+}
+
+impl Default for GeologicTime {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            total_steps: 300,
+            step: 0,
+        }
+    }
+}
+
+impl GeologicTime {
+    /// How far along the aging is, 0-1.
+    pub fn progress(&self) -> f32 {
+        (self.step as f32 / self.total_steps.max(1) as f32).min(1.0)
+    }
+
+    /// The palette crossfade at the current step: bare rock to soil in the first half, soil to
+    /// vegetation in the second.
+    pub fn crossfade(&self, base: &PaletteCrossfade) -> PaletteCrossfade {
+        let progress = self.progress();
+        let (a, b, t) = if progress < 0.5 {
+            (ColorPalette::BARE_ROCK, ColorPalette::SOIL, progress * 2.0)
+        } else {
+            (
+                ColorPalette::SOIL,
+                ColorPalette::SUMMER,
+                progress * 2.0 - 1.0,
+            )
+        };
+        PaletteCrossfade {
+            enabled: true,
+            a,
+            b,
+            t,
+            autoplay: false,
+            ..*base
+        }
+    }
+}
+
+/// Moves the step forward once per frame and the palette along with it. Runs before
+/// [`erode_geologic_time`] so the erosion and the colors of a step land in the same frame.
+pub fn advance_geologic_time(
+    mut geologic: ResMut<GeologicTime>,
+    mut crossfade: ResMut<PaletteCrossfade>,
+    mut saved: Local<Option<PaletteCrossfade>>,
+) {
+    if !geologic.enabled {
+        // Hand the palette back as it was, and start from fresh terrain next time.
+        if let Some(saved) = saved.take() {
+            *crossfade = saved;
+            geologic.step = 0;
+        }
+        return;
+    }
+
+    if saved.is_none() {
+        *saved = Some(*crossfade);
+    }
+    if geologic.step < geologic.total_steps {
+        geologic.step += 1;
+    }
+
+    let aged = geologic.crossfade(&saved.unwrap_or(*crossfade));
+    crossfade.set_if_neq(aged);
+}
+
+#[derive(Default)]
+pub struct GeologicState {
+    /// The tile being aged and its heights before any erosion.
+    base: Option<(Entity, Vec<Vec<f32>>)>,
+    /// The step the tile currently shows.
+    shown: usize,
+}
+
+/// One step of aging: a pass of thermal erosion, then a round of hydraulic erosion with
+/// droplets drawn from `seed`.
+pub fn age(heights: &[Vec<f32>], seed: isize) -> Vec<Vec<f32>> {
+    let mut heights = erosion::thermal_erosion_step(heights, &ThermalErosion::default());
+    erosion::hydraulic_erode(
+        &mut heights,
+        DROPLETS_PER_STEP,
+        seed,
+        ErosionParams::default(),
+    );
+    heights
+}
+
+/// Erodes the tile up to the current step. The colors are left to
+/// [`recolor_tiles`](crate::coloring::recolor_tiles) when the palette moved this frame anyway.
+pub fn erode_geologic_time(
+    mut state: Local<GeologicState>,
+    mut redraw: TileRedraw,
+    geologic: Res<GeologicTime>,
+    settings: Res<GenSettings>,
+) {
+    // The aged tile was replaced, e.g. by a new seed. Start over from the new one.
+    if let Some((entity, _)) = state.base {
+        if redraw.tiles.get(entity).is_err() {
+            state.base = None;
+        }
+    }
+
+    if !geologic.enabled {
+        // Hand the tile back as it was.
+        if let Some((entity, base)) = state.base.take() {
+            redraw.show_heights(entity, base);
+        }
+        return;
+    }
+
+    let Some((entity, base)) = &state.base else {
+        let Some((entity, _, heights, _, _)) =
+            redraw.tiles.iter().find(|(_, _, _, level, _)| level.0 == 0)
+        else {
+            return;
+        };
+        state.base = Some((entity, heights.0.clone()));
+        state.shown = 0;
+        return;
+    };
+    let entity = *entity;
+
+    // Restarted, age the original tile again.
+    let mut heights = match redraw.tiles.get(entity) {
+        Ok(_) if geologic.step < state.shown => base.clone(),
+        Ok((_, _, heights, _, _)) => heights.0.clone(),
+        Err(_) => return,
+    };
+    if geologic.step < state.shown {
+        state.shown = 0;
+    }
+    if geologic.step == state.shown {
+        return;
+    }
+
+    for step in state.shown..geologic.step {
+        heights = age(&heights, settings.noise_seed().wrapping_add(step as isize));
+    }
+    state.shown = geologic.step;
+
+    if redraw.coloring.is_changed() {
+        if let Ok((_, _, mut tile_heights, _, _)) = redraw.tiles.get_mut(entity) {
+            tile_heights.0 = heights;
+        }
+    } else {
+        redraw.show_heights(entity, heights);
+    }
+}
+
+/// The geologic time section of the erosion window.
+pub fn geologic_time_ui(ui: &mut egui::Ui, geologic: &mut GeologicTime) {
+    ui.checkbox(&mut geologic.enabled, "Geologic Time");
+    ui.add(egui::Slider::new(&mut geologic.total_steps, 10..=2_000).prefix("Total Steps: "));
+    ui.add(egui::ProgressBar::new(geologic.progress()).text(format!(
+        "Step {} of {}",
+        geologic.step.min(geologic.total_steps),
+        geologic.total_steps
+    )));
+    if ui.button("Restart").clicked() {
+        geologic.step = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diamond_square::DiamondSquare;
+
+    use super::*;
+
+    /// Mean height difference between neighboring cells.
+    fn roughness(heights: &[Vec<f32>]) -> f32 {
+        let size = heights.len();
+        let mut total = 0.0;
+        for x in 0..size - 1 {
+            for y in 0..size - 1 {
+                total += (heights[x + 1][y] - heights[x][y]).abs();
+                total += (heights[x][y + 1] - heights[x][y]).abs();
+            }
+        }
+        total / (2 * (size - 1) * (size - 1)) as f32
+    }
+
+    #[test]
+    fn aging_makes_the_terrain_smoother_every_step() {
+        let seed = 3;
+        let mut heights = DiamondSquare::new(257)
+            .seed(seed)
+            .roughness(2.0)
+            .generate()
+            .to_rows();
+        let fresh = roughness(&heights);
+
+        let mut previous = fresh;
+        for step in 0..40 {
+            heights = age(&heights, seed.wrapping_add(step));
+            let current = roughness(&heights);
+            assert!(
+                current < previous,
+                "step {}: {} -> {}",
+                step,
+                previous,
+                current
+            );
+            previous = current;
+        }
+        assert!(previous < fresh * 0.9);
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{Position, Tile, TileLevel};
+
+/// Outlines every tile and labels it with its [`Position`], for debugging streaming and edge
+/// matching.
+#[derive(Resource, Default)]
+pub struct ShowTileGrid(pub bool);
+
+/// Outline colors per clipmap level, repeating past the last one.
+const LEVEL_COLORS: [Color; 4] = [Color::YELLOW, Color::CYAN, Color::FUCHSIA, Color::ORANGE];
+
+pub fn draw_tile_grid(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    show: Res<ShowTileGrid>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tiles: Query<(&Position, &TileLevel, &Transform), With<Tile>>,
+) {
+    if !show.0 {
+        return;
+    }
+
+    // Outline each quad from its own transform, so a tile that is placed or sized differently
+    // from its position shows up as an overlap or a gap.
+    for (_, level, transform) in tiles.iter() {
+        gizmos.rect(
+            transform.translation.truncate().extend(0.01),
+            Quat::IDENTITY,
+            transform.scale.truncate(),
+            LEVEL_COLORS[level.0 as usize % LEVEL_COLORS.len()],
+        );
+    }
+
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+
+    // The labels go behind every window so they never cover the UI.
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    for (position, level, transform) in tiles.iter() {
+        let Some(screen) = camera.world_to_viewport(camera_transform, transform.translation) else {
+            continue;
+        };
+
+        painter.text(
+            egui::pos2(screen.x, screen.y),
+            egui::Align2::CENTER_CENTER,
+            format!("({}, {}) L{}", position.0 .0, position.0 .1, level.0),
+            egui::FontId::monospace(14.0),
+            egui::Color32::WHITE,
+        );
+    }
+}
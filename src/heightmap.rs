@@ -0,0 +1,122 @@
+/// A square grid of raw heights, stored row after row. Row `x` runs along the image rows of
+/// every conversion, so `get(x, y)` is pixel `(y, x)`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Heightmap {
+    size: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Takes `size * size` heights, row after row.
+    pub fn new(size: usize, heights: Vec<f32>) -> Self {
+        assert_eq!(heights.len(), size * size, "expected {0}x{0} heights", size);
+        Self { size, heights }
+    }
+
+    /// From `rows[x][y]`, the layout the generation works in.
+    pub fn from_rows(rows: &[Vec<f32>]) -> Self {
+        Self::new(rows.len(), rows.concat())
+    }
+
+    /// Cells per side.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<f32> {
+        (x < self.size && y < self.size).then(|| self.heights[x * self.size + y])
+    }
+
+    /// Bilinearly interpolates between the cells, clamping to the edges.
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let last = (self.size - 1) as f32;
+        let (x, y) = (x.clamp(0.0, last), y.clamp(0.0, last));
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+        let (u, v) = (x.fract(), y.fract());
+
+        let height = |x: usize, y: usize| self.heights[x * self.size + y];
+        let top = height(x0, y0) * (1.0 - v) + height(x0, y1) * v;
+        let bottom = height(x1, y0) * (1.0 - v) + height(x1, y1) * v;
+        top * (1.0 - u) + bottom * u
+    }
+
+    /// Every height, row after row.
+    pub fn as_slice(&self) -> &[f32] {
+        &self.heights
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        self.heights.iter().copied()
+    }
+
+    /// The rows one at a time, borrowed.
+    pub fn rows(&self) -> impl Iterator<Item = &[f32]> {
+        self.heights.chunks_exact(self.size)
+    }
+
+    /// Back to `rows[x][y]`.
+    pub fn to_rows(&self) -> Vec<Vec<f32>> {
+        self.rows().map(<[f32]>::to_vec).collect()
+    }
+
+    pub fn min_max(&self) -> (f32, f32) {
+        self.iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), h| {
+                (min.min(h), max.max(h))
+            })
+    }
+
+    /// Linearly rescaled so the lowest height becomes 0 and the highest 1. A perfectly flat map
+    /// becomes all zeros.
+    pub fn normalized(&self) -> Heightmap {
+        let (min, max) = self.min_max();
+        let range = max - min;
+        let heights = self
+            .iter()
+            .map(|h| if range > 0.0 { (h - min) / range } else { 0.0 })
+            .collect();
+        Heightmap::new(self.size, heights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "expected 3x3 heights")]
+    fn heights_must_fill_the_square() {
+        Heightmap::new(3, vec![0.0; 8]);
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let rows = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        let heightmap = Heightmap::from_rows(&rows);
+        assert_eq!(heightmap.as_slice(), &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(heightmap.get(1, 0), Some(3.0));
+        assert_eq!(heightmap.get(2, 0), None);
+        assert_eq!(heightmap.to_rows(), rows);
+    }
+
+    #[test]
+    fn normalized_spans_zero_to_one() {
+        let heightmap = Heightmap::new(2, vec![-3.0, 1.0, 5.0, 0.0]).normalized();
+        assert_eq!(heightmap.min_max(), (0.0, 1.0));
+        assert_eq!(heightmap.get(0, 1), Some(0.5));
+
+        let flat = Heightmap::new(2, vec![7.0; 4]).normalized();
+        assert_eq!(flat.as_slice(), &[0.0; 4]);
+    }
+
+    #[test]
+    fn concatenated_rows_are_the_flat_buffer() {
+        let heightmap = crate::DiamondSquare::new(33).seed(9).generate();
+
+        let rows: Vec<&[f32]> = heightmap.rows().collect();
+        assert_eq!(rows.len(), 33);
+        assert!(rows.iter().all(|row| row.len() == 33));
+        assert_eq!(rows.concat(), heightmap.as_slice());
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+/// Bins the preset targets are described with, evenly spread over heights 0-1.
+const TARGET_BINS: usize = 32;
+
+/// A preset elevation distribution, over heights after the logistic curve.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TargetDistribution {
+    /// Every height equally common.
+    #[default]
+    Uniform,
+    /// Mostly middling heights, few deep seas or high peaks.
+    Gaussian,
+    /// Lots of sea and lowland, rare peaks.
+    Coastal,
+    /// Deep valleys and a lot of high ground.
+    Alpine,
+}
+
+impl TargetDistribution {
+    pub const ALL: [TargetDistribution; 4] = [
+        TargetDistribution::Uniform,
+        TargetDistribution::Gaussian,
+        TargetDistribution::Coastal,
+        TargetDistribution::Alpine,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TargetDistribution::Uniform => "Uniform",
+            TargetDistribution::Gaussian => "Gaussian",
+            TargetDistribution::Coastal => "Coastal",
+            TargetDistribution::Alpine => "Alpine",
+        }
+    }
+
+    /// Relative frequency of every bin, unnormalized.
+    pub fn bins(self) -> Vec<f32> {
+        let gaussian =
+            |f: f32, mean: f32, spread: f32| (-((f - mean) / spread).powi(2) / 2.0).exp();
+        (0..TARGET_BINS)
+            .map(|bin| (bin as f32 + 0.5) / TARGET_BINS as f32)
+            .map(|f| match self {
+                TargetDistribution::Uniform => 1.0,
+                TargetDistribution::Gaussian => gaussian(f, 0.5, 0.15),
+                TargetDistribution::Coastal => (1.0 - f).powi(3),
+                TargetDistribution::Alpine => {
+                    0.2 + gaussian(f, 0.15, 0.05) + gaussian(f, 0.75, 0.1)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Remaps every generated tile so its heights follow a target distribution, for terrain that
+/// has to match real-world statistics. The single tile is matched on its own; streamed tiles
+/// are left alone, as each would be stretched differently and they would no longer meet.
+#[derive(Resource, Debug, PartialEq, Clone, Copy, Default)]
+pub struct HistogramMatching {
+    pub enabled: bool,
+    pub target: TargetDistribution,
+}
+
+/// Histogram specification: every cell keeps its rank among the heights but gets the height at
+/// the same quantile of `target`.
+///
+/// `target` holds relative frequencies of equally wide bins over heights 0-1 after the logistic
+/// curve, like the coloring sees them. The remapped heights are put back through the inverse of
+/// the curve, so they stay raw heights. Heights within a bin are spread linearly.
+pub fn match_histogram(heights: &mut [Vec<f32>], target: &[f32]) {
+    let total: f32 = target.iter().sum();
+    let cells = heights.iter().map(Vec::len).sum::<usize>();
+    if cells == 0 || target.is_empty() || total <= 0.0 {
+        return;
+    }
+
+    // Target CDF at the upper edge of every bin.
+    let cdf: Vec<f32> = target
+        .iter()
+        .scan(0.0, |sum, &weight| {
+            *sum += weight / total;
+            Some(*sum)
+        })
+        .collect();
+    let inverse_cdf = |quantile: f32| {
+        let bin = cdf.partition_point(|&c| c < quantile).min(cdf.len() - 1);
+        let below = if bin == 0 { 0.0 } else { cdf[bin - 1] };
+        let within = ((quantile - below) / (cdf[bin] - below).max(f32::EPSILON)).clamp(0.0, 1.0);
+        (bin as f32 + within) / cdf.len() as f32
+    };
+
+    let mut order: Vec<(usize, usize)> = heights
+        .iter()
+        .enumerate()
+        .flat_map(|(x, row)| (0..row.len()).map(move |y| (x, y)))
+        .collect();
+    order.sort_by(|&(ax, ay), &(bx, by)| heights[ax][ay].total_cmp(&heights[bx][by]));
+
+    for (rank, (x, y)) in order.into_iter().enumerate() {
+        let quantile = (rank as f32 + 0.5) / cells as f32;
+        // Keep away from 0 and 1, where the inverse logistic curve is infinite.
+        let f = inverse_cdf(quantile).clamp(0.001, 0.999);
+        heights[x][y] = (f / (1.0 - f)).ln();
+    }
+}
+
+/// The histogram matching section of the settings window. Returns whether it changed.
+pub fn histogram_matching_ui(ui: &mut egui::Ui, matching: &mut HistogramMatching) -> bool {
+    let mut changed = ui
+        .checkbox(&mut matching.enabled, "Match Elevation Histogram")
+        .changed();
+    egui::ComboBox::from_label("Target Distribution")
+        .selected_text(matching.target.name())
+        .show_ui(ui, |ui| {
+            for target in TargetDistribution::ALL {
+                changed |= ui
+                    .selectable_value(&mut matching.target, target, target.name())
+                    .changed();
+            }
+        });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use diamond_square::DiamondSquare;
+
+    use super::*;
+    use crate::coloring::logistic;
+
+    #[test]
+    fn matched_heights_follow_every_target() {
+        let generated = DiamondSquare::new(129)
+            .seed(11)
+            .roughness(2.0)
+            .generate()
+            .to_rows();
+
+        for target in TargetDistribution::ALL {
+            let mut heights = generated.clone();
+            let bins = target.bins();
+            match_histogram(&mut heights, &bins);
+
+            let cells = heights.iter().flatten().count() as f32;
+            let mut histogram = vec![0.0; bins.len()];
+            for &f in heights.iter().flatten() {
+                let bin = (logistic(f) * bins.len() as f32) as usize;
+                histogram[bin.min(bins.len() - 1)] += 1.0 / cells;
+            }
+
+            let total: f32 = bins.iter().sum();
+            for (bin, (&share, &weight)) in histogram.iter().zip(&bins).enumerate() {
+                assert!(
+                    (share - weight / total).abs() < 0.005,
+                    "{} bin {}: {} instead of {}",
+                    target.name(),
+                    bin,
+                    share,
+                    weight / total
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use image::GrayImage;
+
+use crate::RegenerateEvent;
+
+/// How far below the water level masked-out cells are pushed, in raw height units.
+const SEA_DEPTH: f32 = 0.5;
+
+/// An authored landmass outline for the single tile view: white is land, black is sea.
+#[derive(Resource, Default, Clone)]
+pub struct LandMask {
+    pub image: Option<GrayImage>,
+    /// The file `image` was loaded from, so the generation log can name it.
+    pub path: Option<PathBuf>,
+}
+
+impl LandMask {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            image: Some(image::open(path.as_ref())?.to_luma8()),
+            path: Some(path.as_ref().to_path_buf()),
+        })
+    }
+}
+
+/// Bilinearly samples the mask at cell `(x, y)` of a `size` cells wide tile, stretching the mask
+/// over the tile whatever its resolution. Image rows run along the heightmap rows. Returns 0-1.
+fn sample(mask: &GrayImage, size: usize, (x, y): (usize, usize)) -> f32 {
+    let (width, height) = mask.dimensions();
+    let cells = size.saturating_sub(1).max(1) as f32;
+    let u = y as f32 / cells * (width - 1) as f32;
+    let v = x as f32 / cells * (height - 1) as f32;
+
+    let pixel = |u: u32, v: u32| mask.get_pixel(u.min(width - 1), v.min(height - 1)).0[0] as f32;
+    let (u0, v0) = (u.floor() as u32, v.floor() as u32);
+    let (fu, fv) = (u.fract(), v.fract());
+    let top = pixel(u0, v0) * (1.0 - fu) + pixel(u0 + 1, v0) * fu;
+    let bottom = pixel(u0, v0 + 1) * (1.0 - fu) + pixel(u0 + 1, v0 + 1) * fu;
+    (top * (1.0 - fv) + bottom * fv) / 255.0
+}
+
+/// Shapes freshly generated heights to a land mask. Cells where the mask is at most half white
+/// end up below `water_level` (a height after the logistic curve, like
+/// [`WaterLevel`](crate::coloring::WaterLevel)), cells that are fully white keep their generated
+/// height, and the gray band in between blends the two for a smooth coastline.
+pub fn generate_masked(
+    mask: &GrayImage,
+    heights: Vec<Vec<f32>>,
+    water_level: f32,
+) -> Vec<Vec<f32>> {
+    // Undo the logistic curve to find the water level in raw height units.
+    let water_level = water_level.clamp(0.001, 0.999);
+    let sea_floor = (water_level / (1.0 - water_level)).ln() - SEA_DEPTH;
+
+    let size = heights.len();
+    heights
+        .into_iter()
+        .enumerate()
+        .map(|(x, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(|(y, f)| {
+                    let t = ((sample(mask, size, (x, y)) - 0.5) * 2.0).clamp(0.0, 1.0);
+                    let land = t * t * (3.0 - 2.0 * t);
+                    sea_floor + (f - sea_floor) * land
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn land_mask_ui(
+    mut contexts: EguiContexts,
+    mut mask: ResMut<LandMask>,
+    mut regenerate: EventWriter<RegenerateEvent>,
+    mut path: Local<String>,
+) {
+    const DEFAULT_PATH: &str = "land_mask.png";
+
+    if path.is_empty() {
+        *path = DEFAULT_PATH.to_string();
+    }
+
+    egui::Window::new("Land Mask")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut *path);
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Load Land Mask").clicked() {
+                    match LandMask::open(&*path) {
+                        Ok(loaded) => {
+                            *mask = loaded;
+                            regenerate.send(RegenerateEvent);
+                        }
+                        Err(err) => error!("Failed to load land mask {}: {}", *path, err),
+                    }
+                }
+                if ui
+                    .add_enabled(mask.image.is_some(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    *mask = LandMask::default();
+                    regenerate.send(RegenerateEvent);
+                }
+            });
+
+            ui.label(match &mask.image {
+                Some(mask) => format!("Mask: {}x{}", mask.width(), mask.height()),
+                None => "No mask, generating freely.".to_string(),
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring::logistic;
+
+    #[test]
+    fn masked_out_cells_end_up_under_water() {
+        // Smaller than the tile, sea on the left and land on the right.
+        let mask = GrayImage::from_fn(8, 8, |u, _| image::Luma([if u < 4 { 0 } else { 0xFF }]));
+        // Mountains everywhere before masking.
+        let heights: Vec<Vec<f32>> = (0..33)
+            .map(|x| (0..33).map(|y| 3.0 + (x * y) as f32 / 100.0).collect())
+            .collect();
+        let water_level = 0.2;
+
+        let masked = generate_masked(&mask, heights.clone(), water_level);
+
+        let mut sea = 0;
+        for x in 0..33 {
+            for y in 0..33 {
+                let coverage = sample(&mask, 33, (x, y));
+                if coverage <= 0.5 {
+                    sea += 1;
+                    assert!(logistic(masked[x][y]) < water_level);
+                } else if coverage == 1.0 {
+                    assert!((masked[x][y] - heights[x][y]).abs() < 1e-5);
+                }
+            }
+        }
+        assert!(sea > 33 * 10);
+    }
+}
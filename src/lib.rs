@@ -0,0 +1,467 @@
+//! Diamond-square terrain generation without the demo app around it.
+//!
+//! ```
+//! use diamond_square::{ColorRamp, DiamondSquare};
+//!
+//! let heightmap = DiamondSquare::new(257).seed(42).roughness(2.0).generate();
+//! let image = heightmap.to_image(&ColorRamp::terrain());
+//! assert_eq!(image.texture_descriptor.size.width, 257);
+//! ```
+
+use std::hash::{DefaultHasher, Hasher};
+
+mod heightmap;
+mod ramp;
+
+pub use heightmap::Heightmap;
+pub use ramp::ColorRamp;
+
+// Easy cordnate to hash function. Allowing for unique but persistent outputs.
+pub fn hash(seed: isize, x: i32, y: i32) -> f32 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_isize(seed);
+    hasher.write_i32(x);
+    hasher.write_i32(y);
+    let res = (hasher.finish() % 0xFF) as f32 / 0xFF as f32;
+    res
+}
+
+/// Where the four corners of a tile start before any detail is added.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum CornerSeeding {
+    /// Hashed at the tile's corners in the world grid, so neighbouring tiles share them.
+    #[default]
+    Hashed,
+    /// All four at the same height, for a tile that doesn't lean any way.
+    Constant(f32),
+    /// Given explicitly, in the order `[0][0]`, `[0][last]`, `[last][0]`, `[last][last]`.
+    Custom([f32; 4]),
+}
+
+/// Edges of a tile given up front instead of generated, e.g. copied from an already generated
+/// neighbour so the two meet exactly whatever their seeds. Every edge given must hold one height
+/// per cell, and edges that share a corner must agree on it.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Borders {
+    /// `heights[0]`.
+    pub first_row: Option<Vec<f32>>,
+    /// `heights[last]`.
+    pub last_row: Option<Vec<f32>>,
+    /// `heights[x][0]` for every `x`.
+    pub first_column: Option<Vec<f32>>,
+    /// `heights[x][last]` for every `x`.
+    pub last_column: Option<Vec<f32>>,
+}
+
+impl Borders {
+    /// Whether cell `(x, y)` of a `size` wide tile lies on one of the given edges.
+    fn contains(&self, size: usize, (x, y): (usize, usize)) -> bool {
+        let last = size - 1;
+        (x == 0 && self.first_row.is_some())
+            || (x == last && self.last_row.is_some())
+            || (y == 0 && self.first_column.is_some())
+            || (y == last && self.last_column.is_some())
+    }
+
+    /// Overwrites the given edges of `heights`.
+    fn apply(&self, heights: &mut [Vec<f32>]) {
+        let last = heights.len() - 1;
+        if let Some(row) = &self.first_row {
+            heights[0].copy_from_slice(row);
+        }
+        if let Some(row) = &self.last_row {
+            heights[last].copy_from_slice(row);
+        }
+        for (y, column) in [(0, &self.first_column), (last, &self.last_column)] {
+            if let Some(column) = column {
+                for (x, &height) in column.iter().enumerate() {
+                    heights[x][y] = height;
+                }
+            }
+        }
+    }
+}
+
+/// Configures a diamond-square generation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiamondSquare {
+    size: usize,
+    seed: isize,
+    roughness: f32,
+    persistence: f32,
+    position: (i32, i32),
+    corners: CornerSeeding,
+    borders: Borders,
+}
+
+impl DiamondSquare {
+    /// A `size` cells wide tile at the origin with seed 0 and roughness 2. `size` must be
+    /// `2^n + 1`.
+    pub fn new(size: usize) -> Self {
+        assert!(
+            size >= 3 && (size - 1).is_power_of_two(),
+            "size must be 2^n + 1, got {}",
+            size
+        );
+        Self {
+            size,
+            seed: 0,
+            roughness: 2.0,
+            persistence: 0.5,
+            position: (0, 0),
+            corners: CornerSeeding::Hashed,
+            borders: Borders::default(),
+        }
+    }
+
+    pub fn seed(self, seed: isize) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Displacement of the first level, shrinking by the [`persistence`](Self::persistence)
+    /// every level after.
+    pub fn roughness(self, roughness: f32) -> Self {
+        Self { roughness, ..self }
+    }
+
+    /// How much of the displacement is kept from one level to the next, 0.5 by default. Lower
+    /// values give smoother terrain and higher ones rougher terrain: the Hurst exponent of the
+    /// surface is `-log2(persistence)`.
+    pub fn persistence(self, persistence: f32) -> Self {
+        Self {
+            persistence,
+            ..self
+        }
+    }
+
+    /// The tile's place in the world grid. Tiles next to each other share their edges.
+    pub fn position(self, position: (i32, i32)) -> Self {
+        Self { position, ..self }
+    }
+
+    pub fn corners(self, corners: CornerSeeding) -> Self {
+        Self { corners, ..self }
+    }
+
+    /// Keeps the given edges as they are. The rest of the tile is generated around them, so it
+    /// still follows from the seed and position.
+    pub fn borders(self, borders: Borders) -> Self {
+        let edges = [
+            &borders.first_row,
+            &borders.last_row,
+            &borders.first_column,
+            &borders.last_column,
+        ];
+        for edge in edges.into_iter().flatten() {
+            assert_eq!(edge.len(), self.size, "a border needs one height per cell");
+        }
+        Self { borders, ..self }
+    }
+
+    /// Runs the whole generation.
+    pub fn generate(&self) -> Heightmap {
+        let mut generation = self.start();
+        generation.finish();
+        generation.into_heightmap()
+    }
+
+    /// Seeds the corners without adding any detail yet, to run the levels one at a time.
+    pub fn start(&self) -> Generation {
+        let size = self.size;
+        // this has to be dynamically allocated because the image is not static.
+        let mut heightmap: Vec<Vec<f32>> = vec![vec![0.0; size]; size];
+
+        let (px, py) = self.position;
+        let corners = match self.corners {
+            CornerSeeding::Hashed => {
+                // Rows run along -Y and columns along +X, so row 0 is the top edge and
+                // column 0 the left, like `Position::cell_to_world` lays tiles out.
+                let hash = |x: i32, y: i32| hash(self.seed, x, y);
+                [
+                    hash(px, py + 1),
+                    hash(px + 1, py + 1),
+                    hash(px, py),
+                    hash(px + 1, py),
+                ]
+            }
+            CornerSeeding::Constant(height) => [height; 4],
+            CornerSeeding::Custom(corners) => corners,
+        };
+
+        // Set values for all four corners.
+        heightmap[0][0] = corners[0];
+        heightmap[0][size - 1] = corners[1];
+        heightmap[size - 1][0] = corners[2];
+        heightmap[size - 1][size - 1] = corners[3];
+        self.borders.apply(&mut heightmap);
+
+        let span = size as i32 - 1;
+        Generation {
+            heightmap,
+            seed: self.seed,
+            origin: (px * span, (py + 1) * span),
+            chunk_size: size - 1,
+            roughness: self.roughness,
+            persistence: self.persistence,
+            borders: self.borders.clone(),
+        }
+    }
+}
+
+/// The diamond-square algorithm one chunk level at a time.
+#[derive(Debug, Clone)]
+pub struct Generation {
+    heightmap: Vec<Vec<f32>>,
+    seed: isize,
+    /// Global coordinate of cell `[0][0]`, shared by the corner cells of the neighbouring tiles.
+    /// Columns run along global +X and rows along global -Y.
+    origin: (i32, i32),
+    chunk_size: usize,
+    roughness: f32,
+    persistence: f32,
+    borders: Borders,
+}
+
+impl Generation {
+    pub fn is_done(&self) -> bool {
+        self.chunk_size <= 1
+    }
+
+    /// Cells between the ones generated so far, 1 once done.
+    pub fn spacing(&self) -> usize {
+        self.chunk_size.max(1)
+    }
+
+    /// Runs one square and diamond step, halving the chunk size and scaling the displacement by
+    /// the persistence. Does nothing once done.
+    pub fn step(&mut self) {
+        if self.is_done() {
+            return;
+        }
+
+        let heightmap = &mut self.heightmap;
+        let image_size = heightmap.len();
+        let (chunk_size, roughness) = (self.chunk_size, self.roughness);
+        let half = chunk_size / 2;
+        let (seed, (ox, oy)) = (self.seed, self.origin);
+        // Offsets are keyed on the global cell, so both tiles along an edge agree on them.
+        let hash = |x: usize, y: usize| hash(seed, ox + y as i32, oy - x as i32);
+        let last = image_size - 1;
+        let borders = &self.borders;
+
+        //square step
+        for y in (0..image_size - 1).step_by(chunk_size) {
+            for x in (0..image_size - 1).step_by(chunk_size) {
+                let top_left = heightmap[x][y];
+                let top_right = heightmap[x + chunk_size][y];
+                let bottom_left = heightmap[x][y + chunk_size];
+                let bottom_right = heightmap[x + chunk_size][y + chunk_size];
+
+                let average = (top_left + top_right + bottom_left + bottom_right) / 4.0;
+
+                let random_factor = hash(x + half, y + half) * 2.0 - 1.0;
+                let random_offset = random_factor * roughness;
+                heightmap[x + half][y + half] = average + random_offset;
+            }
+        }
+
+        // diamond step
+        for y in (0..image_size).step_by(half) {
+            for x in ((y + half) % chunk_size..(image_size)).step_by(chunk_size) {
+                if borders.contains(image_size, (x, y)) {
+                    continue;
+                }
+
+                // Cells on the tile's edge only average along the edge, the same cells the
+                // neighbouring tile sees, so the shared edge comes out identical on both.
+                if x == 0 || x == last {
+                    let average = (heightmap[x][y - half] + heightmap[x][y + half]) / 2.0;
+                    heightmap[x][y] = average + (hash(x, y) * 2.0 - 1.0) * roughness;
+                    continue;
+                }
+                if y == 0 || y == last {
+                    let average = (heightmap[x - half][y] + heightmap[x + half][y]) / 2.0;
+                    heightmap[x][y] = average + (hash(x, y) * 2.0 - 1.0) * roughness;
+                    continue;
+                }
+
+                let mut neighbors = 0;
+                let mut neighbor_sum = 0.0;
+
+                if x > half {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x - half][y];
+                }
+
+                if y > half {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x][y - half];
+                }
+
+                if x + half < image_size - 1 {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x + half][y];
+                }
+
+                if y + half < image_size - 1 {
+                    neighbors += 1;
+                    neighbor_sum += heightmap[x][y + half];
+                }
+
+                heightmap[x][y] = neighbor_sum / neighbors as f32;
+
+                let random = hash(x, y) * 2.0 - 1.0;
+                let random = random * roughness;
+                heightmap[x][y] += random;
+            }
+        }
+
+        self.chunk_size /= 2;
+        self.roughness *= self.persistence;
+    }
+
+    /// Runs every remaining step.
+    pub fn finish(&mut self) {
+        while !self.is_done() {
+            self.step();
+        }
+    }
+
+    /// The heights so far, as `heights[x][y]`. Only cells on the [`spacing`](Self::spacing) grid
+    /// are generated yet.
+    pub fn heights(&self) -> &[Vec<f32>] {
+        &self.heightmap
+    }
+
+    pub fn into_heightmap(self) -> Heightmap {
+        Heightmap::from_rows(&self.heightmap)
+    }
+}
+
+/// The plain diamond-square output rescaled to span exactly 0-1, with nothing else applied. This is
+/// the cleanest numeric output for feeding into other tools.
+pub fn generate_map_raw(
+    position: (i32, i32),
+    roughness: f32,
+    seed: isize,
+    image_size: usize,
+) -> Heightmap {
+    DiamondSquare::new(image_size)
+        .seed(seed)
+        .roughness(roughness)
+        .position(position)
+        .generate()
+        .normalized()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-6;
+
+    fn tile(position: (i32, i32)) -> Heightmap {
+        DiamondSquare::new(65).seed(7).position(position).generate()
+    }
+
+    #[test]
+    #[should_panic(expected = "2^n + 1")]
+    fn sizes_must_be_one_more_than_a_power_of_two() {
+        DiamondSquare::new(64);
+    }
+
+    #[test]
+    fn every_valid_size_generates() {
+        for size in [3, 5, 65, 257] {
+            assert_eq!(DiamondSquare::new(size).generate().size(), size);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_heights() {
+        let generate = |seed| DiamondSquare::new(65).seed(seed).generate();
+        assert_eq!(generate(3), generate(3));
+        assert_ne!(generate(3), generate(4));
+    }
+
+    #[test]
+    fn stepping_halves_the_spacing_until_done() {
+        let settings = DiamondSquare::new(65).seed(9).position((2, -3));
+        let mut generation = settings.start();
+        let mut spacing = 64;
+        while !generation.is_done() {
+            assert_eq!(generation.spacing(), spacing);
+            generation.step();
+            spacing /= 2;
+        }
+        assert_eq!(generation.spacing(), 1);
+        assert_eq!(generation.into_heightmap(), settings.generate());
+    }
+
+    #[test]
+    fn pre_seeded_borders_are_kept_exactly() {
+        // Taken from other seeds, so nothing but the borders makes them match.
+        let top = tile((0, 1));
+        let left = DiamondSquare::new(65).seed(8).position((-1, 0)).generate();
+        let last = top.size() - 1;
+        let first_row: Vec<f32> = top.rows().last().unwrap().to_vec();
+        let mut first_column: Vec<f32> = (0..=last).map(|x| left.get(x, last).unwrap()).collect();
+        // Edges that share a corner have to agree on it.
+        first_column[0] = first_row[0];
+
+        let borders = Borders {
+            first_row: Some(first_row.clone()),
+            first_column: Some(first_column.clone()),
+            ..Borders::default()
+        };
+        let tile = DiamondSquare::new(65)
+            .seed(1)
+            .borders(borders)
+            .generate()
+            .to_rows();
+
+        assert_eq!(tile[0], first_row);
+        for (x, &height) in first_column.iter().enumerate() {
+            assert_eq!(tile[x][0], height);
+        }
+    }
+
+    #[test]
+    fn horizontal_neighbours_share_their_edge() {
+        let (left, right) = (tile((0, 0)), tile((1, 0)));
+        let last = left.size() - 1;
+        for x in 0..left.size() {
+            let (a, b) = (left.get(x, last).unwrap(), right.get(x, 0).unwrap());
+            assert!((a - b).abs() < EPSILON, "row {}: {} != {}", x, a, b);
+        }
+    }
+
+    #[test]
+    fn vertical_neighbours_share_their_edge() {
+        // Row 0 is the top edge, so the tile above meets this one with its last row.
+        let (below, above) = (tile((0, 0)), tile((0, 1)));
+        let last = below.size() - 1;
+        for y in 0..below.size() {
+            let (a, b) = (below.get(0, y).unwrap(), above.get(last, y).unwrap());
+            assert!((a - b).abs() < EPSILON, "column {}: {} != {}", y, a, b);
+        }
+    }
+
+    #[test]
+    fn raw_output_spans_zero_to_one_linearly() {
+        let raw = generate_map_raw((2, 3), 2.0, 11, 65);
+        let heights = DiamondSquare::new(65)
+            .seed(11)
+            .roughness(2.0)
+            .position((2, 3))
+            .generate();
+
+        assert_eq!(raw.min_max(), (0.0, 1.0));
+
+        // A straight line through the generated heights, with no curve to band them.
+        let (low, high) = heights.min_max();
+        for (raw, height) in raw.iter().zip(heights.iter()) {
+            assert!((raw - (height - low) / (high - low)).abs() < EPSILON);
+        }
+    }
+}
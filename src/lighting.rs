@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::slope;
+
+/// A directional light baked into the tile colors.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Light {
+    /// Compass direction the light comes from, in degrees clockwise from north (+Y).
+    pub azimuth: f32,
+    /// Angle above the horizon, in degrees.
+    pub elevation: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl Light {
+    /// Unit vector pointing from the terrain towards the light.
+    pub fn direction(&self) -> Vec3 {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            azimuth.cos() * elevation.cos(),
+            elevation.sin(),
+        )
+    }
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        // The usual cartographic light from the north-west.
+        Self {
+            azimuth: 315.0,
+            elevation: 45.0,
+            color: [1.0; 3],
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Baked relief shading. Every light's contribution is summed per cell and the clamped result
+/// multiplies the terrain color.
+#[derive(Resource, Debug, PartialEq, Clone)]
+pub struct Lighting {
+    pub enabled: bool,
+    pub lights: Vec<Light>,
+    /// Light that reaches every cell regardless of its normal.
+    pub ambient: f32,
+    /// How much the heights are exaggerated before computing normals.
+    pub relief: f32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lights: vec![Light::default()],
+            ambient: 0.3,
+            relief: 0.15,
+        }
+    }
+}
+
+/// The light reaching every cell as an RGB multiplier, each channel clamped to 0-1.
+pub fn shade(heights: &[Vec<f32>], lighting: &Lighting) -> Vec<Vec<Vec3>> {
+    let directions: Vec<_> = lighting
+        .lights
+        .iter()
+        .map(|light| (light.direction(), Vec3::from(light.color) * light.intensity))
+        .collect();
+
+    slope::normals(heights, lighting.relief)
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|normal| {
+                    directions
+                        .iter()
+                        .map(|&(direction, color)| color * normal.dot(direction).max(0.0))
+                        .fold(Vec3::splat(lighting.ambient), |sum, light| sum + light)
+                        .clamp(Vec3::ZERO, Vec3::ONE)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The lighting section of the coloring window.
+pub fn lighting_ui(ui: &mut egui::Ui, lighting: &mut Lighting) {
+    ui.checkbox(&mut lighting.enabled, "Baked Lighting");
+    ui.add(egui::Slider::new(&mut lighting.ambient, 0.0..=1.0).prefix("Ambient: "));
+    ui.add(egui::Slider::new(&mut lighting.relief, 0.01..=1.0).prefix("Relief: "));
+
+    let mut removed = None;
+    for (index, light) in lighting.lights.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Light {}", index + 1));
+                ui.color_edit_button_rgb(&mut light.color);
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+            });
+            ui.add(egui::Slider::new(&mut light.azimuth, 0.0..=360.0).prefix("Azimuth: "));
+            ui.add(egui::Slider::new(&mut light.elevation, 0.0..=90.0).prefix("Elevation: "));
+            ui.add(egui::Slider::new(&mut light.intensity, 0.0..=2.0).prefix("Intensity: "));
+        });
+    }
+
+    if let Some(index) = removed {
+        lighting.lights.remove(index);
+    }
+
+    if ui.button("Add Light").clicked() {
+        lighting.lights.push(Light::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_second_light_adds_to_the_first_up_to_full_brightness() {
+        let flat = vec![vec![0.0; 3]; 3];
+        let light = |azimuth| Light {
+            azimuth,
+            intensity: 0.3,
+            ..default()
+        };
+        let lighting = |lights| Lighting {
+            enabled: true,
+            lights,
+            ambient: 0.0,
+            ..default()
+        };
+
+        let one = shade(&flat, &lighting(vec![light(0.0)]))[1][1];
+        let two = shade(&flat, &lighting(vec![light(0.0), light(180.0)]))[1][1];
+        assert!(two.x > one.x, "{} vs {}", two, one);
+
+        let many = shade(&flat, &lighting(vec![light(0.0); 10]))[1][1];
+        assert_eq!(many, Vec3::ONE);
+    }
+}
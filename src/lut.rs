@@ -0,0 +1,144 @@
+use std::{error::Error, path::Path};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+/// How land heights turn into colors in the terrain output.
+#[derive(Resource, Debug, PartialEq, Clone, Default)]
+pub enum ColoringMode {
+    /// The fixed lowland, rock and peak bands.
+    #[default]
+    Bands,
+    /// Every cell, water included, takes its color from the first row of a gradient image,
+    /// indexed by height. See [`Lut`].
+    Lut(Handle<Image>),
+}
+
+/// A 1D color lookup table, lowest height first.
+#[derive(Debug, PartialEq, Clone, Hash)]
+pub struct Lut(Vec<[u8; 3]>);
+
+impl Lut {
+    /// Reads the first row of an RGBA8 image. Any width works, 256 is just the usual one.
+    pub fn from_image(image: &Image) -> Option<Self> {
+        let width = image.texture_descriptor.size.width as usize;
+        let row = image.data.get(..width * 4)?;
+        let colors: Vec<_> = row.chunks_exact(4).map(|c| [c[0], c[1], c[2]]).collect();
+        (!colors.is_empty()).then_some(Self(colors))
+    }
+
+    /// The color at height `f` (0-1), stretching the table over the whole range whatever its
+    /// length.
+    pub fn sample(&self, f: f32) -> [u8; 3] {
+        let last = self.0.len() - 1;
+        self.0[(f.clamp(0.0, 1.0) * last as f32).round() as usize]
+    }
+}
+
+/// The table for the current [`ColoringMode`], decoded once so coloring doesn't need access to
+/// the image assets.
+#[derive(Resource, Debug, PartialEq, Clone, Default)]
+pub struct ActiveLut(pub Option<Lut>);
+
+/// Decodes a gradient image from disk and adds it as an image asset.
+pub fn load_lut_image(
+    path: impl AsRef<Path>,
+    images: &mut Assets<Image>,
+) -> Result<Handle<Image>, Box<dyn Error>> {
+    let rgba = image::open(path)?.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    Ok(images.add(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rgba.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    )))
+}
+
+pub fn update_active_lut(
+    mode: Res<ColoringMode>,
+    images: Res<Assets<Image>>,
+    mut active: ResMut<ActiveLut>,
+) {
+    if !mode.is_changed() {
+        return;
+    }
+
+    let lut = match &*mode {
+        ColoringMode::Bands => None,
+        ColoringMode::Lut(handle) => images.get(handle).and_then(Lut::from_image),
+    };
+    active.set_if_neq(ActiveLut(lut));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coloring::{colorize, logistic, ColorSettings},
+        Position,
+    };
+
+    /// Black to white over `width` pixels.
+    fn grayscale_lut(width: u32) -> Lut {
+        let data = (0..width)
+            .flat_map(|i| {
+                let value = (i * 0xFF / (width - 1)) as u8;
+                [value, value, value, 0xFF]
+            })
+            .collect();
+        let image = Image::new(
+            Extent3d {
+                width,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        Lut::from_image(&image).unwrap()
+    }
+
+    #[test]
+    fn a_grayscale_lut_colors_by_height() {
+        let heights: Vec<Vec<f32>> = (0..5)
+            .map(|x| (0..5).map(|y| (x * 5 + y) as f32 / 4.0 - 3.0).collect())
+            .collect();
+        let settings = ColorSettings {
+            lut: Some(grayscale_lut(256)),
+            ..default()
+        };
+
+        let rgba = colorize(&heights, Position((0, 0)), &settings);
+        for (pixel, &height) in rgba.chunks_exact(4).zip(heights.iter().flatten()) {
+            let gray = (logistic(height) * 0xFF as f32).round() as u8;
+            assert_eq!(pixel, [gray, gray, gray, 0xFF]);
+        }
+    }
+
+    #[test]
+    fn short_luts_stretch_over_every_height() {
+        let lut = grayscale_lut(16);
+        assert_eq!(lut.sample(0.0), [0; 3]);
+        assert_eq!(lut.sample(1.0), [0xFF; 3]);
+        for i in 0..=100 {
+            let f = i as f32 / 100.0;
+            let [gray, ..] = lut.sample(f);
+            // Half a step of the table either way.
+            assert!((gray as f32 - f * 255.0).abs() <= 255.0 / 15.0 / 2.0 + 0.5);
+        }
+    }
+}
@@ -1,22 +1,151 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::{hash::Hash, path::Path, time::Instant};
 
 use bevy::{
+    ecs::system::SystemParam,
+    log::LogPlugin,
     prelude::*,
     render::{
         render_asset::RenderAssetUsages,
         render_resource::{Extent3d, TextureDimension, TextureFormat},
     },
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+    window::PrimaryWindow,
 };
 use bevy_inspector_egui::{
     bevy_egui::{EguiContexts, EguiPlugin},
     egui,
 };
+use diamond_square::hash;
+use diamond_square::DiamondSquare;
+use serde::{Deserialize, Serialize};
+
+mod alignment;
+mod annotations;
+mod batch;
+mod bump;
+mod cache;
+mod climate;
+mod clipmap;
+mod coastline;
+mod coloring;
+mod contours;
+mod detail;
+mod determinism;
+mod dither;
+mod document;
+mod erosion;
+mod export;
+mod favorites;
+mod fixed;
+mod geologic;
+mod grid;
+mod histogram;
+mod landmask;
+mod lighting;
+mod lut;
+mod mapmode;
+mod material;
+mod nudge;
+mod palette;
+mod peak;
+mod poi;
+mod postprocess;
+mod progressive;
+mod recording;
+mod refine;
+mod relief;
+mod seed;
+#[cfg(feature = "server")]
+mod server;
+mod shore;
+mod slope;
+mod stats;
+mod stitch;
+mod strata;
+mod svg;
+mod telemetry;
+mod terrain3d;
+mod tilemap;
+mod topology;
+mod viewshed;
+mod voxel;
+mod walkability;
+mod water;
+mod watershed;
+
+use alignment::SubpixelAlignment;
+use annotations::MapAnnotations;
+use bump::BumpMapped;
+use cache::ColorCache;
+use climate::Climate;
+use clipmap::Clipmap;
+use coastline::CoastlineSmoothing;
+use coloring::{
+    colorize, ColorSettings, Coloring, EffectiveElevation, OutputMode, SnowModel, WaterLevel,
+};
+use contours::{ContourLabels, Contours};
+use detail::DetailTexturing;
+use dither::DitherMode;
+use document::TerrainDocument;
+use favorites::Favorites;
+use fixed::Arithmetic;
+use geologic::GeologicTime;
+use grid::ShowTileGrid;
+use histogram::HistogramMatching;
+use landmask::LandMask;
+use lighting::Lighting;
+use lut::{ActiveLut, ColoringMode};
+use mapmode::MapMode;
+use material::{TileExtension, TileMaterial};
+use nudge::{Nudge, NudgeTarget};
+use palette::PaletteCrossfade;
+use peak::CentralPeak;
+use poi::{CurrentPointsOfInterest, PointOfInterest, PointsOfInterest};
+use postprocess::PostProcessShader;
+use progressive::{ProgressiveGeneration, Refining};
+use relief::ShadedReliefExport;
+use seed::SeedSource;
+use stats::{CurrentStats, TileStats};
+use stitch::MultiSeed;
+use strata::Strata;
+use telemetry::Telemetry;
+use terrain3d::TerrainMesh;
+use topology::Topology;
+use viewshed::Viewshed;
+use walkability::Walkability;
+use water::WaterAnimation;
 
 fn main() {
+    // Batch runs are headless and exit once every seed is written.
+    if let Some(args) = batch::BatchArgs::parse() {
+        let result = args.map_err(Into::into).and_then(|args| batch::run(&args));
+        match result {
+            Ok(0) => return,
+            Ok(failed) => eprintln!("{} seeds failed", failed),
+            Err(err) => eprintln!("Batch generation failed: {}", err),
+        }
+        std::process::exit(1);
+    }
+
+    // So is the tile server, which runs until it is killed.
+    #[cfg(feature = "server")]
+    if let Some(args) = server::ServerArgs::parse() {
+        if let Err(err) = server::run(&args) {
+            eprintln!("Tile server failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
+                .set(LogPlugin {
+                    update_subscriber: telemetry::requested()
+                        .then_some(telemetry::add_log_file as fn(_) -> _),
+                    ..default()
+                })
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "Diamond-Square Implementation".to_string(),
@@ -26,32 +155,264 @@ fn main() {
                     ..default()
                 }),
         )
+        .init_resource::<GenSettings>()
+        .init_resource::<Clipmap>()
+        .init_resource::<OutputMode>()
+        .init_resource::<WaterLevel>()
+        .init_resource::<CoastlineSmoothing>()
+        .init_resource::<SnowModel>()
+        .init_resource::<EffectiveElevation>()
+        .init_resource::<DitherMode>()
+        .init_resource::<Lighting>()
+        .init_resource::<Climate>()
+        .init_resource::<Strata>()
+        .init_resource::<Nudge>()
+        .init_resource::<Favorites>()
+        .init_resource::<CurrentStats>()
+        .init_resource::<ShowTileGrid>()
+        .init_resource::<ColoringMode>()
+        .init_resource::<ActiveLut>()
+        .init_resource::<GenerationEpoch>()
+        .init_resource::<DetailTexturing>()
+        .init_resource::<SeedSource>()
+        .init_resource::<SubpixelAlignment>()
+        .init_resource::<WaterAnimation>()
+        .init_resource::<LandMask>()
+        .init_resource::<Telemetry>()
+        .init_resource::<BumpMapped>()
+        .init_resource::<Contours>()
+        .init_resource::<ContourLabels>()
+        .init_resource::<ColorCache>()
+        .init_resource::<PostProcessShader>()
+        .init_resource::<Walkability>()
+        .init_resource::<PaletteCrossfade>()
+        .init_resource::<CentralPeak>()
+        .init_resource::<MultiSeed>()
+        .init_resource::<MapAnnotations>()
+        .init_resource::<ProgressiveGeneration>()
+        .init_resource::<MapMode>()
+        .init_resource::<ShadedReliefExport>()
+        .init_resource::<Viewshed>()
+        .init_resource::<GeologicTime>()
+        .init_resource::<HistogramMatching>()
+        .init_resource::<PointsOfInterest>()
+        .init_resource::<CurrentPointsOfInterest>()
+        .init_resource::<TerrainMesh>()
         .add_event::<GenTileEvent>()
+        .add_event::<LoadTileEvent>()
+        .add_event::<RegenerateEvent>()
+        .add_event::<TileGenerated>()
         .add_plugins(EguiPlugin)
+        .add_plugins(MaterialPlugin::<TileMaterial>::default())
         .add_systems(Update, bevy::window::close_on_esc)
-        .add_systems(Startup, setup)
-        .add_systems(Update, process_gentile)
+        .add_systems(Startup, (setup, bump::spawn_bump_light))
+        .add_systems(
+            Startup,
+            determinism::verify_determinism.run_if(determinism::requested),
+        )
+        .add_systems(
+            Update,
+            (
+                process_regenerate,
+                process_gentile,
+                finish_gentile_tasks,
+                progressive::refine_tiles,
+            )
+                .chain(),
+        )
         .add_systems(Update, ui_example)
+        .add_systems(
+            Update,
+            (
+                pan_camera,
+                terrain3d::orbit_camera,
+                clipmap::update_clipmap,
+                (
+                    lut::update_active_lut,
+                    palette::animate_crossfade,
+                    geologic::advance_geologic_time,
+                    geologic::erode_geologic_time,
+                    coloring::recolor_tiles,
+                )
+                    .chain(),
+                nudge::nudge_parameters,
+                stats::update_stats,
+                grid::draw_tile_grid,
+                detail::update_detail_materials,
+                alignment::update_tile_meshes,
+                water::update_water,
+                bump::update_bump_mapping,
+                (contours::update_contours, contours::draw_contours).chain(),
+                postprocess::update_post_process,
+                mapmode::toggle_map_mode,
+                poi::track_points_of_interest,
+            ),
+        )
+        // The windows.
+        .add_systems(
+            Update,
+            (
+                clipmap::clipmap_ui,
+                coloring::coloring_ui,
+                refine::refine_ui,
+                favorites::favorites_ui,
+                stats::stats_ui,
+                export::export_ui,
+                erosion::erosion_ui,
+                recording::recording_ui,
+                landmask::land_mask_ui,
+                contours::contours_ui,
+                postprocess::post_process_ui,
+                stitch::multi_seed_ui,
+                viewshed::viewshed_ui,
+                poi::points_of_interest_ui,
+                terrain3d::terrain_mesh_ui,
+            ),
+        )
         .run();
 }
 
-#[derive(Event, Debug)]
+#[derive(Event, Debug, Clone, Copy)]
 struct GenTileEvent {
     pub position: Position,
     pub seed: isize,
     pub image_size: usize,
     pub roughness: f32,
+    /// Clipmap level; a tile at level `n` covers `2^n` world units per side.
+    pub level: u32,
+    /// Quarter turns counter-clockwise, 0-3. A rotated tile only stitches seamlessly with its
+    /// neighbors if its edges are symmetric, which `build_tile` checks and warns about.
+    pub rotation: u8,
+    pub topology: Topology,
+    pub arithmetic: Arithmetic,
+    /// The [`GenerationEpoch`] the tile was requested in.
+    pub epoch: u64,
+}
+
+/// Counts terrain replacements. Tile requests from an earlier epoch are stale, so they are
+/// discarded instead of overwriting the newer terrain when they arrive late.
+#[derive(Resource, Debug, Default)]
+struct GenerationEpoch(u64);
+
+/// Replaces the current terrain with one built from the current [`GenSettings`].
+#[derive(Event, Debug)]
+struct RegenerateEvent;
+
+/// Sent once a generated tile has its final heights.
+#[derive(Event, Debug)]
+struct TileGenerated {
+    pub entity: Entity,
+    pub position: Position,
+    pub level: u32,
+    /// Empty unless [`PointsOfInterest`] is enabled.
+    pub points_of_interest: Vec<PointOfInterest>,
+    /// How far every cell is from the nearest water, in cells, laid out like the heights. See
+    /// [`shore::distance_to_water`].
+    pub distance_to_water: Vec<Vec<f32>>,
+}
+
+/// Spawns a tile from an already generated heightmap, e.g. one read back from a project file.
+#[derive(Event, Debug)]
+struct LoadTileEvent {
+    pub position: Position,
+    pub heights: Vec<Vec<f32>>,
 }
 
 #[derive(Component)]
 struct Tile;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// The raw heightmap a tile was built from, kept around so it can be saved or edited later.
+#[derive(Component)]
+struct TileHeights(Vec<Vec<f32>>);
+
+/// The clipmap level a tile was generated for. Plain tiles are level 0.
+#[derive(Component, Debug, Clone, Copy)]
+struct TileLevel(u32);
+
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 struct Position((i32, i32));
 
-fn setup(mut commands: Commands, mut gentile: EventWriter<GenTileEvent>) {
-    const DEFAULT_TILE_SIZE: usize = 2usize.pow(9) + 1;
+impl Position {
+    /// Where cell `heights[x][y]` of a level 0 tile with `size` cells per side lands in the world.
+    /// Rows of the heightmap run down the texture, so `x` moves along -Y and `y` along +X.
+    fn cell_to_world(self, size: usize, (x, y): (usize, usize)) -> Vec2 {
+        let (px, py) = self.0;
+        let cells = (size - 1) as f32;
+        Vec2::new(
+            px as f32 - 0.5 + y as f32 / cells,
+            py as f32 + 0.5 - x as f32 / cells,
+        )
+    }
+
+    /// The nearest cell to a world point, if the point is on this tile.
+    fn world_to_cell(self, size: usize, point: Vec2) -> Option<(usize, usize)> {
+        let (px, py) = self.0;
+        let cells = (size - 1) as f32;
+        let x = ((py as f32 + 0.5 - point.y) * cells).round();
+        let y = ((point.x - px as f32 + 0.5) * cells).round();
+        let range = 0.0..=cells;
+        (range.contains(&x) && range.contains(&y)).then_some((x as usize, y as usize))
+    }
+}
+
+/// The parameters used for the next "Generate Terrain" click.
+#[derive(Resource, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+struct GenSettings {
+    pub seed: isize,
+    pub roughness: f32,
+    pub node_size: usize,
+    #[serde(default)]
+    pub rotation: u8,
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub arithmetic: Arithmetic,
+    /// Varies the world per playthrough while `seed` stays the shareable world seed. 0 leaves
+    /// the world exactly as `seed` alone makes it.
+    #[serde(default)]
+    pub run_seed: isize,
+}
+
+impl Default for GenSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            roughness: 2.0,
+            node_size: 9,
+            rotation: 0,
+            topology: Topology::Planar,
+            arithmetic: Arithmetic::Float,
+            run_seed: 0,
+        }
+    }
+}
+
+impl GenSettings {
+    fn image_size(&self) -> usize {
+        2usize.pow(self.node_size as u32) + 1
+    }
+
+    /// The seed the noise is actually sampled with, `seed` combined with `run_seed`. The run
+    /// seed is scrambled first so nearby run seeds still give unrelated worlds.
+    fn noise_seed(&self) -> isize {
+        if self.run_seed == 0 {
+            return self.seed;
+        }
+
+        // SplitMix64 finalizer.
+        let mut mix = self.run_seed as u64;
+        mix = (mix ^ (mix >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mix = (mix ^ (mix >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        mix ^= mix >> 31;
+        self.seed ^ mix as isize
+    }
+}
 
+fn setup(
+    mut commands: Commands,
+    mut gentile: EventWriter<GenTileEvent>,
+    settings: Res<GenSettings>,
+) {
     commands.spawn(Camera3dBundle {
         transform: Transform::from_xyz(0.0, 000.0, 1.0),
         ..Default::default()
@@ -60,214 +421,930 @@ fn setup(mut commands: Commands, mut gentile: EventWriter<GenTileEvent>) {
     // Setup initial tile.
     gentile.send(GenTileEvent {
         position: Position((0, 0)),
-        seed: 0,
-        roughness: 2.0,
-        image_size: DEFAULT_TILE_SIZE,
+        seed: settings.noise_seed(),
+        roughness: settings.roughness,
+        image_size: settings.image_size(),
+        level: 0,
+        rotation: settings.rotation,
+        topology: settings.topology,
+        arithmetic: settings.arithmetic,
+        epoch: 0,
     });
 }
 
+/// The mouse, and the window and camera to place it on the terrain, for systems that pick cells.
+#[derive(SystemParam)]
+struct Cursor<'w, 's> {
+    buttons: Res<'w, ButtonInput<MouseButton>>,
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl Cursor<'_, '_> {
+    /// Where the cursor points on the tile plane (z = 0), if it's over the window.
+    fn world_position(&self) -> Option<Vec2> {
+        let cursor = self.windows.get_single().ok()?.cursor_position()?;
+        let (camera, transform) = self.cameras.get_single().ok()?;
+        let ray = camera.viewport_to_world(transform, cursor)?;
+        let distance = ray.intersect_plane(Vec3::ZERO, Plane3d::new(Vec3::Z))?;
+        Some(ray.get_point(distance).truncate())
+    }
+}
+
+fn pan_camera(
+    mut contexts: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    const PAN_SPEED: f32 = 1.0;
+    const ZOOM_SPEED: f32 = 1.0;
+
+    // Don't move while typing into the settings, or while the arrows are nudging parameters.
+    if contexts.ctx_mut().wants_keyboard_input() || nudge::modifier_pressed(&keys) {
+        return;
+    }
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let pressed = |codes: [KeyCode; 2]| keys.any_pressed(codes) as i32 as f32;
+    let direction = Vec2::new(
+        pressed([KeyCode::KeyD, KeyCode::ArrowRight])
+            - pressed([KeyCode::KeyA, KeyCode::ArrowLeft]),
+        pressed([KeyCode::KeyW, KeyCode::ArrowUp]) - pressed([KeyCode::KeyS, KeyCode::ArrowDown]),
+    );
+    let zoom = pressed([KeyCode::KeyE, KeyCode::Minus]) - pressed([KeyCode::KeyQ, KeyCode::Equal]);
+
+    // Scale movement with the distance to the tiles so panning feels the same at any zoom.
+    let distance = transform.translation.z.max(0.1);
+    transform.translation += (direction * PAN_SPEED * distance).extend(0.0) * time.delta_seconds();
+    transform.translation.z += zoom * ZOOM_SPEED * distance * time.delta_seconds();
+}
+
+fn process_regenerate(
+    mut commands: Commands,
+    mut event: EventReader<RegenerateEvent>,
+    mut gentile: EventWriter<GenTileEvent>,
+    mut clipmap: ResMut<Clipmap>,
+    mut epoch: ResMut<GenerationEpoch>,
+    tile_query: Query<Entity, Or<(With<Tile>, With<GenTileTask>)>>,
+    settings: Res<GenSettings>,
+) {
+    // Several requests in one frame still only need one new terrain.
+    if event.read().count() == 0 {
+        return;
+    }
+
+    // Anything still queued for the old terrain is superseded.
+    epoch.0 += 1;
+
+    // Clear all tiles, cancelling the ones still being generated.
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // The clipmap refills itself around the camera once its cache is empty.
+    if clipmap.enabled {
+        clipmap.clear();
+    } else {
+        // Send an event to generate a new tile.
+        gentile.send(GenTileEvent {
+            position: Position((0, 0)),
+            seed: settings.noise_seed(),
+            roughness: settings.roughness,
+            image_size: settings.image_size(),
+            level: 0,
+            rotation: settings.rotation,
+            topology: settings.topology,
+            arithmetic: settings.arithmetic,
+            epoch: epoch.0,
+        });
+    }
+}
+
+/// The switches deciding how tiles are generated, for [`process_gentile`].
+#[derive(SystemParam)]
+struct GenerationOptions<'w> {
+    epoch: Res<'w, GenerationEpoch>,
+    multi_seed: Res<'w, MultiSeed>,
+    progressive: Res<'w, ProgressiveGeneration>,
+    telemetry: Res<'w, Telemetry>,
+    points_of_interest: Res<'w, PointsOfInterest>,
+}
+
 fn process_gentile(
     mut commands: Commands,
     mut event: EventReader<GenTileEvent>,
-    mut images: ResMut<Assets<Image>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut loaded: EventReader<LoadTileEvent>,
+    mut assets: TileAssets,
+    coloring: Coloring,
+    shaping: TileShaping,
+    options: GenerationOptions,
 ) {
+    let color_settings = coloring.settings();
+
     for tile_event in event.read() {
-        let (px, py) = tile_event.position.0;
-
-        // Create the texture from dynamically generated image.
-        let texture = images.add(Image::new(
-            Extent3d {
-                width: tile_event.image_size as u32,
-                height: tile_event.image_size as u32,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            generate_map(
+        if tile_event.epoch != options.epoch.0 {
+            continue;
+        }
+
+        // Give each coarser clipmap level its own terrain instead of repeating level 0's.
+        let seed = match tile_event.level {
+            0 => tile_event.seed,
+            level => tile_event
+                .seed
+                .wrapping_mul(31)
+                .wrapping_add(level as isize),
+        };
+
+        let stitched = options.multi_seed.enabled
+            && tile_event.level == 0
+            && tile_event.topology == Topology::Planar;
+
+        // Timings in the generation log only mean something for tiles generated in one go.
+        let refine_later = options.progressive.0
+            && !options.telemetry.0
+            && !stitched
+            && tile_event.level == 0
+            && tile_event.topology == Topology::Planar
+            && tile_event.arithmetic == Arithmetic::Float;
+        if refine_later {
+            let mut generation = DiamondSquare::new(tile_event.image_size)
+                .seed(seed)
+                .roughness(tile_event.roughness)
+                .position(tile_event.position.0)
+                .start();
+            progressive::step_to_preview(&mut generation);
+            let heights = shape_tile(
+                progressive::preview(&generation),
                 tile_event.position,
-                tile_event.roughness,
-                tile_event.seed,
-                tile_event.image_size,
-            ),
-            TextureFormat::Rgba8Unorm,
-            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-        ));
+                tile_event.level,
+                shaping.shaping(),
+                color_settings.water_level.0,
+            );
+            let tile = spawn_tile(
+                &mut commands,
+                &mut assets,
+                &color_settings,
+                tile_event.position,
+                tile_event.level,
+                rotate_quarter_turns(&heights, tile_event.rotation),
+            );
+            commands.entity(tile).insert(Refining {
+                generation,
+                rotation: tile_event.rotation,
+            });
+            continue;
+        }
 
-        // Spawn in a quad with the generated image.
+        // The task can't borrow the resources, so it gets its own copies of what it needs.
+        let tile = TileBuild {
+            event: *tile_event,
+            seed,
+            tile_seed: stitched.then(|| options.multi_seed.tile_seed(tile_event.position, seed)),
+            peak: *shaping.peak,
+            histogram: *shaping.histogram,
+            land_mask: if shaping.streamed() {
+                LandMask::default()
+            } else {
+                shaping.land_mask.clone()
+            },
+            streamed: shaping.streamed(),
+            color_settings: color_settings.clone(),
+            telemetry: options.telemetry.0,
+            points_of_interest: options.points_of_interest.enabled,
+        };
         commands.spawn((
-            PbrBundle {
-                mesh: meshes.add(Rectangle::new(1.0, 1.0)),
-                material: materials.add(StandardMaterial {
-                    base_color_texture: Some(texture.clone()),
-                    double_sided: true,
-                    cull_mode: None,
-                    unlit: true,
-                    alpha_mode: AlphaMode::Blend,
-                    ..Default::default()
+            GenTileTask {
+                task: AsyncComputeTaskPool::get().spawn(async move { build_tile(tile) }),
+                epoch: tile_event.epoch,
+            },
+            tile_event.position,
+            TileLevel(tile_event.level),
+        ));
+    }
+
+    for load_event in loaded.read() {
+        spawn_tile(
+            &mut commands,
+            &mut assets,
+            &color_settings,
+            load_event.position,
+            0,
+            load_event.heights.clone(),
+        );
+    }
+}
+
+/// A tile being generated on the [`AsyncComputeTaskPool`], picked up by
+/// [`finish_gentile_tasks`] once done. The entity carries the tile's [`Position`] and
+/// [`TileLevel`] meanwhile, and despawning it cancels the generation.
+#[derive(Component)]
+struct GenTileTask {
+    task: Task<BuiltTile>,
+    epoch: u64,
+}
+
+/// Everything [`build_tile`] needs, owned so it can run on another thread.
+struct TileBuild {
+    event: GenTileEvent,
+    /// The level's seed, see [`process_gentile`].
+    seed: isize,
+    /// The seed of this tile of a stitched world, `None` unless stitched.
+    tile_seed: Option<isize>,
+    peak: CentralPeak,
+    histogram: HistogramMatching,
+    land_mask: LandMask,
+    streamed: bool,
+    color_settings: ColorSettings,
+    telemetry: bool,
+    points_of_interest: bool,
+}
+
+/// What [`build_tile`] hands back to the main thread.
+struct BuiltTile {
+    heights: Vec<Vec<f32>>,
+    points_of_interest: Vec<PointOfInterest>,
+    distance_to_water: Vec<Vec<f32>>,
+}
+
+/// Generates and shapes a tile in one go, with the gameplay data sent along in
+/// [`TileGenerated`].
+fn build_tile(tile: TileBuild) -> BuiltTile {
+    let started = Instant::now();
+    let (tile_event, seed) = (tile.event, tile.seed);
+    let color_settings = &tile.color_settings;
+
+    let heights = if let Some(tile_seed) = tile.tile_seed {
+        stitch::generate_stitched(
+            tile_event.position,
+            tile_event.roughness,
+            seed,
+            tile_seed,
+            tile_event.image_size,
+        )
+    } else {
+        generate_tile(
+            tile_event.position,
+            tile_event.roughness,
+            seed,
+            tile_event.image_size,
+            tile_event.topology,
+            tile_event.arithmetic,
+        )
+    };
+
+    let shaping = Shaping {
+        peak: &tile.peak,
+        histogram: &tile.histogram,
+        land_mask: &tile.land_mask,
+        streamed: tile.streamed,
+    };
+    let heights = shape_tile(
+        heights,
+        tile_event.position,
+        tile_event.level,
+        shaping,
+        color_settings.water_level.0,
+    );
+
+    let rotated = rotate_quarter_turns(&heights, tile_event.rotation);
+    if edges(&rotated) != edges(&heights) {
+        warn!(
+            "Tile {:?} is rotated but its edges aren't symmetric, it won't stitch seamlessly",
+            tile_event.position
+        );
+    }
+    let heights = rotated;
+
+    if tile.telemetry {
+        // Everything the tile was generated and shaped with, so a report can be reproduced exactly.
+        let stats = TileStats::compute(&heights, tile_event.position, color_settings);
+        tracing::info!(
+            target: telemetry::TARGET,
+            seed,
+            roughness = tile_event.roughness,
+            image_size = tile_event.image_size,
+            position = ?tile_event.position.0,
+            level = tile_event.level,
+            rotation = tile_event.rotation,
+            topology = ?tile_event.topology,
+            arithmetic = ?tile_event.arithmetic,
+            land_mask = ?tile.land_mask.path,
+            central_peak = ?tile.peak.enabled.then_some((tile.peak.height, tile.peak.radius)),
+            histogram = ?tile.histogram.enabled.then_some(tile.histogram.target),
+            streamed = tile.streamed,
+            tile_seed = ?tile.tile_seed,
+            water_level = color_settings.water_level.0,
+            min = stats.min,
+            max = stats.max,
+            mean = stats.mean,
+            fingerprint = %stats.fingerprint,
+            millis = started.elapsed().as_secs_f32() * 1000.0,
+        );
+    }
+
+    let points = if tile.points_of_interest {
+        poi::tile_points_of_interest(&heights, tile_event.position, color_settings)
+    } else {
+        Vec::new()
+    };
+    BuiltTile {
+        distance_to_water: shore::tile_distance_to_water(&heights, color_settings.water_level.0),
+        heights,
+        points_of_interest: points,
+    }
+}
+
+/// Spawns the tiles whose generation finished, unless a newer terrain superseded them.
+fn finish_gentile_tasks(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut GenTileTask, &Position, &TileLevel)>,
+    mut assets: TileAssets,
+    coloring: Coloring,
+    epoch: Res<GenerationEpoch>,
+    mut tile_generated: EventWriter<TileGenerated>,
+) {
+    if tasks.is_empty() {
+        return;
+    }
+    let color_settings = coloring.settings();
+
+    for (entity, mut task, &position, level) in tasks.iter_mut() {
+        let Some(built) = block_on(future::poll_once(&mut task.task)) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+        if task.epoch != epoch.0 {
+            continue;
+        }
+
+        let tile = spawn_tile(
+            &mut commands,
+            &mut assets,
+            &color_settings,
+            position,
+            level.0,
+            built.heights,
+        );
+        tile_generated.send(TileGenerated {
+            entity: tile,
+            position,
+            level: level.0,
+            points_of_interest: built.points_of_interest,
+            distance_to_water: built.distance_to_water,
+        });
+    }
+}
+
+/// The large-scale shaping every generated tile gets, see [`shape_tile`].
+#[derive(Clone, Copy)]
+struct Shaping<'a> {
+    peak: &'a CentralPeak,
+    histogram: &'a HistogramMatching,
+    land_mask: &'a LandMask,
+    /// Whether the tile is part of the streamed clipmap world instead of the single tile.
+    streamed: bool,
+}
+
+/// The shaping resources, for systems that generate tiles.
+#[derive(SystemParam)]
+struct TileShaping<'w> {
+    peak: Res<'w, CentralPeak>,
+    histogram: Res<'w, HistogramMatching>,
+    land_mask: Res<'w, LandMask>,
+    clipmap: Res<'w, Clipmap>,
+}
+
+impl TileShaping<'_> {
+    fn streamed(&self) -> bool {
+        self.clipmap.enabled
+    }
+
+    fn shaping(&self) -> Shaping<'_> {
+        Shaping {
+            peak: &self.peak,
+            histogram: &self.histogram,
+            land_mask: &self.land_mask,
+            streamed: self.streamed(),
+        }
+    }
+}
+
+/// Applies the large-scale shaping every generated tile gets, the central peak, the histogram
+/// matching and the land mask, to freshly generated heights.
+fn shape_tile(
+    heights: Vec<Vec<f32>>,
+    position: Position,
+    level: u32,
+    shaping: Shaping,
+    water_level: f32,
+) -> Vec<Vec<f32>> {
+    let Shaping {
+        peak,
+        histogram,
+        land_mask,
+        streamed,
+    } = shaping;
+    let mut heights = if peak.enabled {
+        peak::add_central_peak(heights, position, level, peak)
+    } else {
+        heights
+    };
+
+    if histogram.enabled && !streamed {
+        histogram::match_histogram(&mut heights, &histogram.target.bins());
+    }
+
+    // The mask outlines the single tile, the streamed world isn't bounded by it.
+    match &land_mask.image {
+        Some(mask) if !streamed => landmask::generate_masked(mask, heights, water_level),
+        _ => heights,
+    }
+}
+
+/// The assets every tile is made of, for systems that spawn tiles.
+#[derive(SystemParam)]
+struct TileAssets<'w> {
+    images: ResMut<'w, Assets<Image>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<TileMaterial>>,
+}
+
+fn spawn_tile(
+    commands: &mut Commands,
+    assets: &mut TileAssets,
+    color_settings: &ColorSettings,
+    position: Position,
+    level: u32,
+    heights: Vec<Vec<f32>>,
+) -> Entity {
+    let (px, py) = position.0;
+    let image_size = heights.len();
+
+    // Level 0 tiles are unit quads centered on their position; coarser levels double in size
+    // each step and sit slightly behind the finer ones so the detailed tiles draw on top.
+    let scale = (1 << level) as f32;
+    let center = Vec2::new(px as f32 + 0.5, py as f32 + 0.5) * scale - 0.5;
+
+    // Create the texture from dynamically generated image.
+    let texture = assets.images.add(Image::new(
+        Extent3d {
+            width: image_size as u32,
+            height: image_size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        colorize(&heights, position, color_settings),
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    ));
+
+    // Spawn in a quad with the generated image.
+    commands
+        .spawn((
+            MaterialMeshBundle {
+                // Replaced by an aligned quad once `SubpixelAlignment` sees the new tile.
+                mesh: assets.meshes.add(alignment::tile_mesh(image_size, false)),
+                material: assets.materials.add(TileMaterial {
+                    base: StandardMaterial {
+                        base_color_texture: Some(texture.clone()),
+                        double_sided: true,
+                        cull_mode: None,
+                        unlit: true,
+                        alpha_mode: AlphaMode::Blend,
+                        ..Default::default()
+                    },
+                    // Filled in from `DetailTexturing` and `WaterAnimation` once the tile exists.
+                    extension: TileExtension::default(),
                 }),
-                transform: Transform::from_xyz((px as i32) as f32, (py as i32) as f32, 0.0),
+                transform: Transform::from_xyz(center.x, center.y, -0.001 * level as f32)
+                    .with_scale(Vec3::new(scale, scale, 1.0)),
                 ..Default::default()
             },
             Tile,
-        ));
-    }
+            position,
+            TileLevel(level),
+            TileHeights(heights),
+        ))
+        .id()
+}
+
+/// Everything the settings window edits, for [`ui_example`].
+#[derive(SystemParam)]
+struct GenerationControls<'w> {
+    settings: ResMut<'w, GenSettings>,
+    clipmap: ResMut<'w, Clipmap>,
+    nudge: ResMut<'w, Nudge>,
+    seeds: ResMut<'w, SeedSource>,
+    peak: ResMut<'w, CentralPeak>,
+    histogram: ResMut<'w, HistogramMatching>,
+    progressive: ResMut<'w, ProgressiveGeneration>,
+    regenerate: EventWriter<'w, RegenerateEvent>,
 }
 
 fn ui_example(
     mut contexts: EguiContexts,
-    mut gentile: EventWriter<GenTileEvent>,
-    mut commands: Commands,
-    mut sprite_query: Query<(Entity, &Tile)>,
-    mut seed: Local<isize>,
-    mut roughness: Local<Option<f32>>,
-    mut node_size: Local<Option<usize>>,
+    controls: GenerationControls,
+    // Grouped to stay within the number of parameters a system should take.
+    (mut commands, mut load_tile): (Commands, EventWriter<LoadTileEvent>),
+    mut sprite_query: Query<(Entity, Has<GenTileTask>), Or<(With<Tile>, With<GenTileTask>)>>,
+    tile_query: Query<(&Position, &TileHeights)>,
+    mut project_path: Local<String>,
+    coloring: Coloring,
 ) {
-    const DEFAULT_ROUGHNESS: f32 = 2.0;
-    const DEFAULT_NODE_SIZE: usize = 6;
+    const DEFAULT_PROJECT_PATH: &str = "terrain.ron";
 
-    // Initialize default values if they are not set yet.
-    if roughness.is_none() {
-        *roughness = Some(DEFAULT_ROUGHNESS);
-    }
+    let GenerationControls {
+        mut settings,
+        mut clipmap,
+        mut nudge,
+        mut seeds,
+        mut peak,
+        mut histogram,
+        mut progressive,
+        mut regenerate,
+    } = controls;
 
-    if node_size.is_none() {
-        *node_size = Some(DEFAULT_NODE_SIZE);
+    if project_path.is_empty() {
+        *project_path = DEFAULT_PROJECT_PATH.to_string();
     }
 
     // Settings window.
     egui::Window::new("Terrain Generation Settings").show(contexts.ctx_mut(), |ui| {
-        ui.label(format!("Seed: {}", *seed));
-        ui.add(egui::Slider::new(roughness.as_mut().unwrap(), 1.0..=6.0).prefix("Roughness: "));
-        ui.add(egui::Slider::new(node_size.as_mut().unwrap(), 4..=10).prefix("Node Size"));
+        ui.label(format!("Seed: {}", settings.seed));
+        ui.horizontal(|ui| {
+            ui.label("Run Seed:");
+            ui.add(egui::DragValue::new(&mut settings.run_seed));
+        });
+        let roughness =
+            ui.add(egui::Slider::new(&mut settings.roughness, 1.0..=6.0).prefix("Roughness: "));
+        let node_size =
+            ui.add(egui::Slider::new(&mut settings.node_size, 4..=10).prefix("Node Size"));
+        ui.add(egui::Slider::new(&mut settings.rotation, 0..=3).prefix("Quarter Turns: "));
+        egui::ComboBox::from_label("Topology")
+            .selected_text(settings.topology.name())
+            .show_ui(ui, |ui| {
+                for topology in Topology::ALL {
+                    ui.selectable_value(&mut settings.topology, topology, topology.name());
+                }
+            });
+        egui::ComboBox::from_label("Arithmetic")
+            .selected_text(settings.arithmetic.name())
+            .show_ui(ui, |ui| {
+                for arithmetic in Arithmetic::ALL {
+                    ui.selectable_value(&mut settings.arithmetic, arithmetic, arithmetic.name());
+                }
+            });
 
-        if ui.button("Generate Terrain").clicked() {
-            // Clear all tiles. Should only be one.
-            for (entity, _) in sprite_query.iter_mut() {
-                commands.entity(entity).despawn();
-            }
+        // Whichever slider was touched last is the one the keyboard nudges.
+        if roughness.changed() || roughness.clicked() {
+            nudge.active = NudgeTarget::Roughness;
+        }
+        if node_size.changed() || node_size.clicked() {
+            nudge.active = NudgeTarget::NodeSize;
+        }
+        ui.label(format!("Nudging {} (Ctrl + arrows)", nudge.active.name()));
 
+        ui.separator();
+        // Reshapes the current terrain in place of picking a new seed.
+        if peak::central_peak_ui(ui, &mut peak) {
+            regenerate.send(RegenerateEvent);
+        }
+        if histogram::histogram_matching_ui(ui, &mut histogram) {
+            regenerate.send(RegenerateEvent);
+        }
+
+        ui.checkbox(&mut progressive.0, "Progressive Generation")
+            .on_hover_text("Show a coarse preview first, then add detail frame by frame.");
+
+        if ui.button("Generate Terrain").clicked() {
             // Generate a new seed.
-            *seed = rand::random();
-
-            // Send an event to generate a new tile.
-            gentile.send(GenTileEvent {
-                position: Position((0, 0)),
-                seed: *seed,
-                roughness: roughness.unwrap(),
-                image_size: 2usize.pow(node_size.unwrap() as u32) + 1,
+            settings.seed = seeds.next_seed();
+            regenerate.send(RegenerateEvent);
+        }
+
+        // Generating again cancels these, so they never hold up a new terrain.
+        let generating = sprite_query.iter().filter(|&(_, task)| task).count();
+        if generating > 0 {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Generating {} tiles", generating));
             });
         }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Project:");
+            ui.text_edit_singleline(&mut *project_path);
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Save Project").clicked() {
+                if let Some((position, heights)) = tile_query.iter().next() {
+                    let document = TerrainDocument {
+                        settings: *settings,
+                        position: *position,
+                        heights: heights.0.clone(),
+                    };
+
+                    match document.save(&*project_path) {
+                        Ok(()) => info!("Saved project to {}", *project_path),
+                        Err(err) => error!("Failed to save project {}: {}", *project_path, err),
+                    }
+                }
+            }
+
+            if ui.button("Export").clicked() {
+                if let Some((&position, heights)) = tile_query.iter().next() {
+                    let color_settings = coloring.settings();
+                    let sidecar = export::Sidecar {
+                        settings: &settings,
+                        position,
+                        stats: TileStats::compute(&heights.0, position, &color_settings),
+                    };
+                    let rgba = colorize(&heights.0, position, &color_settings);
+                    let dir = Path::new("export");
+                    match export::export_heightmap_and_terrain(dir, &sidecar, &heights.0, rgba) {
+                        Ok([heightmap, terrain]) => {
+                            info!("Exported {} and {}", heightmap.display(), terrain.display())
+                        }
+                        Err(err) => error!("Failed to export to {}: {}", dir.display(), err),
+                    }
+                }
+            }
+
+            if ui.button("Open Project").clicked() {
+                match TerrainDocument::open(&*project_path) {
+                    Ok(document) => {
+                        for (entity, _) in sprite_query.iter_mut() {
+                            commands.entity(entity).despawn();
+                        }
+
+                        // Restore the tile as saved, including any edits, rather than regenerating.
+                        clipmap.disable();
+                        *settings = document.settings;
+                        load_tile.send(LoadTileEvent {
+                            position: document.position,
+                            heights: document.heights,
+                        });
+                    }
+                    Err(err) => error!("Failed to open project {}: {}", *project_path, err),
+                }
+            }
+        });
     });
 }
 
-fn generate_map(position: Position, roughness: f32, seed: isize, image_size: usize) -> Vec<u8> {
-    // this has to be dynamically allocated because the image is not static.
-    let mut heightmap: Vec<Vec<f32>> = vec![vec![0.0; image_size]; image_size];
+/// The heights of one tile with the given topology and arithmetic.
+fn generate_tile(
+    position: Position,
+    roughness: f32,
+    seed: isize,
+    image_size: usize,
+    topology: Topology,
+    arithmetic: Arithmetic,
+) -> Vec<Vec<f32>> {
+    match (topology, arithmetic) {
+        (Topology::Planar, Arithmetic::Float) => {
+            generate_heightmap(position, roughness, seed, image_size)
+        }
+        (Topology::Planar, Arithmetic::Fixed) => {
+            fixed::generate_heightmap(position, roughness, seed, image_size)
+        }
+        (Topology::Torus, _) => topology::generate_torus(roughness, seed, image_size),
+    }
+}
 
-    let mut chunk_size = image_size - 1;
-    let mut roughness = roughness;
+/// Rotates a heightmap by `turns` quarter turns counter-clockwise as seen in the world. Only the
+/// lowest two bits of `turns` matter.
+fn rotate_quarter_turns(heights: &[Vec<f32>], turns: u8) -> Vec<Vec<f32>> {
+    let size = heights.len();
+    let mut rotated = heights.to_vec();
+    for _ in 0..turns % 4 {
+        // Rows run along -Y and columns along +X, so a counter-clockwise turn in the world
+        // moves `old[x][y]` to `new[size - 1 - y][x]`.
+        rotated = (0..size)
+            .map(|x| (0..size).map(|y| rotated[y][size - 1 - x]).collect())
+            .collect();
+    }
+    rotated
+}
 
-    // Easy cordnate to hash function. Allowing for unique but persistent outputs.
-    let hash = |x: i32, y: i32| {
-        let mut hasher = DefaultHasher::new();
-        hasher.write_isize(seed);
-        hasher.write_i32(x);
-        hasher.write_i32(y);
-        let res = (hasher.finish() % 0xFF) as f32 / 0xFF as f32;
-        res
-    };
+/// The four borders of a heightmap, which have to match for neighboring tiles to stitch.
+fn edges(heights: &[Vec<f32>]) -> [Vec<f32>; 4] {
+    let last = heights.len() - 1;
+    [
+        heights[0].clone(),
+        heights[last].clone(),
+        heights.iter().map(|row| row[0]).collect(),
+        heights.iter().map(|row| row[last]).collect(),
+    ]
+}
 
-    
-    // Set values for all four corners.
-    let (px, py) = position.0;
-    heightmap[0][0] = hash(px, py);
-    heightmap[0][image_size - 1] = hash(px, py + 1);
-    heightmap[image_size - 1][0] = hash(px + 1, py);
-    heightmap[image_size - 1][image_size - 1] = hash(px + 1, py + 1);
-
-    // The Diamond-Square algorithm.
-    while chunk_size > 1 {
-        let half = chunk_size / 2;
-
-        //square step
-        for y in (0..image_size - 1).step_by(chunk_size) {
-            for x in (0..image_size - 1).step_by(chunk_size) {
-                let top_left = heightmap[x][y];
-                let top_right = heightmap[x + chunk_size][y];
-                let bottom_left = heightmap[x][y + chunk_size];
-                let bottom_right = heightmap[x + chunk_size][y + chunk_size];
-
-                let average = (top_left + top_right + bottom_left + bottom_right) / 4.0;
-                heightmap[x + half][y + half] = average;
-
-                let random_factor = hash(x as i32, y as i32) * 2.0 - 1.0;
-                let random_offset = random_factor * roughness;
-                heightmap[x][y] += random_offset;
-            }
+fn generate_heightmap(
+    position: Position,
+    roughness: f32,
+    seed: isize,
+    image_size: usize,
+) -> Vec<Vec<f32>> {
+    DiamondSquare::new(image_size)
+        .seed(seed)
+        .roughness(roughness)
+        .position(position.0)
+        .generate()
+        .to_rows()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, tasks::TaskPool};
+    use diamond_square::Heightmap;
+
+    use super::*;
+
+    fn tile_event(position: Position, seed: isize, epoch: u64) -> GenTileEvent {
+        GenTileEvent {
+            position,
+            seed,
+            image_size: 65,
+            roughness: 2.0,
+            level: 0,
+            rotation: 0,
+            topology: Topology::Planar,
+            arithmetic: Arithmetic::Float,
+            epoch,
+        }
+    }
+
+    fn tile_build(event: GenTileEvent) -> TileBuild {
+        TileBuild {
+            event,
+            seed: event.seed,
+            tile_seed: None,
+            peak: CentralPeak::default(),
+            histogram: HistogramMatching::default(),
+            land_mask: LandMask::default(),
+            streamed: false,
+            color_settings: ColorSettings::default(),
+            telemetry: false,
+            points_of_interest: false,
         }
+    }
 
-        // diamond step
-        for y in (0..image_size).step_by(half) {
-            for x in ((y + half) % chunk_size..(image_size)).step_by(chunk_size) {
-                let mut neighbors = 0;
-                let mut neighbor_sum = 0.0;
+    /// A world with the resources tiles are spawned, shaped and colored with, for running the
+    /// tile systems once.
+    pub(crate) fn tile_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<TileMaterial>>();
+        world.init_resource::<Events<TileGenerated>>();
+        world.init_resource::<OutputMode>();
+        world.init_resource::<WaterLevel>();
+        world.init_resource::<CoastlineSmoothing>();
+        world.init_resource::<SnowModel>();
+        world.init_resource::<EffectiveElevation>();
+        world.init_resource::<DitherMode>();
+        world.init_resource::<Lighting>();
+        world.init_resource::<Climate>();
+        world.init_resource::<Strata>();
+        world.init_resource::<Walkability>();
+        world.init_resource::<Viewshed>();
+        world.init_resource::<PaletteCrossfade>();
+        world.init_resource::<ActiveLut>();
+        world.init_resource::<CentralPeak>();
+        world.init_resource::<HistogramMatching>();
+        world.init_resource::<LandMask>();
+        world.init_resource::<Clipmap>();
+        world.init_resource::<PointsOfInterest>();
+        world
+    }
 
-                if x > half {
-                    neighbors += 1;
-                    neighbor_sum += heightmap[x - half][y];
-                }
+    #[test]
+    fn tiles_built_on_the_task_pool_match_direct_generation() {
+        let pool = AsyncComputeTaskPool::get_or_init(TaskPool::new);
+        let event = tile_event(Position((1, -2)), 5, 0);
+        let tile = tile_build(event);
 
-                if y > half {
-                    neighbors += 1;
-                    neighbor_sum += heightmap[x][y - half];
-                }
+        let built = block_on(pool.spawn(async move { build_tile(tile) }));
 
-                if x + half < image_size - 1 {
-                    neighbors += 1;
-                    neighbor_sum += heightmap[x + half][y];
-                }
+        let direct = generate_tile(
+            event.position,
+            event.roughness,
+            event.seed,
+            event.image_size,
+            event.topology,
+            event.arithmetic,
+        );
+        assert_eq!(built.heights, direct);
+        assert!(built.points_of_interest.is_empty());
+    }
 
-                if y + half < image_size - 1 {
-                    neighbors += 1;
-                    neighbor_sum += heightmap[x][y + half];
-                }
+    #[test]
+    fn half_a_turn_flips_both_axes() {
+        let heights = generate_heightmap(Position((0, 0)), 2.0, 3, 17);
+        let flipped: Vec<Vec<f32>> = heights
+            .iter()
+            .rev()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        assert_eq!(rotate_quarter_turns(&heights, 2), flipped);
+        assert_eq!(rotate_quarter_turns(&heights, 4), heights);
+    }
 
-                heightmap[x][y] = neighbor_sum / neighbors as f32;
+    #[test]
+    fn only_tiles_of_the_latest_epoch_are_spawned() {
+        let pool = AsyncComputeTaskPool::get_or_init(TaskPool::new);
+        let mut world = tile_world();
 
-                let random = hash(x as i32, y as i32) * 2.0 - 1.0;
-                let random = random * roughness;
-                heightmap[x][y] += random;
-            }
+        // The seed changed three times in a row, each change starting a new epoch before the
+        // tiles of the last one were done.
+        let position = Position((0, 0));
+        for epoch in 1..=3 {
+            let tile = tile_build(tile_event(position, epoch as isize, epoch));
+            let task = pool.spawn(async move { build_tile(tile) });
+            world.spawn((GenTileTask { task, epoch }, position, TileLevel(0)));
         }
+        world.insert_resource(GenerationEpoch(3));
 
-        chunk_size /= 2;
-        roughness /= 2.0;
-    }
-
-    // Transform the raw data into a usable format.
-    heightmap
-        .into_iter()
-        .flatten()
-        // Plug each value into logistics curve to clamp (0-1).
-        .map(|f| 1.0 / (1.0 + std::f32::consts::E.powf(-f)))
-        // Apply basic coloring based on value.
-        .map(|f| {
-            let value = (f * 0xFF as f32) as i32;
-            match f {
-                f if f < 0.2 => (value) << 0,
-                f if f < 0.65 => (value) << 8,
-                f if f < 0.9 => (value / 2 << 16) | (value / 2 << 8) | value / 2,
-                _ => (value << 16) | (value << 8) | value,
-            }
-        })
-        // Convert to a color format that Bevy can use.
-        .map(|f| {
-            let r = ((f >> 16) & 0xFF) as u8;
-            let g = ((f >> 8) & 0xFF) as u8;
-            let b = (f & 0xFF) as u8;
-            [r, g, b, 0xFF]
-        })
-        .flatten()
-        .collect()
+        let mut pending = world.query::<&GenTileTask>();
+        while pending.iter(&world).next().is_some() {
+            world.run_system_once(finish_gentile_tasks);
+        }
+
+        let latest = tile_event(position, 3, 3);
+        let expected = generate_tile(
+            position,
+            latest.roughness,
+            latest.seed,
+            latest.image_size,
+            latest.topology,
+            latest.arithmetic,
+        );
+        let mut tiles = world.query_filtered::<&TileHeights, With<Tile>>();
+        let heights: Vec<_> = tiles.iter(&world).collect();
+        assert_eq!(heights.len(), 1);
+        assert_eq!(heights[0].0, expected);
+        assert_eq!(world.resource::<Events<TileGenerated>>().len(), 1);
+    }
+
+    #[test]
+    fn run_seeds_give_unrelated_worlds_that_reproduce() {
+        let world = |run_seed| {
+            let settings = GenSettings {
+                seed: 5,
+                run_seed,
+                ..default()
+            };
+            generate_heightmap(Position((0, 0)), 2.0, settings.noise_seed(), 65)
+        };
+        let (first, second) = (world(1), world(2));
+        assert_eq!(first, world(1));
+
+        // Pearson correlation of the two worlds' heights.
+        let (a, b) = (first.concat(), second.concat());
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        let (mean_a, mean_b) = (mean(&a), mean(&b));
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in a.iter().zip(&b) {
+            covariance += (a - mean_a) * (b - mean_b);
+            variance_a += (a - mean_a).powi(2);
+            variance_b += (b - mean_b).powi(2);
+        }
+        let correlation: f32 = covariance / (variance_a * variance_b).sqrt();
+        assert!(correlation.abs() < 0.25, "correlation {}", correlation);
+    }
+
+    #[test]
+    fn a_high_central_peak_is_the_summit_and_disabling_it_restores_the_tile() {
+        let position = Position((0, 0));
+        let heights = generate_heightmap(position, 2.0, 8, 65);
+        let shape = |peak: &CentralPeak| {
+            let shaping = Shaping {
+                peak,
+                histogram: &HistogramMatching::default(),
+                land_mask: &LandMask::default(),
+                streamed: false,
+            };
+            shape_tile(heights.clone(), position, 0, shaping, 0.2)
+        };
+
+        let peak = CentralPeak {
+            enabled: true,
+            height: 100.0,
+            ..default()
+        };
+        let raised = Heightmap::from_rows(&shape(&peak));
+        // Tile (0, 0) is centered on the world origin.
+        assert_eq!(raised.get(32, 32), Some(raised.min_max().1));
+
+        let disabled = CentralPeak {
+            enabled: false,
+            ..peak
+        };
+        assert_eq!(shape(&disabled), heights);
+    }
 }
@@ -0,0 +1,222 @@
+use bevy::{ecs::system::SystemParam, prelude::*, render::camera::ScalingMode};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{bump::BumpMapped, contours::Contours, lighting::Lighting, Tile};
+
+/// Empty space kept around the terrain in map mode, as a fraction of its size.
+const MAP_MARGIN: f32 = 0.05;
+
+/// A top-down orthographic view framing all loaded terrain, toggled with M. Leaving it puts the
+/// camera back where it was.
+#[derive(Resource, Debug)]
+pub struct MapMode {
+    /// Also shows contour lines and turns off lighting while in map mode, for a clean
+    /// cartographic look. Both are restored when leaving.
+    pub clean_look: bool,
+    /// The view from before entering map mode, while in it.
+    saved: Option<SavedView>,
+}
+
+impl Default for MapMode {
+    fn default() -> Self {
+        Self {
+            clean_look: true,
+            saved: None,
+        }
+    }
+}
+
+impl MapMode {
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+}
+
+/// The switches the clean look turns on and off.
+#[derive(SystemParam)]
+pub struct CleanLook<'w> {
+    contours: ResMut<'w, Contours>,
+    lighting: ResMut<'w, Lighting>,
+    bump: ResMut<'w, BumpMapped>,
+}
+
+#[derive(Debug)]
+struct SavedView {
+    transform: Transform,
+    projection: Projection,
+    /// The contour, lighting and bump mapping switches, if the clean look changed them.
+    look: Option<(bool, bool, bool)>,
+}
+
+pub fn toggle_map_mode(
+    mut contexts: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut map_mode: ResMut<MapMode>,
+    mut cameras: Query<(&mut Transform, &mut Projection), With<Camera>>,
+    tiles: Query<&Transform, (With<Tile>, Without<Camera>)>,
+    mut look: CleanLook,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() || !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    let Ok((mut transform, mut projection)) = cameras.get_single_mut() else {
+        return;
+    };
+
+    toggle(
+        &mut map_mode,
+        &mut transform,
+        &mut projection,
+        tiles.iter(),
+        &mut look.contours,
+        &mut look.lighting,
+        &mut look.bump,
+    );
+}
+
+/// Enters map mode framing `tiles`, or goes back to the saved view if already in it.
+fn toggle<'a>(
+    map_mode: &mut MapMode,
+    transform: &mut Transform,
+    projection: &mut Projection,
+    tiles: impl Iterator<Item = &'a Transform>,
+    contours: &mut Contours,
+    lighting: &mut Lighting,
+    bump: &mut BumpMapped,
+) {
+    if let Some(saved) = map_mode.saved.take() {
+        *transform = saved.transform;
+        *projection = saved.projection;
+        if let Some((contours_enabled, lighting_enabled, bump_enabled)) = saved.look {
+            contours.enabled = contours_enabled;
+            lighting.enabled = lighting_enabled;
+            bump.0 = bump_enabled;
+        }
+        return;
+    }
+
+    // Every tile is a unit quad scaled up by its level.
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    for tile in tiles {
+        let half = tile.scale.truncate() / 2.0;
+        min = min.min(tile.translation.truncate() - half);
+        max = max.max(tile.translation.truncate() + half);
+    }
+    if min.x > max.x {
+        return;
+    }
+
+    let look = map_mode.clean_look.then(|| {
+        let previous = (contours.enabled, lighting.enabled, bump.0);
+        contours.enabled = true;
+        lighting.enabled = false;
+        bump.0 = false;
+        previous
+    });
+    map_mode.saved = Some(SavedView {
+        transform: *transform,
+        projection: projection.clone(),
+        look,
+    });
+
+    let size = (max - min) * (1.0 + MAP_MARGIN * 2.0);
+    let center = (min + max) / 2.0;
+    // Keeping the height keeps the panning speed, which scales with it.
+    *transform = Transform::from_xyz(center.x, center.y, transform.translation.z.max(0.1));
+    *projection = Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::AutoMin {
+            min_width: size.x,
+            min_height: size.y,
+        },
+        ..default()
+    });
+}
+
+pub fn map_mode_ui(ui: &mut egui::Ui, map_mode: &mut MapMode) {
+    ui.checkbox(&mut map_mode.clean_look, "Clean Map Mode Look")
+        .on_hover_text("Map mode (M) shows contours and turns off lighting.");
+    if map_mode.is_active() {
+        ui.label("In map mode, press M to go back.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_mode_frames_the_tiles_and_restores_the_view() {
+        let mut map_mode = MapMode::default();
+        let view = Transform::from_xyz(3.0, -2.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let mut transform = view;
+        let mut projection = Projection::Perspective(default());
+        let tiles = [
+            Transform::from_xyz(0.0, 0.0, 0.0),
+            Transform::from_xyz(1.0, 0.0, 0.0),
+        ];
+        let mut contours = Contours::default();
+        let mut lighting = Lighting {
+            enabled: true,
+            ..default()
+        };
+        let mut bump = BumpMapped(true);
+
+        toggle(
+            &mut map_mode,
+            &mut transform,
+            &mut projection,
+            tiles.iter(),
+            &mut contours,
+            &mut lighting,
+            &mut bump,
+        );
+        assert!(map_mode.is_active());
+        assert_eq!(transform.translation.truncate(), Vec2::new(0.5, 0.0));
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+        assert!(matches!(projection, Projection::Orthographic(_)));
+        assert!(contours.enabled && !lighting.enabled && !bump.0);
+
+        toggle(
+            &mut map_mode,
+            &mut transform,
+            &mut projection,
+            tiles.iter(),
+            &mut contours,
+            &mut lighting,
+            &mut bump,
+        );
+        assert!(!map_mode.is_active());
+        assert_eq!(transform, view);
+        assert!(matches!(projection, Projection::Perspective(_)));
+        assert!(!contours.enabled && lighting.enabled && bump.0);
+    }
+
+    #[test]
+    fn without_the_clean_look_the_settings_are_left_alone() {
+        let mut map_mode = MapMode {
+            clean_look: false,
+            ..default()
+        };
+        let mut transform = Transform::from_xyz(0.0, 0.0, 5.0);
+        let mut projection = Projection::Perspective(default());
+        let mut contours = Contours::default();
+        let mut lighting = Lighting {
+            enabled: true,
+            ..default()
+        };
+        let mut bump = BumpMapped(true);
+
+        toggle(
+            &mut map_mode,
+            &mut transform,
+            &mut projection,
+            [Transform::default()].iter(),
+            &mut contours,
+            &mut lighting,
+            &mut bump,
+        );
+        assert!(map_mode.is_active());
+        assert!(!contours.enabled && lighting.enabled && bump.0);
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
+        texture::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+    },
+};
+
+/// The material every tile is drawn with: the colored terrain texture, plus the effects in
+/// [`TileExtension`].
+pub type TileMaterial = ExtendedMaterial<StandardMaterial, TileExtension>;
+
+/// The per-tile effects on top of the standard material, see `assets/shaders/tile.wgsl`. Each
+/// effect is off while its strength is 0.
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone, Default)]
+#[bind_group_data(TileExtensionKey)]
+pub struct TileExtension {
+    /// 1 blends the detail texture fully at zero distance, see
+    /// [`DetailTexturing`](crate::detail::DetailTexturing).
+    #[uniform(100)]
+    pub detail_strength: f32,
+    #[uniform(100)]
+    pub fade_distance: f32,
+    #[uniform(100)]
+    pub detail_tiling: f32,
+    /// How far the water normals bend the shading, see
+    /// [`WaterAnimation`](crate::water::WaterAnimation).
+    #[uniform(100)]
+    pub water_strength: f32,
+    #[uniform(100)]
+    pub water_speed: f32,
+    #[uniform(100)]
+    pub water_tiling: f32,
+    #[texture(101)]
+    #[sampler(102)]
+    pub detail: Option<Handle<Image>>,
+    /// Red is 1 on water cells and 0 on land, one texel per cell like the tile texture.
+    #[texture(103)]
+    #[sampler(104)]
+    pub water_mask: Option<Handle<Image>>,
+    #[texture(105)]
+    #[sampler(106)]
+    pub water_normals: Option<Handle<Image>>,
+    /// Runs the [`PostProcessShader`](crate::postprocess::PostProcessShader) over the final
+    /// color. Not a binding, it selects the pipeline.
+    pub post_process: bool,
+}
+
+/// The parts of a [`TileExtension`] that need a different pipeline.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TileExtensionKey {
+    post_process: bool,
+}
+
+impl From<&TileExtension> for TileExtensionKey {
+    fn from(extension: &TileExtension) -> Self {
+        Self {
+            post_process: extension.post_process,
+        }
+    }
+}
+
+impl MaterialExtension for TileExtension {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/tile.wgsl".into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            if key.bind_group_data.post_process {
+                fragment.shader_defs.push("POST_PROCESS".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A square RGBA texture that repeats across the world with smooth filtering, for textures that
+/// are mapped in world space rather than per tile.
+pub fn tiling_texture(size: u32, data: Vec<u8>) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    // The nearest-filtering default would show every texel as a hard square.
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        mag_filter: ImageFilterMode::Linear,
+        min_filter: ImageFilterMode::Linear,
+        ..default()
+    });
+    image
+}
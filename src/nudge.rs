@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{coloring::SnowModel, GenSettings, RegenerateEvent};
+
+/// Holding either control key turns the arrow keys from camera panning into parameter nudging.
+const MODIFIERS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+
+/// How long the keys have to rest before the terrain is regenerated, so holding an arrow down
+/// doesn't regenerate on every repeat.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum NudgeTarget {
+    #[default]
+    Roughness,
+    NodeSize,
+    SnowLine,
+    MaxSlope,
+}
+
+impl NudgeTarget {
+    const ALL: [NudgeTarget; 4] = [
+        NudgeTarget::Roughness,
+        NudgeTarget::NodeSize,
+        NudgeTarget::SnowLine,
+        NudgeTarget::MaxSlope,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NudgeTarget::Roughness => "Roughness",
+            NudgeTarget::NodeSize => "Node Size",
+            NudgeTarget::SnowLine => "Snow Line",
+            NudgeTarget::MaxSlope => "Max Slope",
+        }
+    }
+
+    fn cycle(self, step: isize) -> Self {
+        let index = Self::ALL.iter().position(|&target| target == self).unwrap() as isize;
+        Self::ALL[(index + step).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+}
+
+/// Which parameter the keyboard adjusts, and the pending regeneration if one was nudged.
+#[derive(Resource, Default)]
+pub struct Nudge {
+    pub active: NudgeTarget,
+    pending: Option<Timer>,
+}
+
+pub fn modifier_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed(MODIFIERS)
+}
+
+/// Ctrl + Up/Down nudges the active parameter by a fixed step, Ctrl + Left/Right picks which
+/// parameter is active.
+pub fn nudge_parameters(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut nudge: ResMut<Nudge>,
+    mut settings: ResMut<GenSettings>,
+    mut snow: ResMut<SnowModel>,
+    mut regenerate: EventWriter<RegenerateEvent>,
+) {
+    if let Some(timer) = nudge.pending.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            nudge.pending = None;
+            regenerate.send(RegenerateEvent);
+        }
+    }
+
+    if !modifier_pressed(&keys) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        nudge.active = nudge.active.cycle(1);
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        nudge.active = nudge.active.cycle(-1);
+    }
+
+    let direction = match (
+        keys.just_pressed(KeyCode::ArrowUp),
+        keys.just_pressed(KeyCode::ArrowDown),
+    ) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => return,
+    };
+
+    match nudge.active {
+        NudgeTarget::Roughness => {
+            settings.roughness = (settings.roughness + direction * 0.05).clamp(1.0, 6.0);
+        }
+        NudgeTarget::NodeSize => {
+            settings.node_size =
+                (settings.node_size as isize + direction as isize).clamp(4, 10) as usize;
+        }
+        // Coloring changes recolor the cached heights on their own, no regeneration needed.
+        NudgeTarget::SnowLine => {
+            snow.snow_line = (snow.snow_line + direction * 0.01).clamp(0.5, 1.0);
+            return;
+        }
+        NudgeTarget::MaxSlope => {
+            snow.max_slope = (snow.max_slope + direction * 0.25).clamp(0.5, 20.0);
+            return;
+        }
+    }
+
+    nudge.pending = Some(Timer::new(DEBOUNCE, TimerMode::Once));
+}
@@ -0,0 +1,211 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+/// Number of steps per cycle the autoplay moves `t` in, so the tiles are recolored at most that
+/// many times per cycle instead of every frame.
+const AUTOPLAY_STEPS: f32 = 32.0;
+
+/// The base colors of the terrain bands. Each is shaded by the cell's quantized brightness like
+/// [`land_color`](crate::coloring::land_color).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ColorPalette {
+    pub name: &'static str,
+    pub water: [u8; 3],
+    pub lowland: [u8; 3],
+    pub rock: [u8; 3],
+    pub peak: [u8; 3],
+}
+
+impl ColorPalette {
+    /// The colors of the default bands.
+    pub const SUMMER: ColorPalette = ColorPalette {
+        name: "Summer",
+        water: [0, 0, 255],
+        lowland: [0, 255, 0],
+        rock: [128, 128, 128],
+        peak: [255, 255, 255],
+    };
+    pub const AUTUMN: ColorPalette = ColorPalette {
+        name: "Autumn",
+        water: [20, 60, 160],
+        lowland: [210, 120, 30],
+        rock: [120, 100, 90],
+        peak: [240, 240, 235],
+    };
+    pub const WINTER: ColorPalette = ColorPalette {
+        name: "Winter",
+        water: [70, 110, 170],
+        lowland: [235, 240, 250],
+        rock: [150, 150, 160],
+        peak: [255, 255, 255],
+    };
+    pub const NIGHT: ColorPalette = ColorPalette {
+        name: "Night",
+        water: [0, 0, 70],
+        lowland: [10, 60, 40],
+        rock: [50, 50, 70],
+        peak: [120, 120, 150],
+    };
+    /// Freshly uplifted land before any weathering.
+    pub const BARE_ROCK: ColorPalette = ColorPalette {
+        name: "Bare Rock",
+        water: [40, 60, 90],
+        lowland: [150, 140, 130],
+        rock: [110, 100, 95],
+        peak: [200, 195, 190],
+    };
+    /// Weathered land that hasn't been grown over yet.
+    pub const SOIL: ColorPalette = ColorPalette {
+        name: "Soil",
+        water: [30, 70, 130],
+        lowland: [140, 100, 60],
+        rock: [115, 95, 80],
+        peak: [230, 225, 220],
+    };
+
+    pub const ALL: [ColorPalette; 6] = [
+        ColorPalette::SUMMER,
+        ColorPalette::AUTUMN,
+        ColorPalette::WINTER,
+        ColorPalette::NIGHT,
+        ColorPalette::BARE_ROCK,
+        ColorPalette::SOIL,
+    ];
+
+    /// The color of a cell at height `f` (0-1) with quantized brightness `value`.
+    pub fn color(&self, f: f32, value: u8, land: bool) -> [u8; 3] {
+        let base = match f {
+            _ if !land => self.water,
+            f if f < 0.65 => self.lowland,
+            f if f < 0.9 => self.rock,
+            _ => self.peak,
+        };
+        base.map(|c| (c as u16 * value as u16 / 0xFF) as u8)
+    }
+}
+
+/// Blends the terrain bands from palette `a` to palette `b`, for seasonal or day/night themes
+/// of the same terrain. While enabled it replaces the fixed bands, snow and strata; a LUT or the
+/// biome output still take precedence.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct PaletteCrossfade {
+    pub enabled: bool,
+    pub a: ColorPalette,
+    pub b: ColorPalette,
+    /// 0 is all `a`, 1 all `b`.
+    pub t: f32,
+    /// Swings `t` back and forth on its own.
+    pub autoplay: bool,
+    /// Seconds for one full swing from `a` to `b` and back.
+    pub period: f32,
+}
+
+impl Default for PaletteCrossfade {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            a: ColorPalette::SUMMER,
+            b: ColorPalette::WINTER,
+            t: 0.0,
+            autoplay: false,
+            period: 20.0,
+        }
+    }
+}
+
+impl PaletteCrossfade {
+    /// The palette halfway through the crossfade, for output that can't blend per pixel. Named
+    /// after whichever end it is closer to.
+    pub fn blended(&self) -> ColorPalette {
+        let mix = |a: [u8; 3], b: [u8; 3]| {
+            [0, 1, 2].map(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * self.t).round() as u8)
+        };
+        let (a, b) = (&self.a, &self.b);
+        ColorPalette {
+            name: if self.t < 0.5 { a.name } else { b.name },
+            water: mix(a.water, b.water),
+            lowland: mix(a.lowland, b.lowland),
+            rock: mix(a.rock, b.rock),
+            peak: mix(a.peak, b.peak),
+        }
+    }
+
+    pub fn color(&self, f: f32, value: u8, land: bool) -> [u8; 3] {
+        let (a, b) = (self.a.color(f, value, land), self.b.color(f, value, land));
+        [0, 1, 2].map(|i| {
+            let (a, b) = (a[i] as f32, b[i] as f32);
+            (a + (b - a) * self.t).round() as u8
+        })
+    }
+}
+
+/// Drives `t` while autoplay is on. Untouched otherwise, so the slider stays where it was left.
+pub fn animate_crossfade(time: Res<Time>, mut crossfade: ResMut<PaletteCrossfade>) {
+    if !crossfade.enabled || !crossfade.autoplay {
+        return;
+    }
+
+    let phase = time.elapsed_seconds() / crossfade.period.max(0.1);
+    let t = 0.5 - 0.5 * (phase * TAU).cos();
+    let t = (t * AUTOPLAY_STEPS).round() / AUTOPLAY_STEPS;
+    // Only recolor when the step actually moves.
+    if crossfade.t != t {
+        crossfade.t = t;
+    }
+}
+
+pub fn crossfade_ui(ui: &mut egui::Ui, crossfade: &mut PaletteCrossfade) {
+    ui.checkbox(&mut crossfade.enabled, "Palette Crossfade");
+    for (label, palette) in [("From", &mut crossfade.a), ("To", &mut crossfade.b)] {
+        egui::ComboBox::from_label(label)
+            .selected_text(palette.name)
+            .show_ui(ui, |ui| {
+                for option in ColorPalette::ALL {
+                    ui.selectable_value(palette, option, option.name);
+                }
+            });
+    }
+    ui.add_enabled(
+        !crossfade.autoplay,
+        egui::Slider::new(&mut crossfade.t, 0.0..=1.0).prefix("Blend: "),
+    );
+    ui.checkbox(&mut crossfade.autoplay, "Autoplay");
+    ui.add(
+        egui::Slider::new(&mut crossfade.period, 1.0..=120.0)
+            .prefix("Cycle: ")
+            .suffix(" s"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_ends_of_the_crossfade_are_the_palettes() {
+        let mut crossfade = PaletteCrossfade {
+            enabled: true,
+            a: ColorPalette::AUTUMN,
+            b: ColorPalette::NIGHT,
+            ..default()
+        };
+
+        for i in 0..=20 {
+            let f = i as f32 / 20.0;
+            for (value, land) in [(40, false), (128, true), (250, true)] {
+                crossfade.t = 0.0;
+                assert_eq!(
+                    crossfade.color(f, value, land),
+                    crossfade.a.color(f, value, land)
+                );
+                crossfade.t = 1.0;
+                assert_eq!(
+                    crossfade.color(f, value, land),
+                    crossfade.b.color(f, value, land)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::Position;
+
+/// Raises a cone around the world origin so the terrain always has one large central mountain,
+/// with the generated detail decorating its flanks. Together with a land mask this makes
+/// volcanic islands.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct CentralPeak {
+    pub enabled: bool,
+    /// Height added at the summit, in raw height units.
+    pub height: f32,
+    /// Distance from the summit at which the cone meets the terrain, in tile widths.
+    pub radius: f32,
+}
+
+impl Default for CentralPeak {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            height: 3.0,
+            radius: 0.4,
+        }
+    }
+}
+
+/// Adds `height * (1 - clamp(distance / radius, 0, 1))` to every cell of a freshly generated
+/// tile, measuring the distance in the world so the cone lines up across tiles of every clipmap
+/// level.
+pub fn add_central_peak(
+    heights: Vec<Vec<f32>>,
+    position: Position,
+    level: u32,
+    peak: &CentralPeak,
+) -> Vec<Vec<f32>> {
+    let size = heights.len();
+    let (px, py) = position.0;
+    // Same placement as `spawn_tile`.
+    let scale = (1 << level) as f32;
+    let center = Vec2::new(px as f32 + 0.5, py as f32 + 0.5) * scale - 0.5;
+    let radius = peak.radius.max(f32::EPSILON);
+
+    heights
+        .into_iter()
+        .enumerate()
+        .map(|(x, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(|(y, f)| {
+                    let local =
+                        position.cell_to_world(size, (x, y)) - Vec2::new(px as f32, py as f32);
+                    let distance = (center + local * scale).length();
+                    f + peak.height * (1.0 - (distance / radius).clamp(0.0, 1.0))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns whether anything changed, so the terrain can be regenerated.
+pub fn central_peak_ui(ui: &mut egui::Ui, peak: &mut CentralPeak) -> bool {
+    let enabled = ui.checkbox(&mut peak.enabled, "Central Peak");
+    let height = ui.add(egui::Slider::new(&mut peak.height, 0.5..=10.0).prefix("Peak Height: "));
+    let radius = ui.add(
+        egui::Slider::new(&mut peak.radius, 0.05..=4.0)
+            .prefix("Peak Radius: ")
+            .suffix(" tiles"),
+    );
+    enabled.changed() || height.changed() || radius.changed()
+}
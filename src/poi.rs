@@ -0,0 +1,337 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    climate::{self, Biome},
+    coloring::{logistic, ColorSettings},
+    slope, watershed, Position, Tile, TileGenerated, TileHeights,
+};
+
+/// Steepest slope, in height per tile width, that still counts as flat for a plateau.
+const PLATEAU_SLOPE: f32 = 1.0;
+/// Fewest cells a flat area needs to count as a plateau.
+const MIN_PLATEAU_CELLS: usize = 16;
+/// Most river mouths reported per tile, the ones draining the most land.
+const MAX_RIVER_MOUTHS: usize = 3;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PoiKind {
+    /// The highest cell on the tile.
+    HighestPeak,
+    /// The lowest cell that is still dry.
+    DeepestValley,
+    /// The middle of the largest flat area in the upper half of the land.
+    LargestPlateau,
+    /// Where a river, following the steepest descent, reaches the water.
+    RiverMouth,
+}
+
+impl PoiKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            PoiKind::HighestPeak => "Highest Peak",
+            PoiKind::DeepestValley => "Deepest Valley",
+            PoiKind::LargestPlateau => "Largest Plateau",
+            PoiKind::RiverMouth => "River Mouth",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PoiKind::HighestPeak => Color::WHITE,
+            PoiKind::DeepestValley => Color::ORANGE,
+            PoiKind::LargestPlateau => Color::FUCHSIA,
+            PoiKind::RiverMouth => Color::CYAN,
+        }
+    }
+}
+
+/// A named location on a tile, for placing landmarks or quests.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PointOfInterest {
+    pub kind: PoiKind,
+    /// The cell, as in `heights[x][y]`.
+    pub position: (usize, usize),
+    /// The biome of the cell, `None` under water.
+    pub biome: Option<Biome>,
+}
+
+/// Whether points of interest are found for every generated tile and sent along in
+/// [`TileGenerated`].
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct PointsOfInterest {
+    pub enabled: bool,
+    pub show_markers: bool,
+}
+
+impl Default for PointsOfInterest {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            show_markers: true,
+        }
+    }
+}
+
+/// The points of interest of the most recently generated single tile.
+#[derive(Resource, Default)]
+pub struct CurrentPointsOfInterest(pub Option<(Position, Vec<PointOfInterest>)>);
+
+/// Finds the points of interest of a tile from its heights after the logistic curve (0-1) and
+/// its biomes, with the same water level as the coloring. Only depends on the heights, so the
+/// same tile always gets the same points. Ties go to the first cell in row order.
+pub fn find_points_of_interest(
+    heights: &[Vec<f32>],
+    biomes: &[Vec<Biome>],
+    water_level: f32,
+) -> Vec<PointOfInterest> {
+    let size = heights.len();
+    let height = |(x, y): (usize, usize)| heights[x][y];
+    let is_land = |cell: (usize, usize)| height(cell) >= water_level;
+    let cells: Vec<(usize, usize)> = (0..size)
+        .flat_map(|x| (0..size).map(move |y| (x, y)))
+        .collect();
+    let point = |kind, cell: (usize, usize)| PointOfInterest {
+        kind,
+        position: cell,
+        biome: is_land(cell).then(|| biomes[cell.0][cell.1]),
+    };
+
+    let mut points = Vec::new();
+
+    // `max_by` and `min_by` keep the last of equal elements and the first, respectively.
+    let highest = cells
+        .iter()
+        .rev()
+        .max_by(|&&a, &&b| height(a).total_cmp(&height(b)));
+    points.extend(highest.map(|&cell| point(PoiKind::HighestPeak, cell)));
+
+    let deepest = cells
+        .iter()
+        .filter(|&&cell| is_land(cell))
+        .min_by(|&&a, &&b| height(a).total_cmp(&height(b)));
+    points.extend(deepest.map(|&cell| point(PoiKind::DeepestValley, cell)));
+
+    let plateau = largest_plateau(heights, water_level);
+    points.extend(plateau.map(|cell| point(PoiKind::LargestPlateau, cell)));
+
+    let mouths = river_mouths(heights, water_level);
+    points.extend(
+        mouths
+            .into_iter()
+            .map(|cell| point(PoiKind::RiverMouth, cell)),
+    );
+
+    points
+}
+
+/// The cell nearest the middle of the largest connected flat area above halfway between the
+/// water and the highest possible height.
+fn largest_plateau(heights: &[Vec<f32>], water_level: f32) -> Option<(usize, usize)> {
+    let size = heights.len();
+    let slopes = slope::slope(heights);
+    let highland = (water_level + 1.0) / 2.0;
+    let is_flat =
+        |(x, y): (usize, usize)| heights[x][y] >= highland && slopes[x][y] < PLATEAU_SLOPE;
+
+    let mut visited = vec![vec![false; size]; size];
+    let mut largest: Vec<(usize, usize)> = Vec::new();
+    for x in 0..size {
+        for y in 0..size {
+            if visited[x][y] || !is_flat((x, y)) {
+                continue;
+            }
+
+            let mut area = Vec::new();
+            let mut queue = VecDeque::from([(x, y)]);
+            visited[x][y] = true;
+            while let Some(cell) = queue.pop_front() {
+                area.push(cell);
+                for (nx, ny) in watershed::neighbors(size, cell) {
+                    if !visited[nx][ny] && is_flat((nx, ny)) {
+                        visited[nx][ny] = true;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            if area.len() > largest.len() {
+                largest = area;
+            }
+        }
+    }
+
+    if largest.len() < MIN_PLATEAU_CELLS {
+        return None;
+    }
+
+    let count = largest.len() as f32;
+    let (cx, cy) = largest.iter().fold((0.0, 0.0), |(sx, sy), &(x, y)| {
+        (sx + x as f32 / count, sy + y as f32 / count)
+    });
+    let distance = |&(x, y): &(usize, usize)| (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+    largest
+        .into_iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+/// Land cells whose steepest descent leads into the water, draining at least a tile width's
+/// worth of cells, most draining first.
+fn river_mouths(heights: &[Vec<f32>], water_level: f32) -> Vec<(usize, usize)> {
+    let size = heights.len();
+    let height = |(x, y): (usize, usize)| heights[x][y];
+
+    // Every cell drains to its lowest neighbor, if that is lower.
+    let downstream = |cell: (usize, usize)| {
+        watershed::neighbors(size, cell)
+            .filter(|&n| height(n) < height(cell))
+            .min_by(|&a, &b| height(a).total_cmp(&height(b)))
+    };
+
+    // Pass the drained area downhill, highest cells first.
+    let mut order: Vec<(usize, usize)> = (0..size)
+        .flat_map(|x| (0..size).map(move |y| (x, y)))
+        .collect();
+    order.sort_by(|&a, &b| height(b).total_cmp(&height(a)).then(a.cmp(&b)));
+    let mut drained = vec![vec![1usize; size]; size];
+    let mut mouths = Vec::new();
+    for cell in order {
+        let Some(next) = downstream(cell) else {
+            continue;
+        };
+        let area = drained[cell.0][cell.1];
+        drained[next.0][next.1] += area;
+        if height(cell) >= water_level && height(next) < water_level && area >= size {
+            mouths.push((area, cell));
+        }
+    }
+
+    mouths.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    mouths
+        .into_iter()
+        .take(MAX_RIVER_MOUTHS)
+        .map(|(_, cell)| cell)
+        .collect()
+}
+
+/// [`find_points_of_interest`] for a tile's raw heights, classified like the coloring does.
+pub fn tile_points_of_interest(
+    heights: &[Vec<f32>],
+    position: Position,
+    settings: &ColorSettings,
+) -> Vec<PointOfInterest> {
+    let heights: Vec<Vec<f32>> = heights
+        .iter()
+        .map(|row| row.iter().map(|&f| logistic(f)).collect())
+        .collect();
+    let water_level = settings.water_level.0;
+    let biomes = climate::biome_field(&heights, position, water_level, &settings.climate);
+    find_points_of_interest(&heights, &biomes, water_level)
+}
+
+/// Keeps the points of the single tile for the window.
+pub fn track_points_of_interest(
+    mut generated: EventReader<TileGenerated>,
+    mut current: ResMut<CurrentPointsOfInterest>,
+) {
+    for event in generated.read().filter(|event| event.level == 0) {
+        current.0 = Some((event.position, event.points_of_interest.clone()));
+    }
+}
+
+pub fn points_of_interest_ui(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    mut settings: ResMut<PointsOfInterest>,
+    current: Res<CurrentPointsOfInterest>,
+    tiles: Query<(&Position, &TileHeights), With<Tile>>,
+) {
+    let mut new_settings = *settings;
+
+    // Points of a tile that has since been replaced are stale.
+    let current = current
+        .0
+        .as_ref()
+        .filter(|(position, _)| new_settings.enabled && tiles.iter().any(|(p, _)| p == position));
+
+    if let Some((position, points)) = current.filter(|_| new_settings.show_markers) {
+        if let Some((_, heights)) = tiles.iter().find(|(p, _)| *p == position) {
+            for point in points {
+                let world = position.cell_to_world(heights.0.len(), point.position);
+                gizmos.circle(
+                    world.extend(0.01),
+                    Direction3d::Z,
+                    0.015,
+                    point.kind.color(),
+                );
+            }
+        }
+    }
+
+    egui::Window::new("Points of Interest")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut new_settings.enabled, "Find Points of Interest")
+                .on_hover_text("Applies to tiles generated from now on.");
+            ui.checkbox(&mut new_settings.show_markers, "Show Markers");
+
+            let Some((_, points)) = current else {
+                ui.label("No points found yet.");
+                return;
+            };
+            for point in points {
+                let (x, y) = point.position;
+                let biome = point
+                    .biome
+                    .map_or("Water".to_string(), |biome| format!("{:?}", biome));
+                ui.label(format!(
+                    "{} at ({}, {}), {}",
+                    point.kind.name(),
+                    x,
+                    y,
+                    biome
+                ));
+            }
+        });
+
+    settings.set_if_neq(new_settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use diamond_square::DiamondSquare;
+
+    use super::*;
+
+    #[test]
+    fn the_highest_peak_is_the_highest_cell() {
+        let raw = DiamondSquare::new(65)
+            .seed(5)
+            .roughness(2.0)
+            .generate()
+            .to_rows();
+        let heights: Vec<Vec<f32>> = raw
+            .iter()
+            .map(|row| row.iter().map(|&f| logistic(f)).collect())
+            .collect();
+        let biomes = vec![vec![Biome::Shrubland; 65]; 65];
+
+        let mut argmax = (0, 0);
+        for (x, row) in heights.iter().enumerate() {
+            for (y, &f) in row.iter().enumerate() {
+                if f > heights[argmax.0][argmax.1] {
+                    argmax = (x, y);
+                }
+            }
+        }
+
+        let points = find_points_of_interest(&heights, &biomes, 0.2);
+        let peaks: Vec<_> = points
+            .iter()
+            .filter(|point| point.kind == PoiKind::HighestPeak)
+            .collect();
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].position, argmax);
+    }
+}
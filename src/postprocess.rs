@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::material::TileMaterial;
+
+/// The shader loaded at startup, which leaves the terrain as it is.
+const DEFAULT_SHADER: &str = "shaders/post_process.wgsl";
+
+/// Runs a WGSL function over the color of every tile fragment, after the terrain texture and
+/// the other tile effects. The shader has to declare
+/// `#define_import_path diamond_square::post_process` and define
+/// `fn post_process(color: vec4<f32>, uv: vec2<f32>, world_position: vec4<f32>) -> vec4<f32>`,
+/// see `assets/shaders/post_process.wgsl`.
+#[derive(Resource, Debug, PartialEq, Clone)]
+pub struct PostProcessShader {
+    pub enabled: bool,
+    pub shader: Handle<Shader>,
+}
+
+impl FromWorld for PostProcessShader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            enabled: false,
+            shader: world.resource::<AssetServer>().load(DEFAULT_SHADER),
+        }
+    }
+}
+
+/// Switches the post-process pass of every tile material on or off with [`PostProcessShader`].
+/// Switching re-specializes the tile pipeline, the shader itself is picked up by its import path.
+pub fn update_post_process(
+    post_process: Res<PostProcessShader>,
+    tiles: Query<&Handle<TileMaterial>, Added<Handle<TileMaterial>>>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+) {
+    if post_process.is_changed() {
+        materials
+            .iter_mut()
+            .for_each(|(_, material)| material.extension.post_process = post_process.enabled);
+        return;
+    }
+
+    for handle in tiles.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.extension.post_process = post_process.enabled;
+        }
+    }
+}
+
+pub fn post_process_ui(
+    mut contexts: EguiContexts,
+    mut post_process: ResMut<PostProcessShader>,
+    asset_server: Res<AssetServer>,
+    mut path: Local<String>,
+) {
+    if path.is_empty() {
+        *path = DEFAULT_SHADER.to_string();
+    }
+
+    let mut enabled = post_process.enabled;
+    egui::Window::new("Post-Process")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut enabled, "Post-Process Shader");
+            ui.horizontal(|ui| {
+                ui.label("Shader:");
+                ui.text_edit_singleline(&mut *path);
+                if ui.button("Load Shader").clicked() {
+                    // Dropping the old handle unloads it, so its import path is free again.
+                    post_process.shader = asset_server.load(path.clone());
+                }
+            });
+            ui.label("Paths are relative to the assets folder.");
+
+            if let Some(state) = asset_server.get_load_state(post_process.shader.id()) {
+                ui.label(format!("{:?}", state));
+            }
+        });
+
+    post_process.set_if_neq(PostProcessShader {
+        enabled,
+        shader: post_process.shader.clone(),
+    });
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use diamond_square::Generation;
+
+use crate::{
+    coloring::{colorize, Coloring},
+    material::TileMaterial,
+    poi::{self, PointsOfInterest},
+    rotate_quarter_turns, shape_tile, shore, Position, TileAssets, TileGenerated, TileHeights,
+    TileShaping,
+};
+
+/// Cells per side of the preview shown before the first refinement.
+const PREVIEW_SIZE: usize = 65;
+
+/// Shows a coarse preview of every new tile right away and adds one level of detail per frame
+/// afterwards, instead of blocking until the whole tile is generated. The finished tile is
+/// identical to one generated in one go.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Copy)]
+pub struct ProgressiveGeneration(pub bool);
+
+/// A tile still showing a preview, with the generation it is being refined by.
+#[derive(Component)]
+pub struct Refining {
+    pub generation: Generation,
+    pub rotation: u8,
+}
+
+/// Steps until the generated cells are at most `(image_size - 1) / (preview_size - 1)` apart,
+/// i.e. a `preview_size` tile's worth of detail.
+pub fn step_to_preview(generation: &mut Generation) {
+    let image_size = generation.heights().len();
+    let spacing = ((image_size - 1) / (PREVIEW_SIZE - 1)).max(1);
+    while generation.spacing() > spacing {
+        generation.step();
+    }
+}
+
+/// The heights so far at full resolution, bilinearly filling the cells between the ones already
+/// generated.
+pub fn preview(generation: &Generation) -> Vec<Vec<f32>> {
+    let h = generation.heights();
+    let image_size = h.len();
+    let spacing = generation.spacing();
+    let last = image_size - 1;
+
+    (0..image_size)
+        .map(|x| {
+            (0..image_size)
+                .map(|y| {
+                    let (x0, y0) = (x / spacing * spacing, y / spacing * spacing);
+                    let (x1, y1) = ((x0 + spacing).min(last), (y0 + spacing).min(last));
+                    let u = (x - x0) as f32 / spacing as f32;
+                    let v = (y - y0) as f32 / spacing as f32;
+                    let top = h[x0][y0] * (1.0 - v) + h[x0][y1] * v;
+                    let bottom = h[x1][y0] * (1.0 - v) + h[x1][y1] * v;
+                    top * (1.0 - u) + bottom * u
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Adds one level of detail to every tile still being refined and redraws its texture in
+/// place. The last level puts the finished heights in.
+pub fn refine_tiles(
+    mut commands: Commands,
+    mut tiles: Query<(
+        Entity,
+        &mut Refining,
+        &mut TileHeights,
+        &Position,
+        &Handle<TileMaterial>,
+    )>,
+    mut assets: TileAssets,
+    coloring: Coloring,
+    shaping: TileShaping,
+    points_of_interest: Res<PointsOfInterest>,
+    mut tile_generated: EventWriter<TileGenerated>,
+) {
+    let settings = coloring.settings();
+
+    for (entity, mut refining, mut heights, &position, material) in tiles.iter_mut() {
+        refining.generation.step();
+        let raw = if refining.generation.is_done() {
+            commands.entity(entity).remove::<Refining>();
+            refining.generation.heights().to_vec()
+        } else {
+            preview(&refining.generation)
+        };
+
+        let shaped = shape_tile(raw, position, 0, shaping.shaping(), settings.water_level.0);
+        let shaped = rotate_quarter_turns(&shaped, refining.rotation);
+
+        if refining.generation.is_done() {
+            tile_generated.send(TileGenerated {
+                entity,
+                position,
+                level: 0,
+                points_of_interest: if points_of_interest.enabled {
+                    poi::tile_points_of_interest(&shaped, position, &settings)
+                } else {
+                    Vec::new()
+                },
+                distance_to_water: shore::tile_distance_to_water(&shaped, settings.water_level.0),
+            });
+        }
+
+        let texture = assets
+            .materials
+            .get(material)
+            .and_then(|material| material.base.base_color_texture.as_ref());
+        if let Some(image) = texture.and_then(|texture| assets.images.get_mut(texture)) {
+            image.data = colorize(&shaped, position, &settings);
+        }
+        heights.0 = shaped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use diamond_square::DiamondSquare;
+
+    use super::*;
+    use crate::{
+        fixed::Arithmetic, generate_heightmap, generate_tile, tests::tile_world, topology::Topology,
+    };
+
+    #[test]
+    fn refining_a_preview_ends_with_the_direct_result() {
+        let (size, seed, roughness, position) = (257, 42, 2.0, Position((1, -2)));
+        let mut generation = DiamondSquare::new(size)
+            .seed(seed)
+            .roughness(roughness)
+            .position(position.0)
+            .start();
+
+        step_to_preview(&mut generation);
+        assert!(!generation.is_done());
+        assert_eq!(preview(&generation).len(), size);
+        while !generation.is_done() {
+            generation.step();
+        }
+
+        assert_eq!(
+            generation.heights(),
+            generate_heightmap(position, roughness, seed, size)
+        );
+    }
+
+    #[test]
+    fn refined_tiles_end_with_the_directly_generated_heights() {
+        let (size, seed, roughness, position) = (257, 42, 2.0, Position((1, -2)));
+        let mut world = tile_world();
+        let mut generation = DiamondSquare::new(size)
+            .seed(seed)
+            .roughness(roughness)
+            .position(position.0)
+            .start();
+        step_to_preview(&mut generation);
+        let heights = TileHeights(preview(&generation));
+        let material = Handle::<TileMaterial>::default();
+        let refining = Refining {
+            generation,
+            rotation: 0,
+        };
+        let tile = world.spawn((refining, heights, position, material)).id();
+
+        while world.get::<Refining>(tile).is_some() {
+            world.run_system_once(refine_tiles);
+        }
+
+        let direct = generate_tile(
+            position,
+            roughness,
+            seed,
+            size,
+            Topology::Planar,
+            Arithmetic::Float,
+        );
+        assert_eq!(world.get::<TileHeights>(tile).unwrap().0, direct);
+        assert_eq!(world.resource::<Events<TileGenerated>>().len(), 1);
+    }
+}
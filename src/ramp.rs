@@ -0,0 +1,85 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::Heightmap;
+
+/// Colors heights between 0 and 1 by blending between the stops around them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorRamp {
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl ColorRamp {
+    /// Stops at heights 0-1, in any order. Two stops at the same height make a hard edge.
+    pub fn new(mut stops: Vec<(f32, [u8; 3])>) -> Self {
+        assert!(!stops.is_empty(), "a color ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Black at the lowest height to white at the highest.
+    pub fn grayscale() -> Self {
+        Self::new(vec![(0.0, [0; 3]), (1.0, [0xFF; 3])])
+    }
+
+    /// Water, lowland, rock and snow, like the demo's default bands.
+    pub fn terrain() -> Self {
+        Self::new(vec![
+            (0.0, [0, 0, 90]),
+            (0.2, [0, 0, 255]),
+            (0.2, [0, 120, 0]),
+            (0.65, [0, 255, 0]),
+            (0.65, [100, 100, 100]),
+            (0.9, [160, 160, 160]),
+            (0.9, [230, 230, 230]),
+            (1.0, [0xFF; 3]),
+        ])
+    }
+
+    pub fn color(&self, height: f32) -> [u8; 3] {
+        let above = self.stops.partition_point(|&(stop, _)| stop <= height);
+        let (Some(&(lo, low)), Some(&(hi, high))) =
+            (self.stops.get(above.wrapping_sub(1)), self.stops.get(above))
+        else {
+            // Outside the stops, hold the nearest one.
+            return self.stops[above.min(self.stops.len() - 1)].1;
+        };
+
+        let t = (height - lo) / (hi - lo);
+        [0, 1, 2].map(|i| (low[i] as f32 + (high[i] as f32 - low[i] as f32) * t) as u8)
+    }
+}
+
+impl Heightmap {
+    /// RGBA bytes, row after row, with the heights normalized to 0-1 before coloring.
+    pub fn to_rgba(&self, ramp: &ColorRamp) -> Vec<u8> {
+        self.normalized()
+            .iter()
+            .flat_map(|height| {
+                let [r, g, b] = ramp.color(height);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+
+    /// A texture of the colored heights, one pixel per cell.
+    pub fn to_image(&self, ramp: &ColorRamp) -> Image {
+        let size = self.size() as u32;
+        Image::new(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.to_rgba(ramp),
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    coloring::{colorize, ColorSettings, Coloring},
+    generate_tile, GenSettings, Position,
+};
+
+/// How a GIF recording runs through the seeds.
+#[derive(Debug, Clone, Copy)]
+pub struct GifRecording {
+    pub frames: u32,
+    /// Time each frame is shown, in milliseconds.
+    pub interval_ms: u32,
+}
+
+impl Default for GifRecording {
+    fn default() -> Self {
+        Self {
+            frames: 30,
+            interval_ms: 500,
+        }
+    }
+}
+
+/// Writes a looping GIF that steps through `recording.frames` consecutive seeds starting at
+/// `settings.seed`. Frames are regenerated at full resolution rather than captured from the
+/// screen, and handed to the encoder one at a time so only a single frame is held in memory.
+pub fn record_gif(
+    path: &Path,
+    settings: &GenSettings,
+    color_settings: &ColorSettings,
+    recording: GifRecording,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let size = settings.image_size();
+    let side = u16::try_from(size).map_err(|_| "tile too large for a GIF")?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), side, side, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    let position = Position((0, 0));
+    for frame in 0..recording.frames {
+        let frame_settings = GenSettings {
+            seed: settings.seed.wrapping_add(frame as isize),
+            ..*settings
+        };
+        let heights = generate_tile(
+            position,
+            settings.roughness,
+            frame_settings.noise_seed(),
+            size,
+            settings.topology,
+            settings.arithmetic,
+        );
+        let mut rgba = colorize(&heights, position, color_settings);
+
+        let mut frame = gif::Frame::from_rgba_speed(side, side, &mut rgba, 10);
+        // GIF delays are in hundredths of a second.
+        frame.delay = (recording.interval_ms / 10).min(u16::MAX as u32) as u16;
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct RecordingState {
+    path: String,
+    recording: GifRecording,
+    /// What the last recording reported, shared with the thread doing the encoding.
+    status: Arc<Mutex<String>>,
+}
+
+pub fn recording_ui(
+    mut contexts: EguiContexts,
+    mut state: Local<RecordingState>,
+    settings: Res<GenSettings>,
+    coloring: Coloring,
+) {
+    const DEFAULT_PATH: &str = "export/terrain.gif";
+
+    if state.path.is_empty() {
+        state.path = DEFAULT_PATH.to_string();
+    }
+
+    egui::Window::new("Record GIF")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut state.path);
+            });
+            ui.add(egui::Slider::new(&mut state.recording.frames, 2..=240).prefix("Frames: "));
+            ui.add(
+                egui::Slider::new(&mut state.recording.interval_ms, 20..=2000)
+                    .prefix("Interval: ")
+                    .suffix(" ms"),
+            );
+
+            let status = state.status.lock().unwrap().clone();
+            let busy = status.starts_with("Recording");
+            if ui
+                .add_enabled(!busy, egui::Button::new("Record GIF"))
+                .clicked()
+            {
+                let path = PathBuf::from(&state.path);
+                let (settings, color_settings) = (*settings, coloring.settings());
+                let (recording, status) = (state.recording, state.status.clone());
+
+                // Encoding many frames takes a while, so it runs off the main thread.
+                *status.lock().unwrap() = format!("Recording {}...", path.display());
+                thread::spawn(move || {
+                    let result = record_gif(&path, &settings, &color_settings, recording);
+                    *status.lock().unwrap() = match result {
+                        Ok(()) => format!("Saved {}", path.display()),
+                        Err(err) => format!("Failed to record {}: {}", path.display(), err),
+                    };
+                });
+            }
+
+            if !status.is_empty() {
+                ui.label(status);
+            }
+        });
+}
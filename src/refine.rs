@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{hash, Cursor, GenSettings, LoadTileEvent, Position, Tile, TileHeights, TileLevel};
+
+/// A square block of cells, `size` cells per side, with `heights[x][y]` as its first corner.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub size: usize,
+}
+
+/// Zooms into `region`, adding `extra_depth` more diamond-square levels between its existing
+/// cells. Every cell of the region is kept as-is at its new location, so the large-scale shape is
+/// preserved while finer detail is added inside. New cells along the border are interpolated
+/// without noise so the refined block still lines up with its surroundings.
+///
+/// `roughness` is the displacement used for the first added level, halving after that just like
+/// in [`generate_heightmap`](crate::generate_heightmap).
+pub fn refine_region(
+    heights: &[Vec<f32>],
+    region: Region,
+    extra_depth: u32,
+    roughness: f32,
+    seed: isize,
+) -> Vec<Vec<f32>> {
+    let stride = 1 << extra_depth;
+    let size = region.size * stride + 1;
+    let mut refined = vec![vec![0.0; size]; size];
+
+    // The existing cells become the fixed seeds.
+    for x in 0..=region.size {
+        for y in 0..=region.size {
+            refined[x * stride][y * stride] = heights[region.x + x][region.y + y];
+        }
+    }
+
+    // Hash in the original tile's cell space so overlapping refinements agree.
+    let hash = |x: usize, y: usize| {
+        let x = (region.x * stride + x) as i32;
+        let y = (region.y * stride + y) as i32;
+        hash(seed, x, y) * 2.0 - 1.0
+    };
+
+    let mut chunk_size = stride;
+    let mut roughness = roughness;
+    while chunk_size > 1 {
+        let half = chunk_size / 2;
+
+        // square step
+        for x in (0..size - 1).step_by(chunk_size) {
+            for y in (0..size - 1).step_by(chunk_size) {
+                let average = (refined[x][y]
+                    + refined[x + chunk_size][y]
+                    + refined[x][y + chunk_size]
+                    + refined[x + chunk_size][y + chunk_size])
+                    / 4.0;
+                refined[x + half][y + half] = average + hash(x + half, y + half) * roughness;
+            }
+        }
+
+        // diamond step
+        for x in (0..size).step_by(half) {
+            let start = if (x / half) % 2 == 0 { half } else { 0 };
+            for y in (start..size).step_by(chunk_size) {
+                let on_border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                if on_border {
+                    // Only the two neighbors along the border, no noise.
+                    refined[x][y] = if x == 0 || x == size - 1 {
+                        (refined[x][y - half] + refined[x][y + half]) / 2.0
+                    } else {
+                        (refined[x - half][y] + refined[x + half][y]) / 2.0
+                    };
+                    continue;
+                }
+
+                let average = (refined[x - half][y]
+                    + refined[x + half][y]
+                    + refined[x][y - half]
+                    + refined[x][y + half])
+                    / 4.0;
+                refined[x][y] = average + hash(x, y) * roughness;
+            }
+        }
+
+        chunk_size = half;
+        roughness /= 2.0;
+    }
+
+    refined
+}
+
+#[derive(Default)]
+pub struct RefineState {
+    region: Option<Region>,
+    extra_depth: u32,
+    drawing: bool,
+    drag_start: Option<(usize, usize)>,
+}
+
+/// Largest refined tile the UI allows, in cells per side.
+const MAX_REFINED_CELLS: usize = 1024;
+
+pub fn refine_ui(
+    mut contexts: EguiContexts,
+    // Grouped to stay within the number of parameters a system should take.
+    (mut commands, mut load_tile): (Commands, EventWriter<LoadTileEvent>),
+    mut gizmos: Gizmos,
+    mut state: Local<RefineState>,
+    cursor: Cursor,
+    tiles: Query<(Entity, &Position, &TileHeights, &TileLevel), With<Tile>>,
+    settings: Res<GenSettings>,
+) {
+    // Refining works on the single tile view, not on streamed tiles.
+    let tile = tiles.iter().find(|(_, _, _, level)| level.0 == 0);
+    let over_ui = contexts.ctx_mut().wants_pointer_input();
+
+    if let Some((_, &position, heights, _)) = tile {
+        let cells = heights.0.len() - 1;
+
+        // Drag out a square region on the tile, snapped to a power of two so it refines evenly.
+        let cell = cursor
+            .world_position()
+            .and_then(|point| position.world_to_cell(heights.0.len(), point));
+        if state.drawing && !over_ui {
+            if let Some(cell) = cell {
+                if cursor.buttons.just_pressed(MouseButton::Left) {
+                    state.drag_start = Some(cell);
+                }
+
+                if let (Some(start), true) =
+                    (state.drag_start, cursor.buttons.pressed(MouseButton::Left))
+                {
+                    let extent = start
+                        .0
+                        .abs_diff(cell.0)
+                        .max(start.1.abs_diff(cell.1))
+                        .max(2);
+                    let size = (1 << extent.ilog2()).min(cells);
+                    let corner = |a: usize, b: usize| a.min(b).min(cells - size);
+                    state.region = Some(Region {
+                        x: corner(start.0, cell.0),
+                        y: corner(start.1, cell.1),
+                        size,
+                    });
+                    state.extra_depth = (cells / size).ilog2().max(1);
+                }
+            }
+        }
+        if cursor.buttons.just_released(MouseButton::Left) {
+            state.drag_start = None;
+        }
+
+        // Preview the region on the tile.
+        if let Some(region) = state.region {
+            let size = heights.0.len();
+            let a = position.cell_to_world(size, (region.x, region.y));
+            let b = position.cell_to_world(size, (region.x + region.size, region.y + region.size));
+            gizmos.rect(
+                ((a + b) / 2.0).extend(0.01),
+                Quat::IDENTITY,
+                (b - a).abs(),
+                Color::YELLOW,
+            );
+        }
+    }
+
+    egui::Window::new("Refine Region")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let Some((entity, &position, heights, _)) = tile else {
+                ui.label("No tile to refine.");
+                return;
+            };
+
+            ui.checkbox(&mut state.drawing, "Draw Region (drag on the tile)");
+
+            let Some(region) = state.region else {
+                ui.label("No region drawn yet.");
+                return;
+            };
+
+            ui.label(format!(
+                "Region: {} cells at ({}, {})",
+                region.size, region.x, region.y
+            ));
+            let max_depth = (MAX_REFINED_CELLS / region.size).ilog2().max(1);
+            ui.add(
+                egui::Slider::new(&mut state.extra_depth, 1..=max_depth).prefix("Extra Detail: "),
+            );
+            ui.label(format!(
+                "Result: {0}x{0} cells",
+                (region.size << state.extra_depth) + 1
+            ));
+
+            if ui.button("Refine").clicked() {
+                // Continue the roughness where the tile's own finest level left off.
+                let cells = (heights.0.len() - 1) as f32;
+                let refined = refine_region(
+                    &heights.0,
+                    region,
+                    state.extra_depth,
+                    settings.roughness / cells,
+                    settings.noise_seed(),
+                );
+
+                commands.entity(entity).despawn();
+                load_tile.send(LoadTileEvent {
+                    position,
+                    heights: refined,
+                });
+                state.region = None;
+                state.drawing = false;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn existing_cells_are_kept_and_detail_is_added() {
+        let heights: Vec<Vec<f32>> = (0..9)
+            .map(|x| (0..9).map(|y| hash(3, x, y)).collect())
+            .collect();
+        let region = Region {
+            x: 2,
+            y: 4,
+            size: 4,
+        };
+
+        let refined = refine_region(&heights, region, 2, 1.0, 5);
+
+        let stride = 4;
+        assert_eq!(refined.len(), region.size * stride + 1);
+        assert!(refined.len().pow(2) > (region.size + 1).pow(2));
+        for x in 0..=region.size {
+            for y in 0..=region.size {
+                let original = heights[region.x + x][region.y + y];
+                assert_eq!(refined[x * stride][y * stride], original);
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::{error::Error, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::{
+    coloring::{colorize, ColorSettings},
+    lighting, Position,
+};
+
+/// The classic shaded relief map: the hypsometric colors and a grey hillshade composited into
+/// one image, ready to drop into a GIS.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct ShadedReliefExport {
+    /// Share of the color in the result, 1 for only color and 0 for only hillshade.
+    pub color_weight: f32,
+}
+
+impl Default for ShadedReliefExport {
+    fn default() -> Self {
+        Self { color_weight: 0.6 }
+    }
+}
+
+/// The grey hillshade of the tile under the configured lights, 0-255 per cell.
+pub fn hillshade(heights: &[Vec<f32>], settings: &ColorSettings) -> Vec<u8> {
+    lighting::shade(heights, &settings.lighting)
+        .into_iter()
+        .flatten()
+        .map(|light| (light.element_sum() / 3.0 * 255.0) as u8)
+        .collect()
+}
+
+/// Blends every pixel of `rgba` with the grey `shade` under it.
+pub fn composite(rgba: &[u8], shade: &[u8], color_weight: f32) -> Vec<u8> {
+    let color_weight = color_weight.clamp(0.0, 1.0);
+    rgba.chunks_exact(4)
+        .zip(shade)
+        .flat_map(|(pixel, &shade)| {
+            let blend =
+                |c: u8| (c as f32 * color_weight + shade as f32 * (1.0 - color_weight)) as u8;
+            [blend(pixel[0]), blend(pixel[1]), blend(pixel[2]), pixel[3]]
+        })
+        .collect()
+}
+
+/// Colors the tile without baked lighting, so the shading only comes in once through the
+/// hillshade, and writes the composite as a PNG.
+pub fn write_png(
+    path: &Path,
+    heights: &[Vec<f32>],
+    position: Position,
+    settings: &ColorSettings,
+    relief: &ShadedReliefExport,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut unlit = settings.clone();
+    unlit.lighting.enabled = false;
+    let rgba = colorize(heights, position, &unlit);
+    let shade = hillshade(heights, settings);
+
+    let size = heights.len() as u32;
+    let pixels = composite(&rgba, &shade, relief.color_weight);
+    let image =
+        image::RgbaImage::from_raw(size, size, pixels).ok_or("image buffer size mismatch")?;
+    image.save(path)?;
+    Ok(())
+}
+
+/// The shaded relief section of the export window.
+pub fn shaded_relief_ui(ui: &mut egui::Ui, relief: &mut ShadedReliefExport) {
+    ui.add(egui::Slider::new(&mut relief.color_weight, 0.0..=1.0).prefix("Color Weight: "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RGBA: [u8; 8] = [200, 100, 0, 0xFF, 10, 250, 90, 0x80];
+    const SHADE: [u8; 2] = [60, 180];
+
+    #[test]
+    fn full_color_weight_is_only_color() {
+        assert_eq!(composite(&RGBA, &SHADE, 1.0), RGBA);
+    }
+
+    #[test]
+    fn zero_color_weight_is_only_hillshade() {
+        assert_eq!(
+            composite(&RGBA, &SHADE, 0.0),
+            [60, 60, 60, 0xFF, 180, 180, 180, 0x80]
+        );
+    }
+
+    #[test]
+    fn other_color_weights_blend_color_and_hillshade() {
+        let blended = composite(&RGBA, &SHADE, 0.25);
+
+        assert_eq!(blended, [95, 70, 45, 0xFF, 137, 197, 157, 0x80]);
+        // Alpha is kept as it is.
+        for (i, (&c, &b)) in RGBA
+            .iter()
+            .zip(&blended)
+            .enumerate()
+            .filter(|(i, _)| i % 4 != 3)
+        {
+            let s = SHADE[i / 4];
+            assert!(c.min(s) <= b && b <= c.max(s));
+        }
+    }
+}
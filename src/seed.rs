@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+/// Where "Generate Terrain" gets its new seeds from.
+#[derive(Resource, Debug)]
+pub enum SeedSource {
+    /// System entropy, so every run explores different terrain.
+    Entropy,
+    /// The same pseudo-random sequence on every run, for reproducible automated tests. Holds the
+    /// generator state.
+    Fixed(u64),
+}
+
+impl Default for SeedSource {
+    /// Decided by the environment of this process, see [`SeedSource::from_env`].
+    fn default() -> Self {
+        let var = std::env::var("DIAMOND_SQUARE_FIXED_SEED").ok();
+        SeedSource::from_env(var.as_deref(), std::env::args())
+    }
+}
+
+impl SeedSource {
+    /// [`SeedSource::Fixed`] if `var`, the value of `DIAMOND_SQUARE_FIXED_SEED`, is `1` or
+    /// `args` contain `--fixed-seed`, [`SeedSource::Entropy`] otherwise.
+    pub fn from_env(var: Option<&str>, mut args: impl Iterator<Item = String>) -> Self {
+        if var == Some("1") || args.any(|arg| arg == "--fixed-seed") {
+            SeedSource::Fixed(0)
+        } else {
+            SeedSource::Entropy
+        }
+    }
+
+    pub fn next_seed(&mut self) -> isize {
+        match self {
+            SeedSource::Entropy => rand::random(),
+            SeedSource::Fixed(state) => {
+                // Knuth's MMIX linear congruential generator.
+                *state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (*state >> 1) as isize
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_heightmap, Position};
+
+    #[test]
+    fn the_variable_or_the_flag_fix_the_seeds() {
+        let source =
+            |var, args: &[&str]| SeedSource::from_env(var, args.iter().map(|arg| arg.to_string()));
+
+        assert!(matches!(source(Some("1"), &[]), SeedSource::Fixed(0)));
+        assert!(matches!(
+            source(None, &["diamond-square", "--fixed-seed"]),
+            SeedSource::Fixed(0)
+        ));
+        assert!(matches!(
+            source(Some("0"), &["diamond-square"]),
+            SeedSource::Entropy
+        ));
+        assert!(matches!(source(None, &[]), SeedSource::Entropy));
+    }
+
+    #[test]
+    fn fixed_runs_generate_the_same_tiles() {
+        // Two fresh runs, each clicking "Generate Terrain" a few times.
+        let run = || {
+            let mut seeds = SeedSource::Fixed(0);
+            (0..3)
+                .map(|_| generate_heightmap(Position((0, 0)), 2.0, seeds.next_seed(), 33))
+                .collect::<Vec<_>>()
+        };
+        let (first, second) = (run(), run());
+        assert_eq!(first, second);
+        assert_ne!(first[0], first[1]);
+    }
+}
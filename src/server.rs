@@ -0,0 +1,142 @@
+use std::{
+    error::Error,
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use tungstenite::Message;
+
+use crate::{batch, GenSettings, Position};
+
+/// Largest tile the server generates, in cells per side, so one request can't take it down.
+const MAX_SIZE: usize = 2049;
+
+/// `--serve <address>`: serves tiles over WebSocket at e.g. `127.0.0.1:9000` instead of opening
+/// a window.
+pub struct ServerArgs {
+    pub address: String,
+}
+
+impl ServerArgs {
+    /// `None` unless `--serve` was given, in which case the window isn't opened at all.
+    pub fn parse() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let index = args.iter().position(|arg| arg == "--serve")?;
+        Some(Self {
+            address: args
+                .get(index + 1)
+                .cloned()
+                .unwrap_or_else(|| "127.0.0.1:9000".to_string()),
+        })
+    }
+}
+
+/// A tile asked for by a client, sent as a JSON text message, e.g.
+/// `{"position": [1, 0], "seed": 42, "roughness": 2.0, "size": 257}`. The roughness and size
+/// default to those of the window.
+///
+/// The server answers every request with one message: a binary message holding the tile as PNG,
+/// exactly as a batch run would write it, or a text message `{"error": "..."}`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TileRequest {
+    pub position: Position,
+    pub seed: isize,
+    pub roughness: Option<f32>,
+    pub size: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ErrorReply {
+    error: String,
+}
+
+/// Accepts connections until the process is killed, each on its own thread.
+pub fn run(args: &ServerArgs) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(&args.address)?;
+    println!("Serving tiles on ws://{}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            let peer = stream.peer_addr();
+            if let Err(err) = serve(stream) {
+                eprintln!("Connection {:?} failed: {}", peer, err);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Answers the requests of one client until it disconnects.
+fn serve(stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    let mut socket = tungstenite::accept(stream)?;
+    loop {
+        let request = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Ok(_) => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        let reply = match respond(&request) {
+            Ok(png) => Message::Binary(png),
+            Err(err) => Message::Text(serde_json::to_string(&ErrorReply {
+                error: err.to_string(),
+            })?),
+        };
+        socket.send(reply)?;
+    }
+}
+
+/// The PNG for one request.
+pub fn respond(request: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let request: TileRequest = serde_json::from_str(request)?;
+    let defaults = GenSettings::default();
+
+    let size = request.size.unwrap_or_else(|| defaults.image_size());
+    if size < 3 || !(size - 1).is_power_of_two() || size > MAX_SIZE {
+        return Err(format!("size must be 2^n + 1, at most {}", MAX_SIZE).into());
+    }
+    let roughness = request.roughness.unwrap_or(defaults.roughness);
+
+    batch::render_png(request.position, roughness, request.seed, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str = r#"{"position": [1, -2], "seed": 42, "roughness": 2.0, "size": 65}"#;
+
+    #[test]
+    fn a_request_is_answered_with_the_directly_generated_png() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream).unwrap();
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        let Ok((mut socket, _)) = tungstenite::client(format!("ws://{}", address), stream) else {
+            panic!("WebSocket handshake failed");
+        };
+        socket.send(Message::Text(REQUEST.to_string())).unwrap();
+        let reply = socket.read().unwrap();
+        socket.close(None).unwrap();
+        // Read until the server has acknowledged the close.
+        while socket.read().is_ok() {}
+        server.join().unwrap();
+
+        let direct = batch::render_png(Position((1, -2)), 2.0, 42, 65).unwrap();
+        assert_eq!(reply, Message::Binary(direct));
+    }
+
+    #[test]
+    fn sizes_that_are_not_a_power_of_two_plus_one_are_refused() {
+        assert!(respond(r#"{"position": [0, 0], "seed": 1, "size": 64}"#).is_err());
+        assert!(respond(r#"{"position": [0, 0], "seed": 1, "size": 4097}"#).is_err());
+        assert!(respond(r#"{"position": [0, 0], "seed": 1, "size": 33}"#).is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+use std::{error::Error, fmt::Write as _, fs, path::Path};
+
+use crate::{coastline, coloring::logistic};
+
+/// Euclidean distance, in cells, from every cell to the nearest water cell. Water cells are 0.
+/// `heights` are already through the logistic curve (0-1) and classified with the same water
+/// level as the coloring. Without any water on the tile every distance is infinite.
+pub fn distance_to_water(heights: &[Vec<f32>], water_level: f32) -> Vec<Vec<f32>> {
+    let land = coastline::land_mask(heights, water_level);
+
+    // Exact distance transform, squared: one pass down the columns, then one along the rows.
+    let columns: Vec<Vec<f32>> = transpose(&land)
+        .iter()
+        .map(|column| {
+            let water = column
+                .iter()
+                .map(|&land| if land { f32::INFINITY } else { 0.0 })
+                .collect::<Vec<_>>();
+            squared_distance_1d(&water)
+        })
+        .collect();
+
+    transpose(&columns)
+        .iter()
+        .map(|row| {
+            squared_distance_1d(row)
+                .into_iter()
+                .map(f32::sqrt)
+                .collect()
+        })
+        .collect()
+}
+
+/// [`distance_to_water`] for a tile's raw heights, put through the logistic curve first.
+pub fn tile_distance_to_water(heights: &[Vec<f32>], water_level: f32) -> Vec<Vec<f32>> {
+    let heights: Vec<Vec<f32>> = heights
+        .iter()
+        .map(|row| row.iter().map(|&f| logistic(f)).collect())
+        .collect();
+    distance_to_water(&heights, water_level)
+}
+
+/// The 1D squared distance transform of Felzenszwalb and Huttenlocher: the lower envelope of the
+/// parabolas rooted at every sample.
+fn squared_distance_1d(f: &[f32]) -> Vec<f32> {
+    let n = f.len();
+    let mut distances = vec![f32::INFINITY; n];
+    // Roots of the parabolas in the envelope, and where each one takes over from the last.
+    let mut roots = Vec::with_capacity(n);
+    let mut starts: Vec<f32> = Vec::with_capacity(n);
+
+    for q in (0..n).filter(|&q| f[q].is_finite()) {
+        // Where the parabola rooted at `q` drops below the one rooted at `p`.
+        let intersection = |p: usize| {
+            let (qf, pf) = (q as f32, p as f32);
+            ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf))
+        };
+
+        // Drop the parabolas that `q` hides completely.
+        while let (Some(&p), Some(&start)) = (roots.last(), starts.last()) {
+            if intersection(p) > start {
+                break;
+            }
+            roots.pop();
+            starts.pop();
+        }
+
+        starts.push(roots.last().map_or(f32::NEG_INFINITY, |&p| intersection(p)));
+        roots.push(q);
+    }
+
+    let mut k = 0;
+    for (q, distance) in distances.iter_mut().enumerate() {
+        if roots.is_empty() {
+            break;
+        }
+        while k + 1 < roots.len() && starts[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - roots[k] as f32;
+        *distance = offset * offset + f[roots[k]];
+    }
+
+    distances
+}
+
+fn transpose<T: Copy>(field: &[Vec<T>]) -> Vec<Vec<T>> {
+    let columns = field.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|y| field.iter().map(|row| row[y]).collect())
+        .collect()
+}
+
+/// Blends from the shore's blue at the coastline to dry ground at the farthest cell from water on
+/// the tile. Water itself is a darker blue.
+pub fn distance_colors(distances: &[Vec<f32>]) -> Vec<u8> {
+    const WATER: [f32; 3] = [20.0, 50.0, 120.0];
+    const COAST: [f32; 3] = [70.0, 150.0, 230.0];
+    const INLAND: [f32; 3] = [210.0, 180.0, 120.0];
+
+    let farthest = distances
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|d| d.is_finite())
+        .fold(1.0, f32::max);
+
+    distances
+        .iter()
+        .flatten()
+        .flat_map(|&distance| {
+            let color = if distance == 0.0 {
+                WATER
+            } else {
+                let t = (distance / farthest).min(1.0);
+                [0, 1, 2].map(|i| COAST[i] + (INLAND[i] - COAST[i]) * t)
+            };
+            let [r, g, b] = color.map(|c| c as u8);
+            [r, g, b, 0xFF]
+        })
+        .collect()
+}
+
+/// Writes the distances as CSV, one line per row laid out like the tile texture. Tiles without
+/// water are written as `inf`.
+pub fn write_csv(path: &Path, distances: &[Vec<f32>]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut csv = String::new();
+    for row in distances {
+        let values: Vec<String> = row.iter().map(|d| format!("{:.3}", d)).collect();
+        writeln!(csv, "{}", values.join(","))?;
+    }
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_grow_linearly_away_from_the_coast() {
+        // The first half of the rows is water, the second half land.
+        let size = 16;
+        let heights: Vec<Vec<f32>> = (0..size)
+            .map(|x| vec![if x < size / 2 { 0.0 } else { 1.0 }; size])
+            .collect();
+
+        let distances = distance_to_water(&heights, 0.5);
+
+        for (x, row) in distances.iter().enumerate() {
+            let expected = (x + 1).saturating_sub(size / 2) as f32;
+            for &distance in row {
+                assert!(
+                    (distance - expected).abs() < 1e-5,
+                    "row {}: {}",
+                    x,
+                    distance
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::math::{Vec2, Vec3};
+
+/// Height gradient of every cell using central differences, falling back to one-sided
+/// differences along the edges. Distances are measured in tile widths rather than cells so the
+/// result doesn't depend on the tile's resolution.
+pub fn gradient(heights: &[Vec<f32>]) -> Vec<Vec<Vec2>> {
+    let size = heights.len();
+    if size < 2 {
+        return vec![vec![Vec2::ZERO; size]; size];
+    }
+
+    let cells_per_tile = (size - 1) as f32;
+    let derivative = |i: usize, sample: &dyn Fn(usize) -> f32| {
+        let (lo, hi) = (i.saturating_sub(1), (i + 1).min(size - 1));
+        (sample(hi) - sample(lo)) / (hi - lo) as f32 * cells_per_tile
+    };
+
+    (0..size)
+        .map(|x| {
+            (0..size)
+                .map(|y| {
+                    Vec2::new(
+                        derivative(x, &|x| heights[x][y]),
+                        derivative(y, &|y| heights[x][y]),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Steepness of every cell, i.e. the length of its gradient.
+pub fn slope(heights: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    gradient(heights)
+        .into_iter()
+        .map(|row| row.into_iter().map(Vec2::length).collect())
+        .collect()
+}
+
+/// Surface normal of every cell in world space (+Z up), with the heights scaled by `relief`
+/// first. Heightmap rows run along -Y and columns along +X, see
+/// [`Position::cell_to_world`](crate::Position::cell_to_world).
+pub fn normals(heights: &[Vec<f32>], relief: f32) -> Vec<Vec<Vec3>> {
+    gradient(heights)
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|g| Vec3::new(-g.y * relief, g.x * relief, 1.0).normalize())
+                .collect()
+        })
+        .collect()
+}
+
+/// Slopes gentler than this, in height per tile width, face no particular direction.
+const FLAT_SLOPE: f32 = 0.01;
+
+/// The compass direction each cell faces, i.e. its downslope azimuth in degrees clockwise from
+/// north (+Y), 0-360. Flat cells have no aspect and are NaN.
+pub fn compute_aspect(heights: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    gradient(heights)
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|g| {
+                    if g.length() < FLAT_SLOPE {
+                        return f32::NAN;
+                    }
+                    // Rows run along -Y and columns along +X, so downhill in the world is
+                    // (-g.y, g.x) as (east, north).
+                    (-g.y).atan2(g.x).to_degrees().rem_euclid(360.0)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_slope_going_down_to_the_east_faces_east() {
+        // Columns run along +X, so the heights fall towards the last column.
+        let heights: Vec<Vec<f32>> = (0..5)
+            .map(|_| (0..5).map(|y| -(y as f32)).collect())
+            .collect();
+        let aspect = compute_aspect(&heights);
+        assert!((aspect[2][2] - 90.0).abs() < 1e-3, "{}", aspect[2][2]);
+    }
+
+    #[test]
+    fn flat_cells_face_nowhere() {
+        let aspect = compute_aspect(&vec![vec![1.0; 5]; 5]);
+        assert!(aspect[2][2].is_nan());
+    }
+}
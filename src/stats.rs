@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use serde::Serialize;
+
+use crate::{
+    cache::{self, ColorCache},
+    climate,
+    coloring::{logistic, ColorSettings, Coloring},
+    geologic::GeologicTime,
+    progressive::Refining,
+    Position, Tile, TileHeights, TileLevel,
+};
+
+/// Summary of a generated tile, shown in the statistics window and written next to exports.
+#[derive(Debug, Clone, Serialize)]
+pub struct TileStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Percentage of cells per biome, including water.
+    pub biome_coverage: BTreeMap<String, f32>,
+    /// Hash of the exact height values, for telling apart tiles that look alike.
+    pub fingerprint: String,
+    /// See [`fractal_dimension`].
+    pub fractal_dimension: f32,
+    /// See [`detect_periodicity`].
+    pub period: Option<usize>,
+}
+
+impl TileStats {
+    pub fn compute(heights: &[Vec<f32>], position: Position, settings: &ColorSettings) -> Self {
+        let cells = heights.iter().flatten();
+        let count = heights.iter().map(Vec::len).sum::<usize>().max(1);
+        let min = cells.clone().copied().fold(f32::INFINITY, f32::min);
+        let max = cells.clone().copied().fold(f32::NEG_INFINITY, f32::max);
+        let mean = cells.sum::<f32>() / count as f32;
+
+        let normalized: Vec<Vec<f32>> = heights
+            .iter()
+            .map(|row| row.iter().map(|&f| logistic(f)).collect())
+            .collect();
+        let water_level = settings.water_level.0;
+        let biomes = climate::biome_field(&normalized, position, water_level, &settings.climate);
+
+        let mut biome_coverage = BTreeMap::new();
+        for (f, biome) in normalized.iter().flatten().zip(biomes.iter().flatten()) {
+            let name = if *f < water_level {
+                "Water".to_string()
+            } else {
+                format!("{:?}", biome)
+            };
+            *biome_coverage.entry(name).or_insert(0.0) += 100.0 / count as f32;
+        }
+
+        Self {
+            min,
+            max,
+            mean,
+            biome_coverage,
+            fingerprint: format!("{:016x}", fingerprint(heights)),
+            fractal_dimension: fractal_dimension(heights),
+            period: detect_periodicity(heights),
+        }
+    }
+}
+
+/// FNV-1a over the bits of every height. Unlike `DefaultHasher` this is stable across Rust
+/// versions and platforms, so fingerprints can be compared between machines.
+pub fn fingerprint(heights: &[Vec<f32>]) -> u64 {
+    heights
+        .iter()
+        .flatten()
+        .flat_map(|f| f.to_bits().to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Estimates the fractal dimension of the surface with the variogram method. The mean squared
+/// height difference grows with the lag `h` as `h^(2H)`, where `H` is the Hurst exponent, and
+/// the surface dimension is `3 - H`. Smooth terrain is close to 2, very rough terrain close
+/// to 3.
+///
+/// The exponent is fitted over power-of-two lags up to a quarter of the tile, along both axes.
+/// Returns NaN for tiles too small or too flat to measure.
+pub fn fractal_dimension(heights: &[Vec<f32>]) -> f32 {
+    let size = heights.len();
+
+    let points: Vec<(f32, f32)> = (0..)
+        .map(|power| 1 << power)
+        .take_while(|&lag| lag <= size / 4)
+        .filter_map(|lag| {
+            let (mut sum, mut count) = (0.0, 0);
+            for x in 0..size - lag {
+                for y in 0..size - lag {
+                    let h = heights[x][y];
+                    sum += (heights[x + lag][y] - h).powi(2) + (heights[x][y + lag] - h).powi(2);
+                    count += 2;
+                }
+            }
+            let variogram = sum / count as f32;
+            (variogram > 0.0).then(|| ((lag as f32).ln(), variogram.ln()))
+        })
+        .collect();
+
+    if points.len() < 2 {
+        return f32::NAN;
+    }
+
+    // Least squares slope of ln(variogram) against ln(lag).
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let covariance: f32 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f32 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let hurst = covariance / variance / 2.0;
+
+    3.0 - hurst.clamp(0.0, 1.0)
+}
+
+/// Lowest autocorrelation at which a lag counts as a repeat.
+const PERIODICITY_THRESHOLD: f32 = 0.6;
+/// Longest period looked for, in cells.
+const MAX_PERIOD: usize = 64;
+/// Only every this many rows and columns are correlated, which is plenty to find a repeat.
+const PERIODICITY_STRIDE: usize = 4;
+
+/// The period, in cells, at which the noise in `field` repeats, if it does.
+///
+/// Terrain is strongly correlated at short lags anyway, so this looks at the displacement
+/// instead: every cell minus the mean of its four neighbors, which is what the hash added. A
+/// good hash leaves that uncorrelated at every lag, while a poor one repeats it. The first lag
+/// from 2 up to [`MAX_PERIOD`] where the autocorrelation along both axes reaches
+/// [`PERIODICITY_THRESHOLD`] is reported.
+pub fn detect_periodicity(field: &[Vec<f32>]) -> Option<usize> {
+    let size = field.len();
+    if size < 8 {
+        return None;
+    }
+
+    let mut displacement: Vec<Vec<f32>> = (1..size - 1)
+        .map(|x| {
+            (1..size - 1)
+                .map(|y| {
+                    let neighbors =
+                        field[x - 1][y] + field[x + 1][y] + field[x][y - 1] + field[x][y + 1];
+                    field[x][y] - neighbors / 4.0
+                })
+                .collect()
+        })
+        .collect();
+
+    let inner = size - 2;
+    let count = (inner * inner) as f32;
+    let mean = displacement.iter().flatten().sum::<f32>() / count;
+    displacement
+        .iter_mut()
+        .flatten()
+        .for_each(|value| *value -= mean);
+    let variance = displacement.iter().flatten().map(|d| d * d).sum::<f32>() / count;
+    if variance <= f32::EPSILON {
+        return None;
+    }
+
+    (2..=MAX_PERIOD.min(inner / 2)).find(|&lag| {
+        let (mut sum, mut pairs) = (0.0, 0);
+        for line in (0..inner).step_by(PERIODICITY_STRIDE) {
+            for i in 0..inner - lag {
+                sum += displacement[line][i] * displacement[line][i + lag]
+                    + displacement[i][line] * displacement[i + lag][line];
+                pairs += 2;
+            }
+        }
+        sum / pairs as f32 / variance >= PERIODICITY_THRESHOLD
+    })
+}
+
+/// Statistics of the displayed tile, recomputed whenever a new tile appears.
+#[derive(Resource, Default)]
+pub struct CurrentStats(pub Option<TileStats>);
+
+/// Recomputes the statistics once the displayed tile's heights have settled. Progressive
+/// refinement and geologic time change them every frame, so the tile is only measured once they
+/// are done.
+pub fn update_stats(
+    mut stats: ResMut<CurrentStats>,
+    mut pending: Local<bool>,
+    tiles: Query<(&Position, Ref<TileHeights>, &TileLevel, Has<Refining>), With<Tile>>,
+    geologic: Res<GeologicTime>,
+    coloring: Coloring,
+) {
+    let Some((&position, heights, _, refining)) =
+        tiles.iter().find(|(_, _, level, _)| level.0 == 0)
+    else {
+        return;
+    };
+
+    *pending |= heights.is_changed();
+    let aging = geologic.enabled && geologic.step < geologic.total_steps;
+    if !*pending || refining || aging {
+        return;
+    }
+
+    stats.0 = Some(TileStats::compute(
+        &heights.0,
+        position,
+        &coloring.settings(),
+    ));
+    *pending = false;
+}
+
+pub fn stats_ui(
+    mut contexts: EguiContexts,
+    stats: Res<CurrentStats>,
+    mut cache: ResMut<ColorCache>,
+) {
+    egui::Window::new("Statistics")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            cache::cache_ui(ui, &mut cache);
+            ui.separator();
+
+            let Some(stats) = &stats.0 else {
+                ui.label("No tile generated yet.");
+                return;
+            };
+
+            ui.label(format!("Min: {:.3}", stats.min));
+            ui.label(format!("Max: {:.3}", stats.max));
+            ui.label(format!("Mean: {:.3}", stats.mean));
+            ui.label(format!("Fingerprint: {}", stats.fingerprint));
+            ui.label(format!("Fractal Dimension: {:.3}", stats.fractal_dimension));
+            match stats.period {
+                Some(period) => ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!("Noise repeats every {} cells", period),
+                ),
+                None => ui.label("No repeating pattern"),
+            };
+
+            ui.separator();
+            for (biome, coverage) in &stats.biome_coverage {
+                ui.label(format!("{}: {:.1}%", biome, coverage));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use diamond_square::{hash, DiamondSquare};
+
+    use super::*;
+
+    const SIZE: usize = 129;
+
+    #[test]
+    fn a_repeating_field_is_detected() {
+        let periodic: Vec<Vec<f32>> = (0..SIZE)
+            .map(|x| {
+                (0..SIZE)
+                    .map(|y| hash(7, (x % 16) as i32, (y % 16) as i32))
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(detect_periodicity(&periodic), Some(16));
+    }
+
+    #[test]
+    fn random_fields_are_not_periodic() {
+        let noise: Vec<Vec<f32>> = (0..SIZE)
+            .map(|x| (0..SIZE).map(|y| hash(7, x as i32, y as i32)).collect())
+            .collect();
+        let terrain = DiamondSquare::new(SIZE)
+            .seed(7)
+            .roughness(2.0)
+            .generate()
+            .to_rows();
+
+        assert_eq!(detect_periodicity(&noise), None);
+        assert_eq!(detect_periodicity(&terrain), None);
+    }
+
+    #[test]
+    fn smoother_terrain_has_a_lower_fractal_dimension() {
+        let dimension = |persistence| {
+            let heights = DiamondSquare::new(SIZE * 2 - 1)
+                .seed(7)
+                .roughness(2.0)
+                .persistence(persistence)
+                .generate()
+                .to_rows();
+            fractal_dimension(&heights)
+        };
+
+        let (smooth, rough) = (dimension(0.4), dimension(0.7));
+        assert!(smooth + 0.2 < rough, "{} is not below {}", smooth, rough);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{hash, Position, RegenerateEvent};
+
+/// A shared world where every tile may have its own seed, e.g. one per creator. Each tile's
+/// interior comes from its own seed, while its corners and edges come from the world seed and
+/// the tiles meeting there, so neighbors stitch whoever generated them and in whatever order.
+/// Only level 0 tiles are stitched.
+#[derive(Resource, Debug, Default, PartialEq, Clone)]
+pub struct MultiSeed {
+    pub enabled: bool,
+    /// Seeds of the tiles that have their own. Every other tile uses the world seed.
+    pub tile_seeds: HashMap<(i32, i32), isize>,
+}
+
+impl MultiSeed {
+    pub fn tile_seed(&self, position: Position, world_seed: isize) -> isize {
+        self.tile_seeds
+            .get(&position.0)
+            .copied()
+            .unwrap_or(world_seed)
+    }
+}
+
+/// The seed of the edge between two neighboring tiles. Symmetric, so both tiles derive the
+/// same edge. FNV-1a of the two positions, so shared worlds stitch the same on every build,
+/// unlike with [`DefaultHasher`](std::hash::DefaultHasher).
+pub fn edge_seed(a: Position, b: Position) -> isize {
+    let (low, high) = (a.0.min(b.0), a.0.max(b.0));
+    [low.0, low.1, high.0, high.1]
+        .into_iter()
+        .flat_map(i32::to_le_bytes)
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        }) as isize
+}
+
+/// Midpoint displacement between two corner heights, `size` samples long. Used for the edges,
+/// with the same roughness falloff as the diamond-square steps.
+fn edge_profile(start: f32, end: f32, size: usize, roughness: f32, seed: isize) -> Vec<f32> {
+    let mut profile = vec![0.0; size];
+    profile[0] = start;
+    profile[size - 1] = end;
+
+    let mut chunk_size = size - 1;
+    let mut roughness = roughness;
+    while chunk_size > 1 {
+        let half = chunk_size / 2;
+        for i in (half..size - 1).step_by(chunk_size) {
+            let random = hash(seed, i as i32, chunk_size as i32) * 2.0 - 1.0;
+            profile[i] = (profile[i - half] + profile[i + half]) / 2.0 + random * roughness;
+        }
+        chunk_size /= 2;
+        roughness /= 2.0;
+    }
+    profile
+}
+
+/// Diamond-square for one tile of a [`MultiSeed`] world. Corners are hashed from the world seed
+/// at their world lattice point, edges from the world seed and [`edge_seed`], and only the
+/// interior from `tile_seed`.
+pub fn generate_stitched(
+    position: Position,
+    roughness: f32,
+    world_seed: isize,
+    tile_seed: isize,
+    image_size: usize,
+) -> Vec<Vec<f32>> {
+    let last = image_size - 1;
+    let (px, py) = position.0;
+    let mut heightmap = vec![vec![0.0; image_size]; image_size];
+
+    // Rows run along -Y and columns along +X, so row 0 is the top edge and column 0 the left.
+    let corner = |x: usize, y: usize| {
+        let cx = px + (y == last) as i32;
+        let cy = py + (x == 0) as i32;
+        hash(world_seed, cx, cy)
+    };
+    for (x, y) in [(0, 0), (0, last), (last, 0), (last, last)] {
+        heightmap[x][y] = corner(x, y);
+    }
+
+    // Both tiles sharing an edge walk it in the same direction, top to bottom or left to right.
+    let edge = |neighbor: (i32, i32), start: f32, end: f32| {
+        let seed = world_seed ^ edge_seed(position, Position(neighbor));
+        edge_profile(start, end, image_size, roughness, seed)
+    };
+    let top = edge((px, py + 1), heightmap[0][0], heightmap[0][last]);
+    let bottom = edge((px, py - 1), heightmap[last][0], heightmap[last][last]);
+    let left = edge((px - 1, py), heightmap[0][0], heightmap[last][0]);
+    let right = edge((px + 1, py), heightmap[0][last], heightmap[last][last]);
+    for i in 0..image_size {
+        heightmap[0][i] = top[i];
+        heightmap[last][i] = bottom[i];
+        heightmap[i][0] = left[i];
+        heightmap[i][last] = right[i];
+    }
+
+    let is_edge = |x: usize, y: usize| x == 0 || y == 0 || x == last || y == last;
+    let noise = |x: usize, y: usize| hash(tile_seed, x as i32, y as i32) * 2.0 - 1.0;
+
+    let mut chunk_size = last;
+    let mut roughness = roughness;
+    while chunk_size > 1 {
+        let half = chunk_size / 2;
+
+        // Square step: the centers of every chunk, never on an edge.
+        for x in (half..last).step_by(chunk_size) {
+            for y in (half..last).step_by(chunk_size) {
+                let average = (heightmap[x - half][y - half]
+                    + heightmap[x + half][y - half]
+                    + heightmap[x - half][y + half]
+                    + heightmap[x + half][y + half])
+                    / 4.0;
+                heightmap[x][y] = average + noise(x, y) * roughness;
+            }
+        }
+
+        // Diamond step: the edge midpoints of every chunk, leaving the tile's own edges alone.
+        for x in (0..image_size).step_by(half) {
+            for y in ((x + half) % chunk_size..image_size).step_by(chunk_size) {
+                if is_edge(x, y) {
+                    continue;
+                }
+                let average = (heightmap[x - half][y]
+                    + heightmap[x + half][y]
+                    + heightmap[x][y - half]
+                    + heightmap[x][y + half])
+                    / 4.0;
+                heightmap[x][y] = average + noise(x, y) * roughness;
+            }
+        }
+
+        chunk_size /= 2;
+        roughness /= 2.0;
+    }
+
+    heightmap
+}
+
+pub fn multi_seed_ui(
+    mut contexts: EguiContexts,
+    mut multi_seed: ResMut<MultiSeed>,
+    mut regenerate: EventWriter<RegenerateEvent>,
+    mut new_tile: Local<(i32, i32, isize)>,
+) {
+    let mut changed = false;
+
+    egui::Window::new("Collaborative World")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            changed |= ui
+                .checkbox(&mut multi_seed.enabled, "Per-Tile Seeds")
+                .on_hover_text("Stream the world with the clipmap to see neighboring tiles.")
+                .changed();
+
+            let mut removed = None;
+            let mut tiles: Vec<_> = multi_seed.tile_seeds.iter().collect();
+            tiles.sort();
+            for (&(x, y), seed) in tiles {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Tile ({}, {}): seed {}", x, y, seed));
+                    if ui.button("Remove").clicked() {
+                        removed = Some((x, y));
+                    }
+                });
+            }
+            if let Some(tile) = removed {
+                multi_seed.tile_seeds.remove(&tile);
+                changed = true;
+            }
+
+            ui.horizontal(|ui| {
+                let (x, y, seed) = &mut *new_tile;
+                ui.add(egui::DragValue::new(x).prefix("x: "));
+                ui.add(egui::DragValue::new(y).prefix("y: "));
+                ui.add(egui::DragValue::new(seed).prefix("seed: "));
+                if ui.button("Set").clicked() {
+                    multi_seed.tile_seeds.insert((*x, *y), *seed);
+                    changed = true;
+                }
+            });
+        });
+
+    if changed {
+        regenerate.send(RegenerateEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 33;
+
+    fn tile(position: (i32, i32), tile_seed: isize) -> Vec<Vec<f32>> {
+        generate_stitched(Position(position), 2.0, 7, tile_seed, SIZE)
+    }
+
+    #[test]
+    fn edge_seeds_are_symmetric() {
+        let (a, b) = (Position((2, -1)), Position((3, -1)));
+        assert_eq!(edge_seed(a, b), edge_seed(b, a));
+    }
+
+    #[test]
+    fn edge_seeds_are_stable() {
+        // Pinned, other builds have to stitch the same edges.
+        let (a, b) = (Position((2, -1)), Position((3, -1)));
+        assert_eq!(edge_seed(a, b) as u64, 14_033_921_271_322_327_740);
+    }
+
+    #[test]
+    fn neighbors_with_their_own_seeds_share_their_edges() {
+        let last = SIZE - 1;
+        for (seed_a, seed_b) in [(1, 2), (-40, 40), (12345, 7)] {
+            let a = tile((2, -1), seed_a);
+
+            // B to the right of A: A's last column is B's first.
+            let right = tile((3, -1), seed_b);
+            for x in 0..SIZE {
+                assert_eq!(a[x][last], right[x][0]);
+            }
+
+            // B above A: A's first row is B's last.
+            let above = tile((2, 0), seed_b);
+            assert_eq!(a[0], above[last]);
+        }
+    }
+}
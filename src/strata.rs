@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::hash;
+
+/// Horizontal rock layers exposed on cliff faces. Heights are cut into `band_count` layers and
+/// each layer gets its own slightly shifted rock color, but only cells at least as steep as
+/// `min_slope` show them, so flat ground keeps its normal coloring.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct Strata {
+    pub enabled: bool,
+    pub band_count: u32,
+    /// How far each layer's color may drift from the base rock color, as a fraction.
+    pub color_variation: f32,
+    /// Slope, in height per tile width, where the layers start to show.
+    pub min_slope: f32,
+}
+
+impl Default for Strata {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            band_count: 24,
+            color_variation: 0.25,
+            min_slope: 6.0,
+        }
+    }
+}
+
+/// The layer color for a cell at height `f` (0-1) with quantized brightness `value`.
+pub fn strata_color(f: f32, value: u8, strata: &Strata) -> [u8; 3] {
+    // Sandstone, darkening with the cell like the other bands do.
+    const BASE: [f32; 3] = [1.0, 0.7, 0.5];
+
+    let band = (f * strata.band_count as f32) as i32;
+    let mut channel = 0;
+    BASE.map(|base| {
+        // Same hash as the terrain, keyed on the layer so every cell in it matches.
+        let jitter = hash(strata.band_count as isize, band, channel) * 2.0 - 1.0;
+        channel += 1;
+        let shade = base * (1.0 + jitter * strata.color_variation);
+        (value as f32 * shade).clamp(0.0, 255.0) as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coloring::{colorize, ColorSettings},
+        Position,
+    };
+
+    /// A 9x9 tile around height 0 rising by `slope`, in height per tile width, along the rows.
+    fn incline(slope: f32) -> Vec<Vec<f32>> {
+        (0..9)
+            .map(|x| vec![(x as f32 - 4.0) * slope / 8.0; 9])
+            .collect()
+    }
+
+    fn colors(heights: &[Vec<f32>], enabled: bool) -> Vec<u8> {
+        let settings = ColorSettings {
+            strata: Strata {
+                enabled,
+                ..default()
+            },
+            ..default()
+        };
+        colorize(heights, Position((0, 0)), &settings)
+    }
+
+    #[test]
+    fn flat_ground_is_unaffected() {
+        let flat = incline(1.0);
+        assert_eq!(colors(&flat, true), colors(&flat, false));
+    }
+
+    #[test]
+    fn cliffs_show_bands_of_their_own_color() {
+        let steep = incline(Strata::default().min_slope * 2.0);
+        assert_ne!(colors(&steep, true), colors(&steep, false));
+
+        let strata = Strata::default();
+        let band = 1.0 / strata.band_count as f32;
+        let (low, high) = (
+            strata_color(band * 5.2, 200, &strata),
+            strata_color(band * 5.8, 200, &strata),
+        );
+        assert_eq!(low, high);
+        assert_ne!(low, strata_color(band * 6.5, 200, &strata));
+    }
+}
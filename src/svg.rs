@@ -0,0 +1,178 @@
+use std::{error::Error, fmt::Write as _, fs, path::Path};
+
+use crate::{contours, palette::ColorPalette};
+
+/// Width of the contour lines, in cells.
+const LINE_WIDTH: f32 = 0.5;
+
+/// The outline of the part of cell `(x, y)` at or above `height`, corners in order around the
+/// cell. Empty when no area of the cell is above.
+fn clipped_cell(heights: &[Vec<f32>], (x, y): (usize, usize), height: f32) -> Vec<(f32, f32)> {
+    let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+    let mut outline: Vec<(f32, f32)> = Vec::new();
+    // A crossing right at a corner lands on the same point twice.
+    let mut push = |point| {
+        if outline.last() != Some(&point) && outline.first() != Some(&point) {
+            outline.push(point);
+        }
+    };
+    for i in 0..4 {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        let (ha, hb) = (heights[a.0][a.1], heights[b.0][b.1]);
+        if ha >= height {
+            push((a.0 as f32, a.1 as f32));
+        }
+        if (ha >= height) != (hb >= height) {
+            let t = (height - ha) / (hb - ha);
+            push((
+                a.0 as f32 + (b.0 as f32 - a.0 as f32) * t,
+                a.1 as f32 + (b.1 as f32 - a.1 as f32) * t,
+            ));
+        }
+    }
+
+    if outline.len() < 3 {
+        outline.clear();
+    }
+    outline
+}
+
+/// Path data covering every cell at or above `height`. Runs of whole cells along a row merge
+/// into one rectangle to keep the file small.
+fn region_path(heights: &[Vec<f32>], height: f32) -> String {
+    let size = heights.len();
+    let above = |x: usize, y: usize| heights[x][y] >= height;
+    let mut path = String::new();
+
+    for x in 0..size - 1 {
+        let mut y = 0;
+        while y < size - 1 {
+            // Whether both corners in column `y` of this row of cells are above.
+            let column = |y: usize| above(x, y) && above(x + 1, y);
+            if column(y) && column(y + 1) {
+                let start = y;
+                while y < size - 1 && column(y + 1) {
+                    y += 1;
+                }
+                // Cell coordinates map to SVG as (y, x), rows running down like the texture.
+                let _ = write!(path, "M{} {}H{}V{}H{}Z", start, x, y, x + 1, start);
+                continue;
+            }
+
+            let outline = clipped_cell(heights, (x, y), height);
+            for (i, (px, py)) in outline.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                let _ = write!(path, "{}{:.2} {:.2}", command, py, px);
+            }
+            if !outline.is_empty() {
+                path.push('Z');
+            }
+            y += 1;
+        }
+    }
+    path
+}
+
+/// A topographic map of heights after the logistic curve (0-1) as an SVG document: the bands
+/// between the contour `levels` filled with a hypsometric tint from `palette`, with the contours
+/// drawn on top, one `<path>` each. One SVG unit is one cell.
+pub fn to_svg(
+    heights: &[Vec<f32>],
+    levels: &[f32],
+    palette: &ColorPalette,
+    water_level: f32,
+) -> String {
+    let size = heights.len();
+    let cells = size.saturating_sub(1);
+    // The band starting at `low` is tinted with the color of its middle.
+    let tint = |low: f32, high: f32| {
+        let f = ((low + high) / 2.0).clamp(0.0, 1.0);
+        let [r, g, b] = palette.color(f, (f * 0xFF as f32) as u8, f >= water_level);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {cells} {cells}" width="{cells}" height="{cells}">"#
+    );
+    if size < 2 {
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    // Each band is painted over the ones below it.
+    let first = levels.first().copied().unwrap_or(1.0);
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{cells}" height="{cells}" fill="{}"/>"#,
+        tint(0.0, first)
+    );
+    let mut fills = String::new();
+    for (i, &level) in levels.iter().enumerate() {
+        let next = levels.get(i + 1).copied().unwrap_or(1.0);
+        let _ = writeln!(
+            fills,
+            r#"<path fill="{}" d="{}"/>"#,
+            tint(level, next),
+            region_path(heights, level)
+        );
+    }
+    let _ = writeln!(svg, r#"<g stroke="none">"#);
+    svg.push_str(&fills);
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(
+        svg,
+        r#"<g fill="none" stroke="black" stroke-width="{}">"#,
+        LINE_WIDTH
+    );
+    for &level in levels {
+        for contour in contours::trace(heights, level) {
+            let mut data = String::new();
+            for (i, point) in contour.points.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                let _ = write!(data, "{}{:.2} {:.2}", command, point.y, point.x);
+            }
+            let _ = writeln!(
+                svg,
+                r#"<path data-height="{:.3}" d="{}"/>"#,
+                contour.height, data
+            );
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn write_svg(path: &Path, svg: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, svg)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_linear_ramp_has_one_contour_path_per_level() {
+        let size = 9;
+        let ramp: Vec<Vec<f32>> = (0..size)
+            .map(|x| vec![x as f32 / (size - 1) as f32; size])
+            .collect();
+        let levels = [0.2, 0.45, 0.7];
+
+        let svg = to_svg(&ramp, &levels, &ColorPalette::SUMMER, 0.2);
+
+        assert_eq!(svg.matches("<path data-height=").count(), levels.len());
+        for level in levels {
+            assert!(svg.contains(&format!(r#"data-height="{:.3}""#, level)));
+        }
+        // One filled region per band above the background rectangle.
+        assert_eq!(svg.matches("<path fill=").count(), levels.len());
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    fmt::{Debug, Write as _},
+    fs::File,
+    io::{LineWriter, Write as _},
+    sync::Mutex,
+};
+
+use bevy::{log::BoxedSubscriber, prelude::*};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    Layer,
+};
+
+/// Tracing target of the per-generation records.
+pub const TARGET: &str = "generation";
+
+/// Where the records are written, in the working directory.
+const LOG_PATH: &str = "generation.log";
+
+/// Whether the app was started with `--log-generation`.
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == "--log-generation")
+}
+
+/// Whether generations are logged, decided once at startup. Logging needs the tile statistics,
+/// so they are only computed while this is on.
+#[derive(Resource, Debug)]
+pub struct Telemetry(pub bool);
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self(requested())
+    }
+}
+
+/// Hook for `LogPlugin::update_subscriber` that adds the generation log file next to Bevy's own
+/// console output.
+pub fn add_log_file(subscriber: BoxedSubscriber) -> BoxedSubscriber {
+    match File::create(LOG_PATH) {
+        Ok(file) => Box::new(subscriber.with(GenerationLog(Mutex::new(LineWriter::new(file))))),
+        Err(err) => {
+            eprintln!("Failed to create {}: {}", LOG_PATH, err);
+            subscriber
+        }
+    }
+}
+
+/// Writes every event with the [`TARGET`] target as one line of `name=value` pairs.
+struct GenerationLog(Mutex<LineWriter<File>>);
+
+impl<S: Subscriber> Layer<S> for GenerationLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != TARGET {
+            return;
+        }
+
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        if let Ok(mut file) = self.0.lock() {
+            // A failed write must not take the app down with it.
+            let _ = writeln!(file, "{}", fields.0.trim_end());
+        }
+    }
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = write!(self.0, "{}={:?} ", field.name(), value);
+    }
+}
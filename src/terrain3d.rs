@@ -0,0 +1,198 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{coloring::logistic, slope};
+
+/// Most cells per side a terrain mesh is built with. Larger tiles are sampled every few cells, so
+/// a 1025 tile doesn't turn into a million vertices.
+const MAX_MESH_CELLS: usize = 256;
+/// Radians the view turns per pixel dragged.
+const ORBIT_SPEED: f32 = 0.005;
+/// Steepest the view can tilt away from looking straight down, in radians.
+const MAX_TILT: f32 = 1.4;
+/// How much one wheel notch moves the camera towards what it looks at.
+const WHEEL_ZOOM: f32 = 0.9;
+
+/// Displaces the tiles into real 3D terrain instead of flat textured quads, lit by the first
+/// [`Lighting`](crate::lighting::Lighting) light. Dragging with the right mouse button orbits the
+/// camera and the wheel zooms.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct TerrainMesh {
+    pub enabled: bool,
+    /// Height of the highest possible peak above the sea, in tile widths.
+    pub vertical_scale: f32,
+}
+
+impl Default for TerrainMesh {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vertical_scale: 0.15,
+        }
+    }
+}
+
+/// A grid mesh of a tile's raw heights, over the same unit square as
+/// [`tile_mesh`](crate::alignment::tile_mesh) and with the same texture mapping. Heights go
+/// through the logistic curve and water is flattened to its surface.
+pub fn heightmap_to_mesh(
+    heights: &[Vec<f32>],
+    vertical_scale: f32,
+    water_level: f32,
+    aligned: bool,
+) -> Mesh {
+    let size = heights.len();
+    let stride = ((size - 1) / MAX_MESH_CELLS).max(1);
+    let samples: Vec<usize> = (0..size).step_by(stride).collect();
+    let cells = (samples.len() - 1) as f32;
+
+    let elevations: Vec<Vec<f32>> = samples
+        .iter()
+        .map(|&x| {
+            samples
+                .iter()
+                .map(|&y| (logistic(heights[x][y]).max(water_level) - water_level) * vertical_scale)
+                .collect()
+        })
+        .collect();
+    // Both the elevations and the gradient are in tile widths, so the normals need no relief.
+    let normals = slope::normals(&elevations, 1.0);
+
+    let texels = size as f32;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    for (x, row) in elevations.iter().enumerate() {
+        for (y, &z) in row.iter().enumerate() {
+            // Rows run along -Y and columns along +X, like `Position::cell_to_world`.
+            let (u, v) = (y as f32 / cells, x as f32 / cells);
+            positions.push([u - 0.5, 0.5 - v, z]);
+            uvs.push(if aligned {
+                [u, v].map(|t| (0.5 + t * (texels - 1.0)) / texels)
+            } else {
+                [u, v]
+            });
+        }
+    }
+
+    let side = samples.len() as u32;
+    let mut indices = Vec::new();
+    for x in 0..side - 1 {
+        for y in 0..side - 1 {
+            let (top_left, bottom_left) = (x * side + y, (x + 1) * side + y);
+            indices.extend([top_left, bottom_left, top_left + 1]);
+            indices.extend([top_left + 1, bottom_left, bottom_left + 1]);
+        }
+    }
+
+    let vertices = positions.len();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        normals
+            .into_iter()
+            .flatten()
+            .map(Vec3::to_array)
+            .collect::<Vec<_>>(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_TANGENT,
+        vec![[1.0, 0.0, 0.0, 1.0]; vertices],
+    )
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Where the camera's view meets the tile plane (z = 0), if it looks down at all.
+fn focus(transform: &Transform) -> Option<Vec3> {
+    let forward = *transform.forward();
+    (forward.z < 0.0)
+        .then(|| transform.translation - forward * (transform.translation.z / forward.z))
+}
+
+/// Orbits the camera around the point it looks at while the terrain is 3D, and puts it back
+/// above the tiles looking straight down once it's flat again.
+pub fn orbit_camera(
+    mut contexts: EguiContexts,
+    terrain: Res<TerrainMesh>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let drag: Vec2 = motion.read().map(|event| event.delta).sum();
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    if terrain.is_changed() && !terrain.enabled {
+        reset_view(&mut transform);
+        return;
+    }
+    if !terrain.enabled || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(focus) = focus(&transform) else {
+        return;
+    };
+
+    if mouse.pressed(MouseButton::Right) && drag != Vec2::ZERO {
+        transform.rotate_around(focus, Quat::from_rotation_z(-drag.x * ORBIT_SPEED));
+
+        let tilt = transform.back().angle_between(Vec3::Z);
+        let pitch = (tilt + drag.y * ORBIT_SPEED).clamp(0.0, MAX_TILT) - tilt;
+        let right = *transform.right();
+        transform.rotate_around(focus, Quat::from_axis_angle(right, pitch));
+    }
+
+    if scroll != 0.0 {
+        let offset = transform.translation - focus;
+        transform.translation = focus + offset * WHEEL_ZOOM.powf(scroll);
+    }
+}
+
+/// Looks straight down at the same spot from the same distance.
+fn reset_view(transform: &mut Transform) {
+    let Some(focus) = focus(transform) else {
+        transform.rotation = Quat::IDENTITY;
+        return;
+    };
+    let distance = transform.translation.distance(focus);
+    *transform = Transform::from_translation(focus + Vec3::Z * distance);
+}
+
+pub fn terrain_mesh_ui(
+    mut contexts: EguiContexts,
+    mut terrain: ResMut<TerrainMesh>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    // Every change rebuilds the meshes of all tiles, so only write back real changes.
+    let mut new_terrain = *terrain;
+
+    egui::Window::new("3D Terrain")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut new_terrain.enabled, "Displace Tiles")
+                .on_hover_text("Drag with the right mouse button to orbit, scroll to zoom.");
+            ui.add(
+                egui::Slider::new(&mut new_terrain.vertical_scale, 0.01..=1.0)
+                    .prefix("Vertical Scale: "),
+            );
+            if ui.button("Look Straight Down").clicked() {
+                if let Ok(mut transform) = camera_query.get_single_mut() {
+                    reset_view(&mut transform);
+                }
+            }
+        });
+
+    terrain.set_if_neq(new_terrain);
+}
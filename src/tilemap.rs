@@ -0,0 +1,109 @@
+use std::{error::Error, fmt::Write as _, fs, path::Path};
+
+use crate::coloring::{logistic, ColorSettings};
+
+/// How far above the water level, in height after the logistic curve, the beaches reach.
+const BEACH_HEIGHT: f32 = 0.03;
+
+/// The discrete terrain types of an exported tilemap. The discriminants are the ids written to
+/// the file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TileType {
+    Water = 0,
+    Sand = 1,
+    Grass = 2,
+    Forest = 3,
+    Rock = 4,
+    Snow = 5,
+}
+
+impl TileType {
+    /// Classifies a height after the logistic curve with the same bands as
+    /// [`land_color`](crate::coloring::land_color). Its green band is split evenly into grass
+    /// and forest, with a strip of sand along the water.
+    pub fn classify(f: f32, settings: &ColorSettings) -> Self {
+        let water_level = settings.water_level.0;
+        let snow_line = if settings.snow.enabled {
+            settings.snow.snow_line
+        } else {
+            0.9
+        };
+
+        match f {
+            f if f < water_level => TileType::Water,
+            f if f < water_level + BEACH_HEIGHT => TileType::Sand,
+            f if f < (water_level + 0.65) / 2.0 => TileType::Grass,
+            f if f < 0.65 => TileType::Forest,
+            f if f < snow_line => TileType::Rock,
+            _ => TileType::Snow,
+        }
+    }
+}
+
+/// Downsamples a tile to `resolution` by `resolution` cells and classifies each one into a
+/// [`TileType`] id. Every cell averages the heights it covers, laid out like the tile texture.
+pub fn to_tilemap(
+    heights: &[Vec<f32>],
+    settings: &ColorSettings,
+    resolution: usize,
+) -> Vec<Vec<u32>> {
+    let size = heights.len();
+    let resolution = resolution.clamp(1, size.max(1));
+    // The cells covered by tilemap row or column `i`, never empty.
+    let block = |i: usize| {
+        let start = i * size / resolution;
+        start..((i + 1) * size / resolution).max(start + 1)
+    };
+
+    (0..resolution)
+        .map(|x| {
+            (0..resolution)
+                .map(|y| {
+                    let (rows, columns) = (block(x), block(y));
+                    let count = rows.len() * columns.len();
+                    let sum: f32 = heights[rows]
+                        .iter()
+                        .flat_map(|row| &row[columns.clone()])
+                        .sum();
+                    TileType::classify(logistic(sum / count as f32), settings) as u32
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Writes a tilemap as CSV, one line per row. This is the layout Tiled reads for a CSV layer,
+/// after adding the tileset's first gid to every id.
+pub fn write_csv(path: &Path, tilemap: &[Vec<u32>]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut csv = String::new();
+    for row in tilemap {
+        let ids: Vec<String> = row.iter().map(u32::to_string).collect();
+        writeln!(csv, "{}", ids.join(","))?;
+    }
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_heightmap, Position};
+
+    #[test]
+    fn tilemaps_have_the_requested_size_and_valid_ids() {
+        let heights = generate_heightmap(Position((0, 0)), 2.0, 4, 65);
+        let settings = ColorSettings::default();
+        for resolution in [4, 13, 64] {
+            let tilemap = to_tilemap(&heights, &settings, resolution);
+            assert_eq!(tilemap.len(), resolution);
+            for row in &tilemap {
+                assert_eq!(row.len(), resolution);
+                assert!(row.iter().all(|&id| id <= TileType::Snow as u32));
+            }
+        }
+    }
+}
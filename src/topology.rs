@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hash;
+
+/// How the generated tile's edges relate to each other.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Topology {
+    /// An ordinary square that continues into its neighboring tiles.
+    #[default]
+    Planar,
+    /// The square is the surface of a torus, so opposite edges are identical and the tile
+    /// repeats seamlessly in every direction. Neighboring tiles don't line up with it.
+    Torus,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 2] = [Topology::Planar, Topology::Torus];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Planar => "Planar",
+            Topology::Torus => "Torus",
+        }
+    }
+}
+
+/// Diamond-square on a torus. Every neighbor lookup wraps around, so there is a single corner
+/// cell shared by all four corners, and the edges are generated once and shared by both sides.
+/// The last row and column of the result repeat the first ones.
+pub fn generate_torus(roughness: f32, seed: isize, image_size: usize) -> Vec<Vec<f32>> {
+    let period = image_size - 1;
+    let mut heightmap = vec![vec![0.0; period]; period];
+    let noise = |x: usize, y: usize| hash(seed, x as i32, y as i32) * 2.0 - 1.0;
+    let wrap = |i: usize| i % period;
+
+    heightmap[0][0] = hash(seed, 0, 0);
+
+    let mut chunk_size = period;
+    let mut roughness = roughness;
+    while chunk_size > 1 {
+        let half = chunk_size / 2;
+
+        // square step
+        for x in (0..period).step_by(chunk_size) {
+            for y in (0..period).step_by(chunk_size) {
+                let (x2, y2) = (wrap(x + chunk_size), wrap(y + chunk_size));
+                let average =
+                    (heightmap[x][y] + heightmap[x2][y] + heightmap[x][y2] + heightmap[x2][y2])
+                        / 4.0;
+                heightmap[x + half][y + half] = average + noise(x + half, y + half) * roughness;
+            }
+        }
+
+        // diamond step
+        for x in (0..period).step_by(half) {
+            let start = if (x / half) % 2 == 0 { half } else { 0 };
+            for y in (start..period).step_by(chunk_size) {
+                let average = (heightmap[wrap(x + period - half)][y]
+                    + heightmap[wrap(x + half)][y]
+                    + heightmap[x][wrap(y + period - half)]
+                    + heightmap[x][wrap(y + half)])
+                    / 4.0;
+                heightmap[x][y] = average + noise(x, y) * roughness;
+            }
+        }
+
+        chunk_size = half;
+        roughness /= 2.0;
+    }
+
+    (0..image_size)
+        .map(|x| {
+            (0..image_size)
+                .map(|y| heightmap[wrap(x)][wrap(y)])
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opposite_edges_are_identical() {
+        let heights = generate_torus(2.0, 21, 33);
+        let last = heights.len() - 1;
+
+        assert_eq!(
+            heights[0].iter().map(|h| h.to_bits()).collect::<Vec<_>>(),
+            heights[last]
+                .iter()
+                .map(|h| h.to_bits())
+                .collect::<Vec<_>>(),
+        );
+        for row in &heights {
+            assert_eq!(row[0].to_bits(), row[last].to_bits());
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{Cursor, Position, Tile, TileHeights, TileLevel};
+
+/// Tints the terrain by what an observer standing on one cell can see: visible cells turn green,
+/// cells hidden behind terrain turn red.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct Viewshed {
+    pub enabled: bool,
+    /// The tile and cell the observer stands on.
+    pub observer: Option<(Position, (usize, usize))>,
+    /// Eye height above the ground, in the same 0-1 units as the heights after the logistic
+    /// curve.
+    pub observer_height: f32,
+}
+
+impl Default for Viewshed {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            observer: None,
+            observer_height: 0.02,
+        }
+    }
+}
+
+/// Which cells have a clear line of sight to an eye `observer_height` above `observer`.
+///
+/// `heights` are after the logistic curve (0-1) and distances are measured in tile widths, so a
+/// height of 1 is as tall as the tile is wide. A ray is cast from the observer to every cell on
+/// the edge of the tile, stepping one cell at a time along its longer axis. A cell on the ray is
+/// visible when the angle up to it is at least as steep as to everything before it.
+pub fn viewshed(
+    heights: &[Vec<f32>],
+    observer: (usize, usize),
+    observer_height: f32,
+) -> Vec<Vec<bool>> {
+    let size = heights.len();
+    let mut visible = vec![vec![false; size]; size];
+    let Some(&ground) = heights.get(observer.0).and_then(|row| row.get(observer.1)) else {
+        return visible;
+    };
+    visible[observer.0][observer.1] = true;
+
+    let eye = ground + observer_height;
+    let cells = (size - 1).max(1) as f32;
+    let (ox, oy) = (observer.0 as f32, observer.1 as f32);
+
+    let last = size - 1;
+    let edge = (0..size)
+        .flat_map(|i| [(i, 0), (i, last), (0, i), (last, i)])
+        .filter(|&target| target != observer);
+
+    for (tx, ty) in edge {
+        let (dx, dy) = (tx as f32 - ox, ty as f32 - oy);
+        let steps = dx.abs().max(dy.abs()) as usize;
+        let mut steepest = f32::NEG_INFINITY;
+
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = (
+                (ox + dx * t).round() as usize,
+                (oy + dy * t).round() as usize,
+            );
+
+            let distance = (dx * t).hypot(dy * t) / cells;
+            let angle = (heights[x][y] - eye) / distance;
+            if angle >= steepest {
+                visible[x][y] = true;
+                steepest = angle;
+            }
+        }
+    }
+
+    visible
+}
+
+pub fn viewshed_ui(
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+    mut placing: Local<bool>,
+    mut viewshed: ResMut<Viewshed>,
+    cursor: Cursor,
+    tiles: Query<(&Position, &TileHeights, &TileLevel), With<Tile>>,
+) {
+    let mut new_viewshed = *viewshed;
+    let over_ui = contexts.ctx_mut().wants_pointer_input();
+
+    // Clicking on a tile moves the observer there.
+    if *placing && !over_ui && cursor.buttons.just_pressed(MouseButton::Left) {
+        let point = cursor.world_position();
+        let clicked = tiles.iter().filter(|(_, _, level)| level.0 == 0).find_map(
+            |(&position, heights, _)| {
+                let cell = position.world_to_cell(heights.0.len(), point?)?;
+                Some((position, cell))
+            },
+        );
+        if let Some(observer) = clicked {
+            new_viewshed.observer = Some(observer);
+            new_viewshed.enabled = true;
+            *placing = false;
+        }
+    }
+
+    // Mark the observer on the tile.
+    if let Some((position, cell)) = new_viewshed.observer.filter(|_| new_viewshed.enabled) {
+        if let Some((_, heights, _)) = tiles.iter().find(|(&p, _, _)| p == position) {
+            let point = position.cell_to_world(heights.0.len(), cell);
+            gizmos.circle(point.extend(0.01), Direction3d::Z, 0.01, Color::YELLOW);
+        }
+    }
+
+    egui::Window::new("Viewshed")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut new_viewshed.enabled, "Show Viewshed");
+            ui.checkbox(&mut *placing, "Place Observer (click on the tile)");
+            ui.add(
+                egui::Slider::new(&mut new_viewshed.observer_height, 0.0..=0.5)
+                    .prefix("Observer Height: "),
+            );
+            match new_viewshed.observer {
+                Some((position, (x, y))) => ui.label(format!(
+                    "Observer: cell ({}, {}) of tile {:?}",
+                    x, y, position.0
+                )),
+                None => ui.label("No observer placed yet."),
+            };
+        });
+
+    viewshed.set_if_neq(new_viewshed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_behind_a_ridge_are_hidden() {
+        let size = 17;
+        // Flat ground with a tall ridge across the whole tile at row 8.
+        let heights: Vec<Vec<f32>> = (0..size)
+            .map(|x| vec![if x == 8 { 0.9 } else { 0.1 }; size])
+            .collect();
+
+        let visible = viewshed(&heights, (2, 8), 0.02);
+
+        assert!(visible[2][8]);
+        assert!(
+            visible[5][8],
+            "the ground in front of the ridge is in sight"
+        );
+        assert!(visible[8][8], "the ridge itself is in sight");
+        for (x, row) in visible.iter().enumerate().skip(9) {
+            assert!(!row[8], "cell {} behind the ridge is hidden", x);
+        }
+    }
+}
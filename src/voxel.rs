@@ -0,0 +1,139 @@
+use std::{error::Error, fs, path::Path};
+
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+
+use crate::climate::Biome;
+
+/// How many blocks of dirt lie under a grass or snow surface before the stone starts.
+const DIRT_DEPTH: u16 = 3;
+
+/// The blocks a column can be topped with. The dirt and water of the expanded columns are
+/// implied, see [`VoxelGrid`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+pub enum Block {
+    Stone,
+    Grass,
+    Sand,
+    Snow,
+}
+
+/// The top of a voxel column.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+pub struct Column {
+    /// Number of solid blocks, the highest one at `height - 1`.
+    pub height: u16,
+    /// The highest solid block.
+    pub surface: Block,
+}
+
+/// Terrain as voxel columns, saved as RON. Only the surface of every column is stored; a
+/// column expands to blocks from the bottom up as:
+///
+/// - stone up to the subsurface,
+/// - dirt for the last `dirt_depth` blocks below a grass or snow surface (sand and stone surfaces sit
+///   right on stone),
+/// - the `surface` block at `height - 1`,
+/// - water from `height` up to `sea_level - 1` if the column is below sea level,
+/// - air above.
+///
+/// `columns[x][y]` is laid out like the tile texture, `x` running down its rows.
+#[derive(Debug, Serialize)]
+pub struct VoxelGrid {
+    pub max_height: u16,
+    pub sea_level: u16,
+    pub dirt_depth: u16,
+    pub columns: Vec<Vec<Column>>,
+}
+
+impl VoxelGrid {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Keep every row of columns on one line.
+        let config = PrettyConfig::new().compact_arrays(true);
+        fs::write(path, ron::ser::to_string_pretty(self, config)?)?;
+        Ok(())
+    }
+}
+
+/// The surface block of a column, from its biome and height (after the logistic curve) with the
+/// same rock and snow bands as [`land_color`](crate::coloring::land_color).
+fn surface(biome: Biome, f: f32, underwater: bool) -> Block {
+    if underwater {
+        return Block::Sand;
+    }
+
+    match (f, biome) {
+        (f, _) if f >= 0.9 => Block::Snow,
+        (f, _) if f >= 0.65 => Block::Stone,
+        (_, Biome::Tundra) => Block::Snow,
+        (_, Biome::ColdDesert | Biome::SubtropicalDesert) => Block::Sand,
+        _ => Block::Grass,
+    }
+}
+
+/// Quantizes heights (after the logistic curve, 0-1) to columns of up to `max_height` blocks
+/// and picks each surface block by biome. Cells below `water_level` are flooded up to the
+/// quantized water level.
+pub fn to_voxels(
+    heights: &[Vec<f32>],
+    biomes: &[Vec<Biome>],
+    max_height: u16,
+    water_level: f32,
+) -> VoxelGrid {
+    let quantize = |f: f32| ((f.clamp(0.0, 1.0) * max_height as f32).round() as u16).max(1);
+    let sea_level = quantize(water_level);
+
+    let columns = heights
+        .iter()
+        .zip(biomes)
+        .map(|(heights, biomes)| {
+            heights
+                .iter()
+                .zip(biomes)
+                .map(|(&f, &biome)| {
+                    let height = quantize(f);
+                    Column {
+                        height,
+                        surface: surface(biome, f, height < sea_level),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    VoxelGrid {
+        max_height,
+        sea_level,
+        dirt_depth: DIRT_DEPTH,
+        columns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_as_high_as_the_quantized_heights() {
+        let heights = vec![vec![0.0, 0.1], vec![0.5, 0.99]];
+        let biomes = vec![vec![Biome::TemperateForest; 2]; 2];
+
+        let voxels = to_voxels(&heights, &biomes, 100, 0.2);
+
+        let columns: Vec<Vec<u16>> = voxels
+            .columns
+            .iter()
+            .map(|row| row.iter().map(|column| column.height).collect())
+            .collect();
+        // Every column keeps at least its surface block.
+        assert_eq!(columns, vec![vec![1, 10], vec![50, 99]]);
+        assert_eq!(voxels.sea_level, 20);
+        assert_eq!(voxels.columns[0][1].surface, Block::Sand);
+        assert_eq!(voxels.columns[1][0].surface, Block::Grass);
+        assert_eq!(voxels.columns[1][1].surface, Block::Snow);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{error::Error, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+/// Tints the terrain by whether it can be walked on: dry cells flatter than `max_slope` are
+/// walkable and turn green, steep or underwater cells turn red.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct Walkability {
+    pub enabled: bool,
+    /// Slope, in height per tile width, from which cells are too steep to walk.
+    pub max_slope: f32,
+}
+
+impl Default for Walkability {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_slope: 3.0,
+        }
+    }
+}
+
+/// Which cells are walkable, from their slopes (see [`slope::slope`](crate::slope::slope)) and
+/// heights after the logistic curve, classified with the same water level as the coloring.
+pub fn walkable_mask(
+    heights: &[Vec<f32>],
+    slopes: &[Vec<f32>],
+    water_level: f32,
+    max_slope: f32,
+) -> Vec<Vec<bool>> {
+    heights
+        .iter()
+        .zip(slopes)
+        .map(|(heights, slopes)| {
+            heights
+                .iter()
+                .zip(slopes)
+                .map(|(&f, &slope)| f >= water_level && slope < max_slope)
+                .collect()
+        })
+        .collect()
+}
+
+/// Writes the mask as a black and white PNG, white where walkable, laid out like the tile
+/// texture.
+pub fn write_png(path: &Path, mask: &[Vec<bool>]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let size = mask.len() as u32;
+    let pixels = mask
+        .iter()
+        .flatten()
+        .map(|&walkable| if walkable { 0xFF } else { 0 })
+        .collect();
+    let image = image::GrayImage::from_raw(size, size, pixels).ok_or("mask size mismatch")?;
+    image.save(path)?;
+    Ok(())
+}
+
+pub fn walkability_ui(ui: &mut egui::Ui, walkability: &mut Walkability) {
+    ui.checkbox(&mut walkability.enabled, "Walkability Overlay");
+    ui.add(
+        egui::Slider::new(&mut walkability.max_slope, 0.5..=20.0).prefix("Max Walkable Slope: "),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steep_or_underwater_cells_are_not_walkable() {
+        let heights = vec![vec![0.5, 0.5], vec![0.1, 0.5]];
+        let slopes = vec![vec![1.0, 5.0], vec![1.0, 2.9]];
+
+        let mask = walkable_mask(&heights, &slopes, 0.2, 3.0);
+
+        assert_eq!(mask, vec![vec![true, false], vec![false, true]]);
+    }
+}
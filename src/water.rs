@@ -0,0 +1,147 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use bevy_inspector_egui::egui;
+
+use crate::{
+    coastline,
+    coloring::{logistic, ColorSettings, Coloring},
+    material::{tiling_texture, TileMaterial},
+    slope, topology, TileHeights,
+};
+
+/// Side length of the generated water normal map, in pixels.
+const NORMALS_SIZE: usize = 64;
+
+/// Ripples the water cells with two normal maps scrolling across each other, so the sea moves
+/// instead of sitting flat. Only the cells the terrain colors as water are affected, so the
+/// waves stop exactly at the coastline.
+#[derive(Resource, Debug, PartialEq, Clone)]
+pub struct WaterAnimation {
+    pub enabled: bool,
+    /// How fast the waves scroll, in world units per second.
+    pub speed: f32,
+    /// How often the normal map repeats per world unit.
+    pub tiling: f32,
+    pub normals: Handle<Image>,
+}
+
+impl FromWorld for WaterAnimation {
+    fn from_world(world: &mut World) -> Self {
+        let normals = world
+            .resource_mut::<Assets<Image>>()
+            .add(water_normals(NORMALS_SIZE));
+
+        Self {
+            enabled: false,
+            speed: 0.02,
+            tiling: 8.0,
+            normals,
+        }
+    }
+}
+
+/// A tangent-space normal map of gentle swells. The swells come from a torus heightmap, so the
+/// map repeats without seams.
+fn water_normals(size: usize) -> Image {
+    let heights = topology::generate_torus(2.0, 0x3A7E5, size + 1);
+    let normals = slope::normals(&heights, 0.05);
+
+    let data = normals
+        .iter()
+        .take(size)
+        .flat_map(|row| row.iter().take(size))
+        .flat_map(|normal| {
+            let [r, g, b] = (*normal * 0.5 + 0.5).to_array().map(|c| (c * 255.0) as u8);
+            [r, g, b, 0xFF]
+        })
+        .collect();
+
+    tiling_texture(size as u32, data)
+}
+
+/// Which cells of a tile are water, with the same water level and coastline smoothing the
+/// coloring uses. Laid out like the tile texture.
+fn water_mask(heights: &[Vec<f32>], settings: &ColorSettings) -> Vec<u8> {
+    let heights: Vec<Vec<f32>> = heights
+        .iter()
+        .map(|row| row.iter().map(|&f| logistic(f)).collect())
+        .collect();
+
+    let mut land = coastline::land_mask(&heights, settings.water_level.0);
+    if settings.coastline.enabled {
+        coastline::smooth_coastline(&mut land, settings.coastline.iterations);
+    }
+
+    land.into_iter()
+        .flatten()
+        .map(|land| if land { 0x00 } else { 0xFF })
+        .collect()
+}
+
+/// Keeps every tile's water mask and the wave settings in sync with the terrain and
+/// [`WaterAnimation`].
+pub fn update_water(
+    water: Res<WaterAnimation>,
+    coloring: Coloring,
+    tiles: Query<(Ref<TileHeights>, &Handle<TileMaterial>)>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let recolored = coloring.is_changed();
+    let settings = coloring.settings();
+
+    for (heights, handle) in tiles.iter() {
+        let remask = water.is_changed() || recolored || heights.is_changed();
+        if !remask {
+            continue;
+        }
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+
+        let extension = &mut material.extension;
+        extension.water_strength = if water.enabled { 1.0 } else { 0.0 };
+        extension.water_speed = water.speed;
+        extension.water_tiling = water.tiling;
+        extension.water_normals = Some(water.normals.clone());
+
+        // The masks are only worth building while the animation shows them.
+        if !water.enabled {
+            continue;
+        }
+
+        let mask = water_mask(&heights.0, &settings);
+        match extension
+            .water_mask
+            .as_ref()
+            .and_then(|handle| images.get_mut(handle))
+        {
+            Some(image) => image.data = mask,
+            None => {
+                let size = heights.0.len() as u32;
+                extension.water_mask = Some(images.add(Image::new(
+                    Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    mask,
+                    TextureFormat::R8Unorm,
+                    RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+                )));
+            }
+        }
+    }
+}
+
+pub fn water_ui(ui: &mut egui::Ui, water: &mut WaterAnimation) {
+    ui.checkbox(&mut water.enabled, "Animated Water");
+    ui.add(egui::Slider::new(&mut water.speed, 0.0..=0.2).prefix("Wave Speed: "));
+    ui.add(egui::Slider::new(&mut water.tiling, 1.0..=64.0).prefix("Wave Tiling: "));
+}
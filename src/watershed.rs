@@ -0,0 +1,129 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+};
+
+/// The eight cells around `(x, y)` that are inside a `size` by `size` grid.
+pub fn neighbors(size: usize, (x, y): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .filter(|&offset| offset != (0, 0))
+        .filter_map(move |(dx, dy)| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            (nx < size && ny < size).then_some((nx, ny))
+        })
+}
+
+/// A cell waiting to be flooded, ordered so the lowest pops first from a max-heap.
+#[derive(PartialEq)]
+struct Flood {
+    height: f32,
+    cell: (usize, usize),
+}
+
+impl Eq for Flood {}
+
+impl Ord for Flood {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties break on the cell so the flood order, and with it the result, is deterministic.
+        other
+            .height
+            .partial_cmp(&self.height)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for Flood {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Splits the terrain into drainage basins: every local minimum (a flat one counts once) seeds a
+/// region, and regions grow outwards in order of height until they meet along the ridges. Returns
+/// the region id of every cell, numbered from 0.
+pub fn watershed(heights: &[Vec<f32>]) -> Vec<Vec<u32>> {
+    const UNLABELED: u32 = u32::MAX;
+
+    let size = heights.len();
+    let height = |(x, y): (usize, usize)| heights[x][y];
+    let is_minimum = |cell| neighbors(size, cell).all(|n| height(n) >= height(cell));
+
+    let mut labels = vec![vec![UNLABELED; size]; size];
+    let mut queue = BinaryHeap::new();
+    let mut next_label = 0;
+
+    for x in 0..size {
+        for y in 0..size {
+            if labels[x][y] != UNLABELED || !is_minimum((x, y)) {
+                continue;
+            }
+
+            // Flat minima spread over several cells; give the whole flat one label.
+            let mut flat = VecDeque::from([(x, y)]);
+            labels[x][y] = next_label;
+            while let Some(cell) = flat.pop_front() {
+                queue.push(Flood {
+                    height: height(cell),
+                    cell,
+                });
+                for (nx, ny) in neighbors(size, cell) {
+                    if labels[nx][ny] == UNLABELED && height((nx, ny)) == height(cell) {
+                        labels[nx][ny] = next_label;
+                        flat.push_back((nx, ny));
+                    }
+                }
+            }
+
+            next_label += 1;
+        }
+    }
+
+    while let Some(Flood { cell, .. }) = queue.pop() {
+        let label = labels[cell.0][cell.1];
+        for (nx, ny) in neighbors(size, cell) {
+            if labels[nx][ny] == UNLABELED {
+                labels[nx][ny] = label;
+                queue.push(Flood {
+                    height: height((nx, ny)),
+                    cell: (nx, ny),
+                });
+            }
+        }
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_valleys_make_two_regions_split_at_the_ridge() {
+        // Valleys along columns 2 and 6 with the ridge between them on column 4.
+        let heights: Vec<Vec<f32>> = (0..9)
+            .map(|_| {
+                (0..9)
+                    .map(|y: i32| (y - 2).abs().min((y - 6).abs()) as f32)
+                    .collect()
+            })
+            .collect();
+
+        let labels = watershed(&heights);
+
+        let mut ids: Vec<u32> = labels.iter().flatten().copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 2);
+
+        let (left, right) = (labels[0][0], labels[0][8]);
+        assert_ne!(left, right);
+        for row in &labels {
+            assert!(row[..4].iter().all(|&id| id == left));
+            assert!(row[5..].iter().all(|&id| id == right));
+        }
+    }
+}